@@ -0,0 +1,188 @@
+//! Artifact dependencies: depending on the binaries or C libraries a package
+//! builds, rather than on its Rust library.
+
+use std::fmt;
+
+use crate::{CargoToml, DeclaredDependency, Dependency, DependencyDetail, Error, Span, Spanned};
+
+/// An artifact kind from a dependency's `artifact` key.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum ArtifactKind {
+    /// `"bin"`: every binary of the package.
+    AllBinaries,
+    /// `"bin:<name>"`: one binary.
+    Binary(String),
+    /// `"cdylib"`: the C-compatible dynamic library.
+    Cdylib,
+    /// `"staticlib"`: the C-compatible static library.
+    Staticlib,
+}
+
+impl ArtifactKind {
+    /// Parse an `artifact` value, like `"bin:foo"`.
+    pub fn parse(value: &str) -> Result<Self, Error> {
+        Self::parse_at(value, None)
+    }
+
+    fn parse_at(value: &str, span: Option<Span>) -> Result<Self, Error> {
+        let invalid = |reason: &str| Error::InvalidArtifact {
+            value: value.to_owned(),
+            reason: reason.to_owned(),
+            span,
+        };
+        match value {
+            "bin" => Ok(ArtifactKind::AllBinaries),
+            "cdylib" => Ok(ArtifactKind::Cdylib),
+            "staticlib" => Ok(ArtifactKind::Staticlib),
+            _ => match value.strip_prefix("bin:") {
+                Some("") => Err(invalid("the binary name is empty")),
+                Some(name) => Ok(ArtifactKind::Binary(name.to_owned())),
+                None => Err(invalid(
+                    "expected `bin`, `bin:<name>`, `cdylib` or `staticlib`",
+                )),
+            },
+        }
+    }
+
+    /// Whether this kind includes the binary `name`.
+    pub fn includes_binary(&self, name: &str) -> bool {
+        match self {
+            ArtifactKind::AllBinaries => true,
+            ArtifactKind::Binary(binary) => binary == name,
+            ArtifactKind::Cdylib | ArtifactKind::Staticlib => false,
+        }
+    }
+}
+
+impl fmt::Display for ArtifactKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ArtifactKind::AllBinaries => f.write_str("bin"),
+            ArtifactKind::Binary(name) => write!(f, "bin:{name}"),
+            ArtifactKind::Cdylib => f.write_str("cdylib"),
+            ArtifactKind::Staticlib => f.write_str("staticlib"),
+        }
+    }
+}
+
+impl DependencyDetail {
+    /// The parsed `artifact` kinds, empty for a regular dependency.
+    ///
+    /// Fails on the first unknown kind, with its span.
+    pub fn artifact_kinds(&self) -> Result<Vec<Spanned<ArtifactKind>>, Error> {
+        self.artifact
+            .iter()
+            .flat_map(|artifact| artifact.values())
+            .map(|value| {
+                Ok(Spanned {
+                    value: ArtifactKind::parse_at(&value.value, value.span)?,
+                    span: value.span,
+                })
+            })
+            .collect()
+    }
+}
+
+/// A dependency with an `artifact` key.
+///
+/// Yielded by [`CargoToml::artifact_dependencies`].
+#[derive(Debug, Clone)]
+pub struct ArtifactDependency<'a> {
+    /// The declaration, with the table it appears in.
+    pub declared: DeclaredDependency<'a>,
+    /// The artifacts depended on.
+    pub kinds: Vec<Spanned<ArtifactKind>>,
+    /// Whether the dependency's library is depended on too (`lib = true`).
+    pub lib: bool,
+    /// The target triple the artifacts are built for, if not the host (for
+    /// build dependencies) or the target being built.
+    pub target: Option<&'a str>,
+}
+
+impl CargoToml {
+    /// Every artifact dependency, in [`CargoToml::all_dependencies`] order.
+    ///
+    /// Build dependencies on binaries are the tools a build script runs.
+    /// `{ workspace = true }` dependencies are not resolved, so artifact keys
+    /// in `[workspace.dependencies]` are not seen.
+    ///
+    /// Fails on an unknown artifact kind.
+    pub fn artifact_dependencies(&self) -> Result<Vec<ArtifactDependency<'_>>, Error> {
+        let mut found = Vec::new();
+        for declared in self.all_dependencies() {
+            let Dependency::Detailed(detail) = declared.dependency else {
+                continue;
+            };
+            if detail.artifact.is_none() {
+                continue;
+            }
+            found.push(ArtifactDependency {
+                declared,
+                kinds: detail.artifact_kinds()?,
+                lib: detail.lib.as_ref().is_some_and(|lib| lib.value),
+                target: detail.target.as_ref().map(|t| t.value.as_str()),
+            });
+        }
+        Ok(found)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::DependencyKind;
+
+    #[test]
+    fn artifact_dependencies() {
+        let manifest = CargoToml::parse(
+            r#"
+[dependencies]
+serde = "1"
+plugin = { path = "../plugin", artifact = "cdylib", lib = true, target = "wasm32-unknown-unknown" }
+
+[build-dependencies]
+tools = { path = "../tools", artifact = ["bin:protoc", "bin:flatc"] }
+"#,
+        )
+        .unwrap();
+        let found: Vec<_> = manifest
+            .artifact_dependencies()
+            .unwrap()
+            .into_iter()
+            .map(|a| {
+                let kinds: Vec<_> = a.kinds.iter().map(|k| k.value.to_string()).collect();
+                (a.declared.kind, a.declared.name, kinds, a.lib, a.target)
+            })
+            .collect();
+        assert_eq!(
+            found,
+            [
+                (
+                    DependencyKind::Normal,
+                    "plugin",
+                    vec!["cdylib".to_owned()],
+                    true,
+                    Some("wasm32-unknown-unknown")
+                ),
+                (
+                    DependencyKind::Build,
+                    "tools",
+                    vec!["bin:protoc".to_owned(), "bin:flatc".to_owned()],
+                    false,
+                    None
+                ),
+            ]
+        );
+
+        let source = "[dependencies]\nx = { path = \"x\", artifact = [\"bin\", \"dylib\"] }\n";
+        let manifest = CargoToml::parse(source).unwrap();
+        let Err(Error::InvalidArtifact { span, .. }) = manifest.artifact_dependencies() else {
+            panic!("expected an invalid artifact");
+        };
+        let span = span.unwrap();
+        assert_eq!(
+            &source[span.offset as usize..][..span.len as usize],
+            "\"dylib\""
+        );
+    }
+}
@@ -0,0 +1,432 @@
+//! `cfg()` expressions in `[target.'cfg(...)'.dependencies]` keys, and the
+//! dependencies that apply to a given platform.
+
+use std::collections::BTreeMap;
+use std::fmt;
+
+use crate::layout::Scan;
+use crate::{CargoToml, Dependency, DependencyKind, Diagnostic, Error, Span};
+
+/// A single configuration option, like `unix` or `target_os = "linux"`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum Cfg {
+    /// A name, like `unix` or `debug_assertions`.
+    Name(String),
+    /// A key and value, like `target_os = "linux"`.
+    KeyPair(String, String),
+}
+
+/// A `cfg()` predicate.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum CfgExpr {
+    /// `not(...)`.
+    Not(Box<CfgExpr>),
+    /// `all(...)`, which is true when empty.
+    All(Vec<CfgExpr>),
+    /// `any(...)`, which is false when empty.
+    Any(Vec<CfgExpr>),
+    /// A single option.
+    Value(Cfg),
+}
+
+/// The key of a `[target.<key>]` table: a target triple or a `cfg()`
+/// expression.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Platform {
+    /// A target name, like `x86_64-unknown-linux-gnu`.
+    Name(String),
+    /// A `cfg(...)` expression.
+    Cfg(CfgExpr),
+}
+
+impl Cfg {
+    /// Parse one line of `rustc --print cfg` output, like `unix` or
+    /// `target_os="linux"`.
+    pub fn parse(line: &str) -> Result<Self, Error> {
+        match CfgExpr::parse(line)? {
+            CfgExpr::Value(cfg) => Ok(cfg),
+            _ => Err(Error::InvalidCfg {
+                expression: line.to_owned(),
+                reason: "expected a name or `key = \"value\"`".to_owned(),
+                span: None,
+            }),
+        }
+    }
+}
+
+impl CfgExpr {
+    /// Parse the inside of a `cfg(...)`, like `all(unix, not(target_os = "macos"))`.
+    pub fn parse(expression: &str) -> Result<Self, Error> {
+        Parser::new(expression)
+            .parse_all()
+            .map_err(|e| e.into_error(expression, None))
+    }
+
+    /// Whether the expression holds when exactly the options in `cfg` are
+    /// set.
+    pub fn matches(&self, cfg: &[Cfg]) -> bool {
+        match self {
+            CfgExpr::Not(e) => !e.matches(cfg),
+            CfgExpr::All(e) => e.iter().all(|e| e.matches(cfg)),
+            CfgExpr::Any(e) => e.iter().any(|e| e.matches(cfg)),
+            CfgExpr::Value(value) => cfg.contains(value),
+        }
+    }
+}
+
+impl Platform {
+    /// Parse a `[target.<key>]` key.
+    pub fn parse(key: &str) -> Result<Self, Error> {
+        Platform::parse_key(key).map_err(|e| e.into_error(key, None))
+    }
+
+    fn parse_key(key: &str) -> Result<Self, CfgError> {
+        let Some(inner) = key.strip_prefix("cfg(") else {
+            if key.is_empty() || key.contains(|c: char| c.is_whitespace() || c == '(') {
+                return Err(CfgError::at(0, "expected a target name or `cfg(...)`"));
+            }
+            return Ok(Platform::Name(key.to_owned()));
+        };
+        let Some(inner) = inner.strip_suffix(')') else {
+            return Err(CfgError::at(key.len(), "expected `)` to close `cfg(`"));
+        };
+        let mut parser = Parser::new(inner);
+        parser.offset = 4;
+        parser.parse_all().map(Platform::Cfg)
+    }
+
+    /// Whether dependencies under this key apply when building for `target`
+    /// with the options in `cfg` set.
+    pub fn matches(&self, target: &str, cfg: &[Cfg]) -> bool {
+        match self {
+            Platform::Name(name) => name == target,
+            Platform::Cfg(expr) => expr.matches(cfg),
+        }
+    }
+}
+
+impl fmt::Display for Cfg {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Cfg::Name(name) => f.write_str(name),
+            Cfg::KeyPair(key, value) => write!(f, "{key} = \"{value}\""),
+        }
+    }
+}
+
+impl fmt::Display for CfgExpr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (name, exprs) = match self {
+            CfgExpr::Value(cfg) => return cfg.fmt(f),
+            CfgExpr::Not(e) => return write!(f, "not({e})"),
+            CfgExpr::All(exprs) => ("all", exprs),
+            CfgExpr::Any(exprs) => ("any", exprs),
+        };
+        let exprs: Vec<_> = exprs.iter().map(ToString::to_string).collect();
+        write!(f, "{name}({})", exprs.join(", "))
+    }
+}
+
+impl fmt::Display for Platform {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Platform::Name(name) => f.write_str(name),
+            Platform::Cfg(expr) => write!(f, "cfg({expr})"),
+        }
+    }
+}
+
+impl CargoToml {
+    /// The dependencies of `kind` that apply when building for the target
+    /// `target` with the options in `cfg` set, as `rustc --print cfg
+    /// --target <target>` lists them.
+    ///
+    /// Merges the top-level table with every matching `[target.<key>]` table.
+    /// A dependency declared in several of them maps to each declaration, the
+    /// top-level one first, then in key order.
+    ///
+    /// Fails if a `[target]` key is neither a target name nor a valid
+    /// `cfg()` expression.
+    pub fn platform_dependencies(
+        &self,
+        kind: DependencyKind,
+        target: &str,
+        cfg: &[Cfg],
+    ) -> Result<BTreeMap<&str, Vec<&Dependency>>, Error> {
+        let mut merged: BTreeMap<_, Vec<_>> = BTreeMap::new();
+        for declared in self.all_dependencies() {
+            if declared.kind != kind {
+                continue;
+            }
+            if let Some(key) = declared.platform
+                && !Platform::parse(key)?.matches(target, cfg)
+            {
+                continue;
+            }
+            merged
+                .entry(declared.name)
+                .or_default()
+                .push(declared.dependency);
+        }
+        Ok(merged)
+    }
+}
+
+/// Check every `[target.<key>]` key in `source`, so that a malformed one is
+/// reported at parse time with its location.
+pub(crate) fn check_target_keys(source: &str, manifest: &CargoToml) -> Result<(), Error> {
+    let Some(targets) = &manifest.target else {
+        return Ok(());
+    };
+    let mut keys: Vec<_> = targets.keys().collect();
+    keys.sort();
+    for key in keys {
+        let Err(error) = Platform::parse_key(key) else {
+            continue;
+        };
+        let span = key_span(source, key).map(|span| {
+            // Point into the key when it is written as-is, between quotes.
+            let inner = span.offset as usize + 1;
+            if source.get(inner..inner + key.len()) == Some(key.as_str()) {
+                Span {
+                    offset: (inner + error.offset.min(key.len())) as u32,
+                    len: 1,
+                }
+            } else {
+                span
+            }
+        });
+        let message = format!("invalid `[target]` key `{key}`: {}", error.reason);
+        return Err(Error::Parse {
+            diagnostic: Box::new(Diagnostic::new(message, source, span)),
+        });
+    }
+    Ok(())
+}
+
+/// The span of `key`, as the second segment of a `target.` header or dotted
+/// key.
+fn key_span(source: &str, key: &str) -> Option<Span> {
+    let scan = Scan::new(source);
+    let tables = scan.tables.iter().map(|t| (&t.path[..], t.start..t.body));
+    let entries = scan.entries.iter().map(|e| {
+        let table = &scan.tables[e.table].path;
+        let path = if table.is_empty() {
+            &e.key[..]
+        } else {
+            &table[..]
+        };
+        (path, e.start..e.value.start)
+    });
+    let (_, region) = tables
+        .chain(entries)
+        .find(|(path, _)| path.len() >= 2 && path[0] == "target" && path[1] == key)?;
+    let text = &source[region.clone()];
+    let found = [format!("'{key}'"), format!("\"{key}\"")]
+        .iter()
+        .find_map(|quoted| Some((text.find(quoted.as_str())?, quoted.len())));
+    let (offset, len) = found.unwrap_or((0, text.trim_end().len()));
+    Some(Span {
+        offset: (region.start + offset) as u32,
+        len: len as u32,
+    })
+}
+
+/// A parse failure at a byte offset in the parsed text.
+struct CfgError {
+    offset: usize,
+    reason: String,
+}
+
+impl CfgError {
+    fn at(offset: usize, reason: impl Into<String>) -> Self {
+        CfgError {
+            offset,
+            reason: reason.into(),
+        }
+    }
+
+    fn into_error(self, expression: &str, span: Option<Span>) -> Error {
+        Error::InvalidCfg {
+            expression: expression.to_owned(),
+            reason: format!("{} at offset {}", self.reason, self.offset),
+            span,
+        }
+    }
+}
+
+struct Parser<'a> {
+    text: &'a str,
+    pos: usize,
+    /// Added to positions in errors, for text taken out of a larger string.
+    offset: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn new(text: &'a str) -> Self {
+        Parser {
+            text,
+            pos: 0,
+            offset: 0,
+        }
+    }
+
+    fn parse_all(&mut self) -> Result<CfgExpr, CfgError> {
+        let expr = self.expr()?;
+        self.skip_ws();
+        if self.pos < self.text.len() {
+            return Err(self.error("unexpected text after the expression"));
+        }
+        Ok(expr)
+    }
+
+    fn expr(&mut self) -> Result<CfgExpr, CfgError> {
+        self.skip_ws();
+        let start = self.pos;
+        let name = self.ident()?;
+        self.skip_ws();
+        if self.eat('(') {
+            let mut exprs = Vec::new();
+            loop {
+                self.skip_ws();
+                if self.eat(')') {
+                    break;
+                }
+                exprs.push(self.expr()?);
+                self.skip_ws();
+                if self.eat(')') {
+                    break;
+                }
+                if !self.eat(',') {
+                    return Err(self.error("expected `,` or `)`"));
+                }
+            }
+            return match name {
+                "all" => Ok(CfgExpr::All(exprs)),
+                "any" => Ok(CfgExpr::Any(exprs)),
+                "not" if exprs.len() == 1 => Ok(CfgExpr::Not(Box::new(exprs.remove(0)))),
+                "not" => Err(CfgError::at(
+                    start + self.offset,
+                    "`not` takes exactly one expression",
+                )),
+                _ => Err(CfgError::at(
+                    start + self.offset,
+                    format!("unknown operator `{name}`; expected `all`, `any` or `not`"),
+                )),
+            };
+        }
+        if self.eat('=') {
+            self.skip_ws();
+            let value = self.string()?;
+            return Ok(CfgExpr::Value(Cfg::KeyPair(
+                name.to_owned(),
+                value.to_owned(),
+            )));
+        }
+        Ok(CfgExpr::Value(Cfg::Name(name.to_owned())))
+    }
+
+    fn ident(&mut self) -> Result<&'a str, CfgError> {
+        let rest = &self.text[self.pos..];
+        let len = rest
+            .char_indices()
+            .find(|&(i, c)| !(c == '_' || c.is_alphabetic() || (i > 0 && c.is_alphanumeric())))
+            .map_or(rest.len(), |(i, _)| i);
+        if len == 0 {
+            return Err(self.error("expected an identifier"));
+        }
+        self.pos += len;
+        Ok(&rest[..len])
+    }
+
+    fn string(&mut self) -> Result<&'a str, CfgError> {
+        if !self.eat('"') {
+            return Err(self.error("expected a string"));
+        }
+        let rest = &self.text[self.pos..];
+        let Some(len) = rest.find('"') else {
+            return Err(self.error("unterminated string"));
+        };
+        self.pos += len + 1;
+        Ok(&rest[..len])
+    }
+
+    fn skip_ws(&mut self) {
+        let rest = &self.text[self.pos..];
+        self.pos += rest.len() - rest.trim_start().len();
+    }
+
+    fn eat(&mut self, c: char) -> bool {
+        if self.text[self.pos..].starts_with(c) {
+            self.pos += c.len_utf8();
+            true
+        } else {
+            false
+        }
+    }
+
+    fn error(&self, reason: &str) -> CfgError {
+        CfgError::at(self.pos + self.offset, reason)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn linux() -> Vec<Cfg> {
+        ["unix", "target_os=\"linux\"", "target_pointer_width=\"64\""]
+            .into_iter()
+            .map(|line| Cfg::parse(line).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn evaluate_cfg() {
+        let expr = CfgExpr::parse(r#"all(unix, not(target_os = "macos"))"#).unwrap();
+        assert!(expr.matches(&linux()));
+        assert_eq!(expr.to_string(), r#"all(unix, not(target_os = "macos"))"#);
+        assert!(!CfgExpr::parse("any()").unwrap().matches(&linux()));
+        assert!(CfgExpr::parse("not(unix, windows)").is_err());
+    }
+
+    #[test]
+    fn dependencies_for_platform() {
+        let manifest = CargoToml::parse(
+            r#"
+[dependencies]
+log = "0.4"
+
+[target.'cfg(all(unix, not(target_os = "macos")))'.dependencies]
+libc = "0.2"
+log = { version = "0.4", features = ["std"] }
+
+[target.'cfg(windows)'.dependencies]
+windows-sys = "0.59"
+
+[target.x86_64-unknown-linux-gnu.dependencies]
+memchr = "2"
+"#,
+        )
+        .unwrap();
+        let dependencies = manifest
+            .platform_dependencies(DependencyKind::Normal, "x86_64-unknown-linux-gnu", &linux())
+            .unwrap();
+        let found: Vec<_> = dependencies
+            .iter()
+            .map(|(name, declarations)| (*name, declarations.len()))
+            .collect();
+        assert_eq!(found, [("libc", 1), ("log", 2), ("memchr", 1)]);
+    }
+
+    #[test]
+    fn malformed_target_key() {
+        let source = "[target.'cfg(all(unix,, windows))'.dependencies]\nlibc = \"0.2\"\n";
+        let Err(Error::Parse { diagnostic }) = CargoToml::parse(source) else {
+            panic!("expected a parse error");
+        };
+        assert_eq!(diagnostic.line, Some(1));
+        assert_eq!(diagnostic.column, Some(23));
+        assert!(diagnostic.message.contains("expected an identifier"));
+    }
+}
@@ -0,0 +1,127 @@
+//! Every dependency a manifest declares, across all of its tables.
+
+use std::collections::HashMap;
+
+use crate::{CargoToml, Dependency, DependencyKind};
+
+/// A dependency declaration from any of a manifest's dependency tables.
+///
+/// Yielded by [`CargoToml::all_dependencies`].
+#[derive(Debug, Clone, Copy)]
+pub struct DeclaredDependency<'a> {
+    /// The dependency table it appears in.
+    pub kind: DependencyKind,
+    /// The `[target.<key>]` key, for platform-specific dependencies; see
+    /// [`Platform::parse`](crate::Platform::parse).
+    pub platform: Option<&'a str>,
+    /// The key in the table, which features and code refer to it by.
+    pub name: &'a str,
+    /// The name of the package depended on: `package = "..."` when the
+    /// dependency is renamed, otherwise `name`.
+    ///
+    /// A rename in `[workspace.dependencies]` is not visible from a member's
+    /// `{ workspace = true }`; see [`Dependency::resolve`].
+    pub package: &'a str,
+    /// The declaration itself.
+    pub dependency: &'a Dependency,
+}
+
+impl CargoToml {
+    /// Every dependency in `[dependencies]`, `[dev-dependencies]`,
+    /// `[build-dependencies]` and their `[target.<key>]` counterparts.
+    ///
+    /// Top-level tables come first, then platform-specific ones in key order;
+    /// within a table, dependencies are in name order. `[workspace.dependencies]`
+    /// and `[patch]` are not included.
+    pub fn all_dependencies(&self) -> impl Iterator<Item = DeclaredDependency<'_>> {
+        let mut platforms: Vec<_> = self.target.iter().flatten().collect();
+        platforms.sort_by_key(|(key, _)| *key);
+        let platforms = platforms.into_iter().flat_map(|(key, spec)| {
+            [
+                (DependencyKind::Normal, &spec.dependencies),
+                (DependencyKind::Development, &spec.dev_dependencies),
+                (DependencyKind::Build, &spec.build_dependencies),
+            ]
+            .map(move |(kind, table)| (kind, Some(key.as_str()), table))
+        });
+        [
+            (DependencyKind::Normal, None, &self.dependencies),
+            (DependencyKind::Development, None, &self.dev_dependencies),
+            (DependencyKind::Build, None, &self.build_dependencies),
+        ]
+        .into_iter()
+        .chain(platforms)
+        .flat_map(|(kind, platform, table)| declared(kind, platform, table.as_ref()))
+    }
+}
+
+fn declared<'a>(
+    kind: DependencyKind,
+    platform: Option<&'a str>,
+    table: Option<&'a HashMap<String, Dependency>>,
+) -> Vec<DeclaredDependency<'a>> {
+    let mut declared: Vec<_> = table
+        .into_iter()
+        .flatten()
+        .map(|(name, dependency)| {
+            let package = match dependency {
+                Dependency::Detailed(detail) => detail.package.as_ref().map(|p| p.value.as_str()),
+                Dependency::Version(_) | Dependency::Workspace(_) => None,
+            };
+            DeclaredDependency {
+                kind,
+                platform,
+                name,
+                package: package.unwrap_or(name),
+                dependency,
+            }
+        })
+        .collect();
+    declared.sort_by_key(|d| d.name);
+    declared
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn all_dependencies() {
+        let manifest = CargoToml::parse(
+            r#"
+[dependencies]
+serde = "1"
+json = { package = "serde_json", version = "1" }
+
+[dev-dependencies]
+insta = "1"
+
+[target.'cfg(windows)'.build-dependencies]
+winres = "0.1"
+
+[target.'cfg(unix)'.dependencies]
+libc = "0.2"
+"#,
+        )
+        .unwrap();
+        let found: Vec<_> = manifest
+            .all_dependencies()
+            .map(|d| (d.kind, d.platform, d.name, d.package))
+            .collect();
+        assert_eq!(
+            found,
+            [
+                (DependencyKind::Normal, None, "json", "serde_json"),
+                (DependencyKind::Normal, None, "serde", "serde"),
+                (DependencyKind::Development, None, "insta", "insta"),
+                (DependencyKind::Normal, Some("cfg(unix)"), "libc", "libc"),
+                (
+                    DependencyKind::Build,
+                    Some("cfg(windows)"),
+                    "winres",
+                    "winres"
+                ),
+            ]
+        );
+    }
+}
@@ -0,0 +1,367 @@
+//! Parse errors located in their source.
+
+use std::fmt;
+use std::ops::Range;
+
+use camino::{Utf8Path, Utf8PathBuf};
+use facet::{Facet, Shape, Type, UserType};
+use facet_format::DeserializeErrorKind;
+
+use crate::layout::Scan;
+use crate::{
+    DebugLevelName, Edition, Error, LintLevelString, LtoName, OptLevelName, PanicStrategy,
+    Resolver, Span, StripLevelName,
+};
+
+/// A parse error with its location, rendered like a compiler diagnostic.
+///
+/// ```text
+/// parse error: invalid value `"2099"` for `package.edition`
+///  --> Cargo.toml:3:11
+///   |
+/// 3 | edition = "2099"
+///   |           ^^^^^^
+///   = expected one of `2015`, `2018`, `2021`, `2024`
+/// ```
+#[derive(Debug, Clone, Facet)]
+pub struct Diagnostic {
+    /// What went wrong.
+    pub message: String,
+    /// The file the source was read from, when parsed from disk.
+    pub path: Option<Utf8PathBuf>,
+    /// Where in the source the problem is, when known.
+    pub span: Option<Span>,
+    /// The 1-based line `span` starts on.
+    pub line: Option<u32>,
+    /// The 1-based column `span` starts at, counted in characters.
+    pub column: Option<u32>,
+    /// The accepted values, for keys that only take a fixed set of values.
+    pub expected: Vec<String>,
+    /// A suggested fix, like "did you mean `package`?".
+    pub help: Option<String>,
+    /// The source line containing `span`, with a caret underline below it.
+    pub snippet: Option<String>,
+}
+
+impl Diagnostic {
+    /// A diagnostic for `span` in `source`.
+    pub(crate) fn new(message: impl Into<String>, source: &str, span: Option<Span>) -> Self {
+        let mut diagnostic = Diagnostic {
+            message: message.into(),
+            path: None,
+            span,
+            line: None,
+            column: None,
+            expected: Vec::new(),
+            help: None,
+            snippet: None,
+        };
+        let Some(span) = span else {
+            return diagnostic;
+        };
+
+        let start = (span.offset as usize).min(source.len());
+        let line_start = source[..start].rfind('\n').map_or(0, |i| i + 1);
+        let line_end = source[start..]
+            .find('\n')
+            .map_or(source.len(), |i| start + i);
+        let line = source[..start].matches('\n').count() + 1;
+        let text = source[line_start..line_end].trim_end_matches('\r');
+        let column = source[line_start..start].chars().count() + 1;
+        let end = (start + span.len as usize)
+            .min(line_start + text.len())
+            .max(start);
+        let width = source[start..end].chars().count().max(1);
+
+        let number = line.to_string();
+        let gutter = " ".repeat(number.len());
+        diagnostic.snippet = Some(format!(
+            "{gutter} |\n{number} | {text}\n{gutter} | {}{}",
+            " ".repeat(column - 1),
+            "^".repeat(width),
+        ));
+        diagnostic.line = Some(line as u32);
+        diagnostic.column = Some(column as u32);
+        diagnostic
+    }
+
+    /// A diagnostic without a location.
+    pub(crate) fn message(message: impl Into<String>) -> Self {
+        Self::new(message, "", None)
+    }
+
+    /// Convert a facet-toml error for `source`.
+    ///
+    /// facet-toml does not always know the span of a bad value, nor which
+    /// values an enum accepts, so when it rejects an enum value, the key with
+    /// a fixed set of values that holds it is looked up in the source.
+    pub(crate) fn from_toml(source: &str, error: facet_toml::DeserializeError) -> Self {
+        if let DeserializeErrorKind::UnexpectedToken {
+            got,
+            expected: "known enum variant",
+        } = &error.kind
+            && let Some(diagnostic) = invalid_enum_value(source, got)
+        {
+            return diagnostic;
+        }
+        match error.span {
+            Some(span) if span.offset != 0 || span.len != 0 => {
+                Self::new(error.kind.to_string(), source, Some(span))
+            }
+            _ => Self::message(error.to_string()),
+        }
+    }
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "parse error: {}", self.message)?;
+        match (&self.path, self.line, self.column) {
+            (Some(path), Some(line), Some(column)) => write!(f, "\n --> {path}:{line}:{column}")?,
+            (None, Some(line), Some(column)) => write!(f, "\n --> {line}:{column}")?,
+            (Some(path), _, _) => write!(f, "\n --> {path}")?,
+            (None, _, _) => {}
+        }
+        if let Some(snippet) = &self.snippet {
+            write!(f, "\n{snippet}")?;
+        }
+        if !self.expected.is_empty() {
+            let expected: Vec<_> = self.expected.iter().map(|e| format!("`{e}`")).collect();
+            let indent = " ".repeat(self.line.map_or(1, |l| l.to_string().len()));
+            write!(f, "\n{indent} = expected one of {}", expected.join(", "))?;
+        }
+        if let Some(help) = &self.help {
+            let indent = " ".repeat(self.line.map_or(1, |l| l.to_string().len()));
+            write!(f, "\n{indent} = help: {help}")?;
+        }
+        Ok(())
+    }
+}
+
+impl Error {
+    /// Record the file a parse error came from.
+    pub(crate) fn with_path(mut self, file: &Utf8Path) -> Self {
+        if let Error::Parse { diagnostic } = &mut self {
+            diagnostic.path = Some(file.to_owned());
+        }
+        self
+    }
+}
+
+/// Keys that take one of a fixed set of values, as dotted paths where `*`
+/// matches any segment, with the type listing those values.
+const ENUM_KEYS: &[(&[&str], &Shape)] = &[
+    (&["package", "edition"], Edition::SHAPE),
+    (&["workspace", "package", "edition"], Edition::SHAPE),
+    (&["lib", "edition"], Edition::SHAPE),
+    (&["bin", "edition"], Edition::SHAPE),
+    (&["example", "edition"], Edition::SHAPE),
+    (&["test", "edition"], Edition::SHAPE),
+    (&["bench", "edition"], Edition::SHAPE),
+    (&["package", "resolver"], Resolver::SHAPE),
+    (&["workspace", "resolver"], Resolver::SHAPE),
+    (&["profile", "*", "panic"], PanicStrategy::SHAPE),
+    (&["lints", "*", "*"], LintLevelString::SHAPE),
+    (&["workspace", "lints", "*", "*"], LintLevelString::SHAPE),
+];
+
+/// Keys whose string values must be one of a fixed set, like `lto = "thin"`,
+/// but which also take numbers or booleans, listed last.
+const NAMED_VALUE_KEYS: &[(&[&str], &Shape, &[&str])] = &[
+    (
+        &["profile", "*", "opt-level"],
+        OptLevelName::SHAPE,
+        OPT_LEVELS,
+    ),
+    (
+        &["profile", "*", "package", "*", "opt-level"],
+        OptLevelName::SHAPE,
+        OPT_LEVELS,
+    ),
+    (
+        &["profile", "*", "build-override", "opt-level"],
+        OptLevelName::SHAPE,
+        OPT_LEVELS,
+    ),
+    (
+        &["profile", "*", "debug"],
+        DebugLevelName::SHAPE,
+        DEBUG_LEVELS,
+    ),
+    (
+        &["profile", "*", "package", "*", "debug"],
+        DebugLevelName::SHAPE,
+        DEBUG_LEVELS,
+    ),
+    (
+        &["profile", "*", "build-override", "debug"],
+        DebugLevelName::SHAPE,
+        DEBUG_LEVELS,
+    ),
+    (&["profile", "*", "lto"], LtoName::SHAPE, BOOLEANS),
+    (&["profile", "*", "strip"], StripLevelName::SHAPE, BOOLEANS),
+];
+
+const OPT_LEVELS: &[&str] = &["0", "1", "2", "3"];
+const DEBUG_LEVELS: &[&str] = &["true", "false", "0", "1", "2"];
+const BOOLEANS: &[&str] = &["true", "false"];
+
+/// Find the first string value `got` in `source` that is not one of the values
+/// its key accepts.
+fn invalid_enum_value(source: &str, got: &str) -> Option<Diagnostic> {
+    let scan = Scan::new(source);
+    scan.entries.iter().find_map(|entry| {
+        let key: Vec<&str> = scan.tables[entry.table]
+            .path
+            .iter()
+            .chain(&entry.key)
+            .map(String::as_str)
+            .collect();
+        let matches = |pattern: &[&str]| {
+            pattern.len() == key.len() && pattern.iter().zip(&key).all(|(p, k)| *p == "*" || p == k)
+        };
+        let (shape, others) = ENUM_KEYS
+            .iter()
+            .find(|(pattern, _)| matches(pattern))
+            .map(|(_, shape)| (*shape, &[][..]))
+            .or_else(|| {
+                NAMED_VALUE_KEYS
+                    .iter()
+                    .find(|(pattern, ..)| matches(pattern))
+                    .map(|(_, shape, others)| (*shape, *others))
+            })?;
+
+        let text = source.get(entry.value.clone())?;
+        // Inline tables, like `{ level = "warn", priority = -1 }`, are left to
+        // facet-toml, as are the numbers and booleans of mixed keys.
+        let is_string = text.starts_with(['"', '\'']);
+        if text.starts_with('{') || (!is_string && !others.is_empty()) {
+            return None;
+        }
+        let names = variant_names(shape);
+        let value = text.trim_matches(|c| c == '"' || c == '\'');
+        if value != got || names.contains(&value) {
+            return None;
+        }
+        Some(invalid_value(
+            source,
+            &key,
+            entry.value.clone(),
+            &names,
+            others,
+        ))
+    })
+}
+
+/// A diagnostic for the value at `range`, which is not one of `names` or
+/// `others`.
+fn invalid_value(
+    source: &str,
+    key: &[&str],
+    range: Range<usize>,
+    names: &[&str],
+    others: &[&str],
+) -> Diagnostic {
+    let span = Span {
+        offset: range.start as u32,
+        len: range.len() as u32,
+    };
+    let mut diagnostic = Diagnostic::new(
+        format!("invalid value `{}` for `{}`", &source[range], key.join(".")),
+        source,
+        Some(span),
+    );
+    diagnostic.expected = names.iter().chain(others).map(|e| e.to_string()).collect();
+    diagnostic
+}
+
+/// The serialized names of a unit-only enum's variants.
+fn variant_names(shape: &Shape) -> Vec<&'static str> {
+    match shape.ty {
+        Type::User(UserType::Enum(enum_type)) => enum_type
+            .variants
+            .iter()
+            .map(|v| v.effective_name())
+            .collect(),
+        _ => Vec::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{CargoToml, Error};
+
+    #[test]
+    fn invalid_edition() {
+        let source = "[package]\nname = \"demo\"\nedition = \"2099\"\n";
+        let Err(Error::Parse { diagnostic }) = CargoToml::parse(source) else {
+            panic!("expected a parse error");
+        };
+        assert_eq!(diagnostic.line, Some(3));
+        assert_eq!(diagnostic.column, Some(11));
+        assert_eq!(diagnostic.expected, ["2015", "2018", "2021", "2024"]);
+        assert_eq!(
+            diagnostic.to_string(),
+            r#"parse error: invalid value `"2099"` for `package.edition`
+ --> 3:11
+  |
+3 | edition = "2099"
+  |           ^^^^^^
+  = expected one of `2015`, `2018`, `2021`, `2024`"#
+        );
+    }
+
+    #[test]
+    fn invalid_panic_strategy() {
+        let source = "[profile.release]\nopt-level = 3\npanic = \"explode\"\n";
+        let Err(Error::Parse { diagnostic }) = CargoToml::parse(source) else {
+            panic!("expected a parse error");
+        };
+        let span = diagnostic.span.unwrap();
+        assert_eq!(
+            &source[span.offset as usize..][..span.len as usize],
+            "\"explode\""
+        );
+        assert_eq!(diagnostic.expected, ["unwind", "abort", "immediate-abort"]);
+    }
+
+    #[test]
+    fn invalid_lto() {
+        let source = "[profile.release]\nlto = \"medium\"\n";
+        let Err(Error::Parse { diagnostic }) = CargoToml::parse(source) else {
+            panic!("expected a parse error");
+        };
+        assert_eq!(diagnostic.line, Some(2));
+        assert_eq!(diagnostic.column, Some(7));
+        assert_eq!(diagnostic.expected, ["thin", "fat", "off", "true", "false"]);
+    }
+
+    #[test]
+    fn syntax_error() {
+        let Err(Error::Parse { diagnostic }) = CargoToml::parse("[package\nname = \"x\"\n") else {
+            panic!("expected a parse error");
+        };
+        assert_eq!(diagnostic.line, Some(1));
+        assert!(diagnostic.snippet.unwrap().contains("[package"));
+    }
+
+    #[test]
+    fn syntax_error_before_invalid_value() {
+        let source = "[package\nedition = \"2099\"\n";
+        let Err(Error::Parse { diagnostic }) = CargoToml::parse(source) else {
+            panic!("expected a parse error");
+        };
+        assert!(diagnostic.expected.is_empty(), "{diagnostic}");
+        assert_eq!(diagnostic.line, Some(1));
+    }
+
+    #[test]
+    fn key_without_value() {
+        for source in ["[package]\nedition", "[package]\nedition \u{e9}\n"] {
+            let Err(Error::Parse { diagnostic }) = CargoToml::parse(source) else {
+                panic!("expected a parse error for {source:?}");
+            };
+            assert!(diagnostic.expected.is_empty(), "{diagnostic}");
+        }
+    }
+}
@@ -0,0 +1,590 @@
+//! Format-preserving `Cargo.toml` editing.
+
+use std::ops::Range;
+
+use camino::Utf8Path;
+
+use crate::layout::{Location, Scan};
+use crate::serialize::{toml_key, toml_string};
+use crate::{CargoToml, Dependency, DependencyKind, Error, Span};
+
+/// Applies edits to a `Cargo.toml` as text patches, preserving comments,
+/// ordering and whitespace everywhere else.
+///
+/// Every edit is re-validated with [`CargoToml::parse`]; an edit that would
+/// produce an invalid manifest is rejected and leaves the source untouched.
+#[derive(Debug, Clone)]
+pub struct ManifestEditor {
+    source: String,
+    manifest: CargoToml,
+}
+
+impl ManifestEditor {
+    /// Start editing `Cargo.toml` content.
+    pub fn parse(contents: &str) -> Result<Self, Error> {
+        Ok(ManifestEditor {
+            manifest: CargoToml::parse(contents)?,
+            source: contents.to_owned(),
+        })
+    }
+
+    /// Start editing a `Cargo.toml` file from disk.
+    pub fn from_path(path: impl AsRef<Utf8Path>) -> Result<Self, Error> {
+        let path = path.as_ref();
+        let contents = std::fs::read_to_string(path).map_err(|source| Error::Io {
+            path: path.to_owned(),
+            source: crate::IoError::from(source),
+        })?;
+        Self::parse(&contents).map_err(|e| e.with_path(path))
+    }
+
+    /// Write the edited manifest to disk.
+    pub fn write_to_path(&self, path: impl AsRef<Utf8Path>) -> Result<(), Error> {
+        let path = path.as_ref();
+        std::fs::write(path, &self.source).map_err(|source| Error::Io {
+            path: path.to_owned(),
+            source: crate::IoError::from(source),
+        })
+    }
+
+    /// The edited manifest text.
+    pub fn source(&self) -> &str {
+        &self.source
+    }
+
+    /// The edited manifest text, consuming the editor.
+    pub fn into_source(self) -> String {
+        self.source
+    }
+
+    /// The edited manifest, as parsed after the last edit.
+    pub fn manifest(&self) -> &CargoToml {
+        &self.manifest
+    }
+
+    /// Set a `[package]` field to a string value, e.g. `description`.
+    pub fn set_package_field(&mut self, key: &str, value: &str) -> Result<(), Error> {
+        if self.manifest.package.is_none() {
+            return Err(edit_error("manifest has no [package] table"));
+        }
+        self.set_entry(&["package"], key, &toml_string(value), false)
+    }
+
+    /// Remove a `[package]` field.
+    pub fn remove_package_field(&mut self, key: &str) -> Result<(), Error> {
+        self.remove_entry(&["package"], key)
+    }
+
+    /// Add a dependency, or replace it if it already exists.
+    ///
+    /// `table` is a [`DependencyKind`] for the top-level tables, or a
+    /// [`DependencyTable`] for `[target.<key>]` and `[workspace.dependencies]`.
+    /// `value` is the TOML for the dependency specification, such as `"1.0"` or
+    /// `{ version = "1.0", features = ["derive"] }`. New entries are inserted in
+    /// alphabetical order when the table is already sorted, like `cargo add`.
+    pub fn add_dependency<'a>(
+        &mut self,
+        table: impl Into<DependencyTable<'a>>,
+        name: &str,
+        value: &str,
+    ) -> Result<(), Error> {
+        let table = self.dependency_table(table.into());
+        self.set_entry(&keys(&table), name, value, true)
+    }
+
+    /// Remove a dependency, including any `[dependencies.name]` table.
+    pub fn remove_dependency<'a>(
+        &mut self,
+        table: impl Into<DependencyTable<'a>>,
+        name: &str,
+    ) -> Result<(), Error> {
+        let table = self.dependency_table(table.into());
+        self.remove_entry(&keys(&table), name)
+    }
+
+    /// Change the version requirement of an existing dependency.
+    pub fn set_dependency_version<'a>(
+        &mut self,
+        table: impl Into<DependencyTable<'a>>,
+        name: &str,
+        version: &str,
+    ) -> Result<(), Error> {
+        let table = table.into();
+        let dependencies = match table {
+            DependencyTable::Package { kind, platform } => {
+                let (dependencies, dev, build) = match platform {
+                    None => (
+                        &self.manifest.dependencies,
+                        &self.manifest.dev_dependencies,
+                        &self.manifest.build_dependencies,
+                    ),
+                    Some(platform) => {
+                        match self.manifest.target.as_ref().and_then(|t| t.get(platform)) {
+                            Some(spec) => (
+                                &spec.dependencies,
+                                &spec.dev_dependencies,
+                                &spec.build_dependencies,
+                            ),
+                            None => (&None, &None, &None),
+                        }
+                    }
+                };
+                match kind {
+                    DependencyKind::Normal => dependencies,
+                    DependencyKind::Development => dev,
+                    DependencyKind::Build => build,
+                }
+            }
+            DependencyTable::Workspace => match &self.manifest.workspace {
+                Some(workspace) => &workspace.dependencies,
+                None => &None,
+            },
+        };
+        let path = self.dependency_table(table);
+        let dependency = dependencies
+            .as_ref()
+            .and_then(|deps| deps.get(name))
+            .ok_or_else(|| {
+                let table: Vec<_> = path.iter().map(|p| toml_key(p)).collect();
+                edit_error(&format!("no dependency `{name}` in [{}]", table.join(".")))
+            })?;
+
+        let current = match dependency {
+            Dependency::Version(current) => Some(current.span),
+            Dependency::Detailed(detail) => detail.version.as_ref().map(|v| v.span),
+            Dependency::Workspace(_) => {
+                return Err(edit_error(&format!(
+                    "`{name}` inherits its version from the workspace"
+                )));
+            }
+        };
+
+        let version = toml_string(version);
+        match current {
+            Some(span) => self.replace_span(span, &version),
+            None => self.insert_into_table(&keys(&path), name, "version", &version),
+        }
+    }
+
+    /// Set a feature's list of enabled features and dependencies, adding the
+    /// feature if it does not exist.
+    pub fn set_feature(&mut self, name: &str, enables: &[&str]) -> Result<(), Error> {
+        let values: Vec<_> = enables.iter().map(|v| toml_string(v)).collect();
+        self.set_entry(
+            &["features"],
+            name,
+            &format!("[{}]", values.join(", ")),
+            false,
+        )
+    }
+
+    /// Remove a feature.
+    pub fn remove_feature(&mut self, name: &str) -> Result<(), Error> {
+        self.remove_entry(&["features"], name)
+    }
+
+    /// The header path of `table`, spelled as in the source, since Cargo
+    /// still accepts `[dev_dependencies]` and `[build_dependencies]`.
+    fn dependency_table(&self, table: DependencyTable<'_>) -> Vec<String> {
+        let (kind, platform) = match table {
+            DependencyTable::Package { kind, platform } => (kind, platform),
+            DependencyTable::Workspace => {
+                return vec!["workspace".to_owned(), "dependencies".to_owned()];
+            }
+        };
+        let mut path: Vec<String> = match platform {
+            Some(platform) => vec!["target".to_owned(), platform.to_owned()],
+            None => Vec::new(),
+        };
+        let name = kind.table_name();
+        let underscored = name.replace('-', "_");
+        let written = crate::unused::keys(&self.source)
+            .into_iter()
+            .any(|(key, _)| {
+                key.len() > path.len()
+                    && key[..path.len()] == path[..]
+                    && key[path.len()] == underscored
+            });
+        path.push(if written {
+            underscored
+        } else {
+            name.to_owned()
+        });
+        path
+    }
+
+    /// Set `key` in `table` to the TOML `value`, replacing the existing value in
+    /// place when it is a single `key = value` entry.
+    fn set_entry(
+        &mut self,
+        table: &[&str],
+        key: &str,
+        value: &str,
+        sorted: bool,
+    ) -> Result<(), Error> {
+        let scan = Scan::new(&self.source);
+        let existing = scan.locations(table, key);
+        let line = format!("{} = {value}\n", toml_key(key));
+        let t = scan.table(table);
+
+        // A plain `key = value` is updated in place; dotted `key.field = ...`
+        // entries are replaced by a single line where the first one was.
+        let entries: Option<Vec<_>> = existing
+            .iter()
+            .map(|location| match location {
+                Location::Entry(entry) if Some(entry.table) == t => Some(*entry),
+                _ => None,
+            })
+            .collect();
+        match entries.as_deref() {
+            Some([entry]) if entry.key.len() == 1 => {
+                return self.apply(vec![(entry.value.clone(), value.to_owned())]);
+            }
+            Some([first, rest @ ..]) => {
+                let mut patches = vec![(first.start..first.end, line)];
+                patches.extend(rest.iter().map(|e| (e.lead..e.end, String::new())));
+                return self.apply(patches);
+            }
+            _ => {}
+        }
+
+        let mut patches: Vec<_> = existing
+            .iter()
+            .map(|location| (location.range(&scan), String::new()))
+            .collect();
+
+        match t {
+            Some(t) => {
+                let entries: Vec<_> = scan.entries_in(t).collect();
+                let is_sorted = entries.windows(2).all(|w| w[0].key[0] <= w[1].key[0]);
+                let position = match entries
+                    .iter()
+                    .find(|e| sorted && is_sorted && e.key[0].as_str() > key)
+                {
+                    Some(next) => next.lead,
+                    None => entries.last().map_or(scan.tables[t].body, |e| e.end),
+                };
+                let prefix = if position == self.source.len() && !self.source.ends_with('\n') {
+                    "\n"
+                } else {
+                    ""
+                };
+                patches.push((position..position, format!("{prefix}{line}")));
+            }
+            None => {
+                let end = self.source.len();
+                let prefix = match &self.source {
+                    s if s.is_empty() => "",
+                    s if s.ends_with('\n') => "\n",
+                    _ => "\n\n",
+                };
+                let header = table
+                    .iter()
+                    .map(|t| toml_key(t))
+                    .collect::<Vec<_>>()
+                    .join(".");
+                patches.push((end..end, format!("{prefix}[{header}]\n{line}")));
+            }
+        }
+        self.apply(patches)
+    }
+
+    /// Remove every entry and sub-table defining `key` in `table`.
+    fn remove_entry(&mut self, table: &[&str], key: &str) -> Result<(), Error> {
+        let scan = Scan::new(&self.source);
+        let patches: Vec<_> = scan
+            .locations(table, key)
+            .iter()
+            .map(|location| (location.range(&scan), String::new()))
+            .collect();
+        if patches.is_empty() {
+            return Err(edit_error(&format!("no `{key}` in [{}]", table.join("."))));
+        }
+        self.apply(patches)
+    }
+
+    /// Add `field = value` to the table value of `key` in `table`, which is
+    /// either an inline table or a `[table.key]` section.
+    fn insert_into_table(
+        &mut self,
+        table: &[&str],
+        key: &str,
+        field: &str,
+        value: &str,
+    ) -> Result<(), Error> {
+        let scan = Scan::new(&self.source);
+        for location in scan.locations(table, key) {
+            match location {
+                Location::Entry(entry) if entry.key.len() == 1 => {
+                    let text = &self.source[entry.value.clone()];
+                    if let Some(rest) = text.strip_prefix('{') {
+                        let at = entry.value.start + 1 + (rest.len() - rest.trim_start().len());
+                        let separator = if rest.trim_start().starts_with('}') {
+                            " "
+                        } else {
+                            ", "
+                        };
+                        return self.apply(vec![(at..at, format!("{field} = {value}{separator}"))]);
+                    }
+                }
+                Location::Table(t) => {
+                    let at = scan.tables[t].body;
+                    return self.apply(vec![(at..at, format!("{field} = {value}\n"))]);
+                }
+                _ => {}
+            }
+        }
+        Err(edit_error(&format!(
+            "`{key}` in [{}] is not a table",
+            table.join(".")
+        )))
+    }
+
+    fn replace_span(&mut self, span: Option<Span>, value: &str) -> Result<(), Error> {
+        let span = span.ok_or_else(|| edit_error("value has no source span"))?;
+        let start = span.offset as usize;
+        self.apply(vec![(start..start + span.len as usize, value.to_owned())])
+    }
+
+    /// Apply non-overlapping patches and re-validate the result.
+    fn apply(&mut self, mut patches: Vec<(Range<usize>, String)>) -> Result<(), Error> {
+        // Back to front, so earlier ranges stay valid; a removal is applied
+        // before an insertion at the same position.
+        patches.sort_by_key(|(range, _)| std::cmp::Reverse((range.start, range.end)));
+        let mut source = self.source.clone();
+        for (range, text) in patches {
+            source.replace_range(range, &text);
+        }
+        self.manifest = CargoToml::parse(&source)?;
+        self.source = source;
+        Ok(())
+    }
+}
+
+/// A dependency table an edit applies to.
+///
+/// A [`DependencyKind`] converts to the top-level table of that kind.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DependencyTable<'a> {
+    /// `[dependencies]`, `[dev-dependencies]` or `[build-dependencies]`, or
+    /// their `[target.<platform>]` counterparts.
+    Package {
+        /// Which of the three tables.
+        kind: DependencyKind,
+        /// The `[target.<key>]` key, like `cfg(unix)`.
+        platform: Option<&'a str>,
+    },
+    /// `[workspace.dependencies]`.
+    Workspace,
+}
+
+impl From<DependencyKind> for DependencyTable<'_> {
+    fn from(kind: DependencyKind) -> Self {
+        DependencyTable::Package {
+            kind,
+            platform: None,
+        }
+    }
+}
+
+fn keys(path: &[String]) -> Vec<&str> {
+    path.iter().map(String::as_str).collect()
+}
+
+fn edit_error(message: &str) -> Error {
+    Error::Edit {
+        message: message.to_owned(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MANIFEST: &str = r#"# The app.
+[package]
+name = "app" # keep me
+version = "0.1.0"
+edition.workspace = true
+
+[dependencies]
+anyhow = "1.0"
+# Serialization.
+serde = { version = "1.0", features = ["derive"] }
+tokio = { path = "../tokio" }
+
+[dependencies.regex]
+version = "1"
+
+[features]
+default = ["std"]
+std = []
+"#;
+
+    #[test]
+    fn edit_dependencies() {
+        let mut editor = ManifestEditor::parse(MANIFEST).unwrap();
+        editor
+            .add_dependency(DependencyKind::Normal, "log", r#""0.4""#)
+            .unwrap();
+        editor
+            .set_dependency_version(DependencyKind::Normal, "serde", "1.0.200")
+            .unwrap();
+        editor
+            .set_dependency_version(DependencyKind::Normal, "tokio", "1")
+            .unwrap();
+        editor
+            .remove_dependency(DependencyKind::Normal, "regex")
+            .unwrap();
+        editor
+            .add_dependency(DependencyKind::Development, "insta", r#""1""#)
+            .unwrap();
+
+        assert_eq!(
+            editor.source(),
+            r#"# The app.
+[package]
+name = "app" # keep me
+version = "0.1.0"
+edition.workspace = true
+
+[dependencies]
+anyhow = "1.0"
+log = "0.4"
+# Serialization.
+serde = { version = "1.0.200", features = ["derive"] }
+tokio = { version = "1", path = "../tokio" }
+
+[features]
+default = ["std"]
+std = []
+
+[dev-dependencies]
+insta = "1"
+"#
+        );
+    }
+
+    #[test]
+    fn edit_package_and_features() {
+        let mut editor = ManifestEditor::parse(MANIFEST).unwrap();
+        editor.set_package_field("name", "renamed").unwrap();
+        editor.set_package_field("edition", "2024").unwrap();
+        editor.set_package_field("license", "MIT").unwrap();
+        editor.set_feature("std", &["serde/std"]).unwrap();
+        editor.set_feature("extra", &["dep:regex"]).unwrap();
+        editor.remove_feature("default").unwrap();
+
+        assert_eq!(
+            editor.source(),
+            r#"# The app.
+[package]
+name = "renamed" # keep me
+version = "0.1.0"
+edition = "2024"
+license = "MIT"
+
+[dependencies]
+anyhow = "1.0"
+# Serialization.
+serde = { version = "1.0", features = ["derive"] }
+tokio = { path = "../tokio" }
+
+[dependencies.regex]
+version = "1"
+
+[features]
+std = ["serde/std"]
+extra = ["dep:regex"]
+"#
+        );
+    }
+
+    #[test]
+    fn edit_underscore_tables() {
+        let source = "[package]\nname = \"app\"\n\n[dev_dependencies]\ninsta = \"1\"\n";
+        let mut editor = ManifestEditor::parse(source).unwrap();
+        editor
+            .add_dependency(DependencyKind::Development, "serde", r#""1""#)
+            .unwrap();
+        assert_eq!(
+            editor.source(),
+            "[package]\nname = \"app\"\n\n[dev_dependencies]\ninsta = \"1\"\nserde = \"1\"\n"
+        );
+        editor
+            .remove_dependency(DependencyKind::Development, "insta")
+            .unwrap();
+        assert_eq!(
+            editor.source(),
+            "[package]\nname = \"app\"\n\n[dev_dependencies]\nserde = \"1\"\n"
+        );
+
+        let source = "build_dependencies.cc = \"1\"\n";
+        let mut editor = ManifestEditor::parse(source).unwrap();
+        editor
+            .set_dependency_version(DependencyKind::Build, "cc", "1.2")
+            .unwrap();
+        editor
+            .remove_dependency(DependencyKind::Build, "cc")
+            .unwrap();
+        assert_eq!(editor.source(), "");
+    }
+
+    #[test]
+    fn edit_target_and_workspace_tables() {
+        let source = "[workspace]\nmembers = []\n\n[workspace.dependencies]\nserde = \"1\"\n\n[target.'cfg(unix)'.dev_dependencies]\nlibc = \"0.2\"\n";
+        let mut editor = ManifestEditor::parse(source).unwrap();
+        let unix = DependencyTable::Package {
+            kind: DependencyKind::Development,
+            platform: Some("cfg(unix)"),
+        };
+        editor.add_dependency(unix, "nix", r#""0.29""#).unwrap();
+        editor
+            .set_dependency_version(unix, "libc", "0.2.150")
+            .unwrap();
+        editor
+            .set_dependency_version(DependencyTable::Workspace, "serde", "1.0.200")
+            .unwrap();
+        editor
+            .add_dependency(DependencyTable::Workspace, "log", r#""0.4""#)
+            .unwrap();
+        let windows = DependencyTable::Package {
+            kind: DependencyKind::Normal,
+            platform: Some("cfg(windows)"),
+        };
+        editor
+            .add_dependency(windows, "winapi", r#""0.3""#)
+            .unwrap();
+        assert_eq!(
+            editor.source(),
+            r#"[workspace]
+members = []
+
+[workspace.dependencies]
+log = "0.4"
+serde = "1.0.200"
+
+[target.'cfg(unix)'.dev_dependencies]
+libc = "0.2.150"
+nix = "0.29"
+
+[target."cfg(windows)".dependencies]
+winapi = "0.3"
+"#
+        );
+
+        editor.remove_dependency(unix, "nix").unwrap();
+        assert!(editor.remove_dependency(windows, "nix").is_err());
+    }
+
+    #[test]
+    fn reject_invalid_edit() {
+        let mut editor = ManifestEditor::parse(MANIFEST).unwrap();
+        assert!(
+            editor
+                .add_dependency(DependencyKind::Normal, "bad", "{ version = ")
+                .is_err()
+        );
+        assert_eq!(editor.source(), MANIFEST);
+    }
+}
@@ -0,0 +1,408 @@
+//! Cargo features: what each feature enables, and which features and optional
+//! dependencies a build activates.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+
+use crate::serialize::toml_key;
+use crate::{CargoToml, Dependency, Error, Severity, Spanned, ValidationIssue};
+
+/// One entry in a feature's list.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum FeatureValue {
+    /// `name`: another feature of this package, or the implicit feature of
+    /// an optional dependency.
+    Feature(String),
+    /// `dep:name`: enables an optional dependency, without exposing a
+    /// feature of the same name.
+    Dep(String),
+    /// `name/feature` or, when `weak`, `name?/feature`: enables `feature` on
+    /// dependency `name`.
+    ///
+    /// The strong form also enables `name` if it is optional; the weak form
+    /// only applies if something else enables it.
+    DepFeature {
+        dep: String,
+        feature: String,
+        weak: bool,
+    },
+}
+
+impl FeatureValue {
+    /// Parse a feature value the way Cargo does.
+    pub fn parse(value: &str) -> Self {
+        if let Some(dep) = value.strip_prefix("dep:") {
+            return FeatureValue::Dep(dep.to_owned());
+        }
+        match value.split_once('/') {
+            Some((dep, feature)) => {
+                let (dep, weak) = match dep.strip_suffix('?') {
+                    Some(dep) => (dep, true),
+                    None => (dep, false),
+                };
+                FeatureValue::DepFeature {
+                    dep: dep.to_owned(),
+                    feature: feature.to_owned(),
+                    weak,
+                }
+            }
+            None => FeatureValue::Feature(value.to_owned()),
+        }
+    }
+}
+
+impl fmt::Display for FeatureValue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FeatureValue::Feature(name) => f.write_str(name),
+            FeatureValue::Dep(dep) => write!(f, "dep:{dep}"),
+            FeatureValue::DepFeature { dep, feature, weak } => {
+                let weak = if *weak { "?" } else { "" };
+                write!(f, "{dep}{weak}/{feature}")
+            }
+        }
+    }
+}
+
+/// The features of a package: `[features]`, plus the implicit feature Cargo
+/// creates for each optional dependency not referenced with `dep:`.
+///
+/// Built with [`CargoToml::feature_graph`].
+#[derive(Debug, Clone)]
+pub struct FeatureGraph {
+    features: BTreeMap<String, Vec<Spanned<FeatureValue>>>,
+    implicit: BTreeSet<String>,
+    /// Dependencies features can refer to, and whether each is optional.
+    dependencies: BTreeMap<String, bool>,
+}
+
+/// The result of [`FeatureGraph::activate`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Activation {
+    /// Every enabled feature, including implicit optional-dependency features.
+    pub features: BTreeSet<String>,
+    /// Optional dependencies that are enabled.
+    pub dependencies: BTreeSet<String>,
+    /// Features enabled on dependencies, keyed by dependency name.
+    pub dependency_features: BTreeMap<String, BTreeSet<String>>,
+}
+
+impl CargoToml {
+    /// Build the feature graph of this package.
+    ///
+    /// Dependencies are referred to by their key in the manifest, which is
+    /// the rename for `package = "..."` dependencies, as in Cargo.
+    pub fn feature_graph(&self) -> FeatureGraph {
+        // Dev-dependencies cannot be optional, but `name/feature` may still
+        // refer to them, for test builds.
+        let mut dependencies = BTreeMap::new();
+        for declared in self.all_dependencies() {
+            *dependencies
+                .entry(declared.name.to_owned())
+                .or_insert(false) |= is_optional(declared.dependency);
+        }
+
+        let mut features: BTreeMap<_, Vec<_>> = self
+            .features
+            .iter()
+            .flatten()
+            .map(|(name, values)| {
+                let values = values
+                    .iter()
+                    .map(|v| Spanned {
+                        value: FeatureValue::parse(&v.value),
+                        span: v.span,
+                    })
+                    .collect();
+                (name.clone(), values)
+            })
+            .collect();
+
+        let explicit_deps: BTreeSet<_> = features
+            .values()
+            .flatten()
+            .filter_map(|v| match &v.value {
+                FeatureValue::Dep(dep) => Some(dep.clone()),
+                _ => None,
+            })
+            .collect();
+        let mut implicit = BTreeSet::new();
+        for (name, _) in dependencies.iter().filter(|(_, optional)| **optional) {
+            if !explicit_deps.contains(name) && !features.contains_key(name) {
+                let value = Spanned {
+                    value: FeatureValue::Dep(name.clone()),
+                    span: None,
+                };
+                features.insert(name.clone(), vec![value]);
+                implicit.insert(name.clone());
+            }
+        }
+
+        FeatureGraph {
+            features,
+            implicit,
+            dependencies,
+        }
+    }
+}
+
+fn is_optional(dependency: &Dependency) -> bool {
+    let optional = match dependency {
+        Dependency::Version(_) => None,
+        Dependency::Workspace(workspace) => workspace.optional.as_ref(),
+        Dependency::Detailed(detail) => detail.optional.as_ref(),
+    };
+    optional.is_some_and(|o| o.value)
+}
+
+impl FeatureGraph {
+    /// Every feature name, including implicit ones, in sorted order.
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.features.keys().map(String::as_str)
+    }
+
+    /// What `feature` enables, with the span of each value in the manifest.
+    pub fn values(&self, feature: &str) -> Option<&[Spanned<FeatureValue>]> {
+        self.features.get(feature).map(Vec::as_slice)
+    }
+
+    /// Whether `feature` is the implicit feature of an optional dependency.
+    pub fn is_implicit(&self, feature: &str) -> bool {
+        self.implicit.contains(feature)
+    }
+
+    /// Compute what building with `requested` features enables.
+    ///
+    /// Requested values use the syntax of `cargo build --features`: feature
+    /// names and `dep/feature`. The `default` feature is included when
+    /// `default_features` is true. Fails if a requested feature or dependency
+    /// does not exist.
+    pub fn activate(
+        &self,
+        requested: &[&str],
+        default_features: bool,
+    ) -> Result<Activation, Error> {
+        let mut queue = Vec::new();
+        for request in requested {
+            let value = FeatureValue::parse(request);
+            let known = match &value {
+                FeatureValue::Feature(name) => self.features.contains_key(name),
+                FeatureValue::Dep(dep) | FeatureValue::DepFeature { dep, .. } => {
+                    self.dependencies.contains_key(dep)
+                }
+            };
+            if !known {
+                return Err(Error::UnknownFeature {
+                    feature: request.to_string(),
+                });
+            }
+            queue.push(value);
+        }
+        if default_features && self.features.contains_key("default") {
+            queue.push(FeatureValue::Feature("default".to_owned()));
+        }
+
+        let mut activation = Activation::default();
+        let mut weak = Vec::new();
+        while let Some(value) = queue.pop() {
+            match value {
+                FeatureValue::Feature(name) => {
+                    if let Some(values) = self.features.get(&name)
+                        && activation.features.insert(name)
+                    {
+                        queue.extend(values.iter().map(|v| v.value.clone()));
+                    }
+                }
+                FeatureValue::Dep(dep) => {
+                    if self.dependencies.get(&dep) == Some(&true) {
+                        activation.dependencies.insert(dep);
+                    }
+                }
+                FeatureValue::DepFeature {
+                    dep,
+                    feature,
+                    weak: true,
+                } => {
+                    weak.push((dep, feature));
+                }
+                FeatureValue::DepFeature {
+                    dep,
+                    feature,
+                    weak: false,
+                } => {
+                    if self.implicit.contains(&dep) {
+                        queue.push(FeatureValue::Feature(dep.clone()));
+                    }
+                    queue.push(FeatureValue::Dep(dep.clone()));
+                    activation
+                        .dependency_features
+                        .entry(dep)
+                        .or_default()
+                        .insert(feature);
+                }
+            }
+        }
+
+        // Weak features only apply to dependencies something else enabled.
+        for (dep, feature) in weak {
+            let optional = self.dependencies.get(&dep) == Some(&true);
+            if !optional || activation.dependencies.contains(&dep) {
+                activation
+                    .dependency_features
+                    .entry(dep)
+                    .or_default()
+                    .insert(feature);
+            }
+        }
+        Ok(activation)
+    }
+
+    /// Feature values that refer to features or dependencies that do not
+    /// exist, or cannot be enabled, each tagged with its span.
+    pub fn dangling(&self) -> Vec<ValidationIssue> {
+        let mut issues = Vec::new();
+        for (feature, values) in &self.features {
+            for value in values {
+                let Some(reason) = self.dangling_reason(&value.value) else {
+                    continue;
+                };
+                issues.push(ValidationIssue {
+                    severity: Severity::Error,
+                    key: format!("features.{}", toml_key(feature)),
+                    message: format!(
+                        "feature `{feature}` includes `{}`, but {reason}",
+                        value.value
+                    ),
+                    span: value.span,
+                });
+            }
+        }
+        issues
+    }
+
+    fn dangling_reason(&self, value: &FeatureValue) -> Option<String> {
+        let dep = match value {
+            FeatureValue::Feature(name) => {
+                return match self.dependencies.get(name) {
+                    _ if self.features.contains_key(name) => None,
+                    Some(false) => Some(format!("`{name}` is not an optional dependency")),
+                    Some(true) => Some(format!(
+                        "the implicit feature of `{name}` is disabled by a `dep:{name}` reference; use `dep:{name}` here too"
+                    )),
+                    None => Some(format!("`{name}` is neither a feature nor a dependency")),
+                };
+            }
+            FeatureValue::Dep(dep) | FeatureValue::DepFeature { dep, .. } => dep,
+        };
+        match (self.dependencies.get(dep), value) {
+            (None, _) => Some(format!("`{dep}` is not a dependency")),
+            (Some(false), FeatureValue::Dep(_)) => {
+                Some(format!("`{dep}` is not an optional dependency"))
+            }
+            (Some(false), FeatureValue::DepFeature { weak: true, .. }) => Some(format!(
+                "`{dep}` is not an optional dependency, so `?` has no effect"
+            )),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MANIFEST: &str = r#"
+[package]
+name = "demo"
+
+[features]
+default = ["std"]
+std = ["serde?/std", "log/std"]
+derive = ["dep:serde", "serde/derive"]
+tracing = ["dep:tracing", "missing", "dep:nope", "anyhow/backtrace"]
+
+[dependencies]
+serde = { version = "1", optional = true }
+log = "0.4"
+rand = { version = "0.8", optional = true }
+tracing = { version = "0.1", optional = true }
+anyhow = "1"
+
+[dev-dependencies]
+criterion = "0.5"
+"#;
+
+    #[test]
+    fn parse_feature_values() {
+        assert_eq!(
+            FeatureValue::parse("dep:serde"),
+            FeatureValue::Dep("serde".to_owned())
+        );
+        let weak = FeatureValue::parse("serde?/derive");
+        assert_eq!(
+            weak,
+            FeatureValue::DepFeature {
+                dep: "serde".to_owned(),
+                feature: "derive".to_owned(),
+                weak: true,
+            }
+        );
+        assert_eq!(weak.to_string(), "serde?/derive");
+    }
+
+    #[test]
+    fn activate_features() {
+        let manifest = CargoToml::parse(MANIFEST).unwrap();
+        let graph = manifest.feature_graph();
+
+        // `serde` is referenced with `dep:`, so only `rand` gets an implicit feature.
+        assert!(graph.is_implicit("rand"));
+        assert!(graph.values("serde").is_none());
+
+        let defaults = graph.activate(&[], true).unwrap();
+        assert_eq!(
+            defaults.features,
+            BTreeSet::from(["default".into(), "std".into()])
+        );
+        assert!(defaults.dependencies.is_empty());
+        assert_eq!(
+            defaults.dependency_features,
+            BTreeMap::from([("log".into(), BTreeSet::from(["std".into()]))])
+        );
+
+        let derive = graph.activate(&["derive", "rand"], true).unwrap();
+        assert_eq!(
+            derive.dependencies,
+            BTreeSet::from(["rand".into(), "serde".into()])
+        );
+        assert_eq!(
+            derive.dependency_features["serde"],
+            BTreeSet::from(["derive".into(), "std".into()])
+        );
+
+        assert!(matches!(
+            graph.activate(&["nope"], false),
+            Err(Error::UnknownFeature { .. })
+        ));
+    }
+
+    #[test]
+    fn dangling_references() {
+        let source = MANIFEST.replace("\"anyhow/backtrace\"", "\"dep:criterion\"");
+        let manifest = CargoToml::parse(&source).unwrap();
+        let issues = manifest.feature_graph().dangling();
+        let found: Vec<_> = issues
+            .iter()
+            .map(|i| {
+                let span = i.span.unwrap();
+                &source[span.offset as usize..][..span.len as usize]
+            })
+            .collect();
+        assert_eq!(found, ["\"missing\"", "\"dep:nope\"", "\"dep:criterion\""]);
+        assert!(
+            issues[2]
+                .message
+                .ends_with("`criterion` is not an optional dependency")
+        );
+    }
+}
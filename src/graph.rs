@@ -0,0 +1,234 @@
+//! Dependency graph over `Cargo.lock` packages.
+
+use std::collections::{HashMap, VecDeque};
+
+use crate::{CargoLock, Error, LockPackage};
+
+/// The dependency graph described by a [`CargoLock`].
+///
+/// Built with [`CargoLock::graph`]. Every dependency reference is resolved up
+/// front, so queries cannot fail.
+#[derive(Debug, Clone)]
+pub struct LockGraph<'a> {
+    lock: &'a CargoLock,
+    index: HashMap<(&'a str, &'a str, Option<&'a str>), usize>,
+    dependencies: Vec<Vec<usize>>,
+    dependents: Vec<Vec<usize>>,
+}
+
+impl CargoLock {
+    /// Build the dependency graph of this lockfile.
+    pub fn graph(&self) -> Result<LockGraph<'_>, Error> {
+        let index: HashMap<_, _> = self
+            .packages
+            .iter()
+            .enumerate()
+            .map(|(i, p)| (key(p), i))
+            .collect();
+
+        let mut dependencies = vec![Vec::new(); self.packages.len()];
+        let mut dependents = vec![Vec::new(); self.packages.len()];
+        for (from, package) in self.packages.iter().enumerate() {
+            for dependency in self.dependencies_of(package)? {
+                let to = index[&key(dependency)];
+                dependencies[from].push(to);
+                dependents[to].push(from);
+            }
+        }
+
+        Ok(LockGraph {
+            lock: self,
+            index,
+            dependencies,
+            dependents,
+        })
+    }
+}
+
+fn key(package: &LockPackage) -> (&str, &str, Option<&str>) {
+    (
+        package.name.as_str(),
+        package.version.as_str(),
+        package.source.as_deref(),
+    )
+}
+
+impl<'a> LockGraph<'a> {
+    /// Packages that `package` depends on directly.
+    pub fn dependencies(&self, package: &LockPackage) -> Vec<&'a LockPackage> {
+        self.packages(
+            self.node(package)
+                .map_or(&[][..], |n| &self.dependencies[n]),
+        )
+    }
+
+    /// Packages that depend on `package` directly.
+    pub fn reverse_dependencies(&self, package: &LockPackage) -> Vec<&'a LockPackage> {
+        self.packages(self.node(package).map_or(&[][..], |n| &self.dependents[n]))
+    }
+
+    /// Every package reachable from `package`, in breadth-first order.
+    ///
+    /// `package` itself is not included.
+    pub fn transitive_dependencies(&self, package: &LockPackage) -> Vec<&'a LockPackage> {
+        let Some(start) = self.node(package) else {
+            return Vec::new();
+        };
+        let mut seen = vec![false; self.lock.packages.len()];
+        seen[start] = true;
+        let mut queue = VecDeque::from([start]);
+        let mut reached = Vec::new();
+        while let Some(node) = queue.pop_front() {
+            for &next in &self.dependencies[node] {
+                if !seen[next] {
+                    seen[next] = true;
+                    reached.push(next);
+                    queue.push_back(next);
+                }
+            }
+        }
+        self.packages(&reached)
+    }
+
+    /// Every package, ordered so that dependencies come before their dependents.
+    ///
+    /// Lockfiles can contain cycles through dev-dependencies; those are broken
+    /// at the edge that closes the cycle.
+    pub fn topological_order(&self) -> Vec<&'a LockPackage> {
+        let mut visited = vec![false; self.lock.packages.len()];
+        let mut order = Vec::with_capacity(self.lock.packages.len());
+        for start in 0..self.lock.packages.len() {
+            if visited[start] {
+                continue;
+            }
+            visited[start] = true;
+            let mut stack = vec![(start, 0)];
+            while let Some((node, edge)) = stack.pop() {
+                match self.dependencies[node].get(edge) {
+                    Some(&next) => {
+                        stack.push((node, edge + 1));
+                        if !visited[next] {
+                            visited[next] = true;
+                            stack.push((next, 0));
+                        }
+                    }
+                    None => order.push(node),
+                }
+            }
+        }
+        self.packages(&order)
+    }
+
+    /// Packages without a source: the workspace members, and any path
+    /// dependencies outside the workspace, which the lockfile does not tell
+    /// apart. [`CargoWorkspace::members`](crate::CargoWorkspace::members) has
+    /// the members themselves.
+    pub fn roots(&self) -> Vec<&'a LockPackage> {
+        self.lock.packages.iter().filter(|p| p.is_path()).collect()
+    }
+
+    /// For each root that depends on `package`, the shortest dependency chain
+    /// from that root to `package`.
+    ///
+    /// Each path starts at one of [`LockGraph::roots`] and ends at `package`. This answers "why is
+    /// this crate in the build", like `cargo tree -i`.
+    pub fn paths_to(&self, package: &LockPackage) -> Vec<Vec<&'a LockPackage>> {
+        let Some(target) = self.node(package) else {
+            return Vec::new();
+        };
+
+        // Breadth-first search over reverse edges records, for every package
+        // that reaches the target, its next hop along a shortest path.
+        let mut next_hop = vec![None; self.lock.packages.len()];
+        next_hop[target] = Some(target);
+        let mut queue = VecDeque::from([target]);
+        while let Some(node) = queue.pop_front() {
+            for &dependent in &self.dependents[node] {
+                if next_hop[dependent].is_none() {
+                    next_hop[dependent] = Some(node);
+                    queue.push_back(dependent);
+                }
+            }
+        }
+
+        self.lock
+            .packages
+            .iter()
+            .enumerate()
+            .filter(|(i, p)| p.is_path() && next_hop[*i].is_some())
+            .map(|(mut node, _)| {
+                let mut path = vec![node];
+                while node != target {
+                    node = next_hop[node].unwrap_or(target);
+                    path.push(node);
+                }
+                self.packages(&path)
+            })
+            .collect()
+    }
+
+    /// The index of `package` in [`CargoLock::packages`].
+    pub(crate) fn node(&self, package: &LockPackage) -> Option<usize> {
+        self.index.get(&key(package)).copied()
+    }
+
+    fn packages(&self, nodes: &[usize]) -> Vec<&'a LockPackage> {
+        nodes.iter().map(|&n| &self.lock.packages[n]).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LOCK: &str = r#"
+version = 4
+
+[[package]]
+name = "app"
+version = "0.1.0"
+dependencies = ["cli", "log"]
+
+[[package]]
+name = "cli"
+version = "0.1.0"
+dependencies = ["log", "memchr"]
+
+[[package]]
+name = "log"
+version = "0.4.20"
+source = "registry+https://github.com/rust-lang/crates.io-index"
+dependencies = ["memchr"]
+
+[[package]]
+name = "memchr"
+version = "2.7.1"
+source = "registry+https://github.com/rust-lang/crates.io-index"
+"#;
+
+    fn names(packages: Vec<&LockPackage>) -> Vec<&str> {
+        packages.iter().map(|p| p.name.as_str()).collect()
+    }
+
+    #[test]
+    fn graph_queries() {
+        let lock = CargoLock::parse(LOCK).unwrap();
+        let graph = lock.graph().unwrap();
+        let app = lock.find_by_name("app").unwrap();
+        let memchr = lock.find_by_name("memchr").unwrap();
+
+        assert_eq!(names(graph.dependencies(app)), ["cli", "log"]);
+        assert_eq!(
+            names(graph.transitive_dependencies(app)),
+            ["cli", "log", "memchr"]
+        );
+        assert_eq!(names(graph.reverse_dependencies(memchr)), ["cli", "log"]);
+        assert_eq!(
+            names(graph.topological_order()),
+            ["memchr", "log", "cli", "app"]
+        );
+
+        let paths: Vec<_> = graph.paths_to(memchr).into_iter().map(names).collect();
+        assert_eq!(paths, [vec!["app", "cli", "memchr"], vec!["cli", "memchr"]]);
+    }
+}
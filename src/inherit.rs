@@ -0,0 +1,640 @@
+//! Workspace inheritance resolution.
+
+use std::collections::HashMap;
+
+use camino::{Utf8Component, Utf8Path, Utf8PathBuf};
+
+use crate::workspace::normalize;
+use crate::{
+    BoolOrVec, BoolOrVecOrWorkspace, CargoToml, Dependency, DependencyDetail, Edition,
+    EditionOrWorkspace, Error, Lints, Resolver, Spanned, StringOrBool, StringOrBoolOrWorkspace,
+    StringOrWorkspace, VecOrWorkspace, Workspace, WorkspacePackage, WorkspaceRef,
+};
+
+/// A `[package]` section with every `{ workspace = true }` field replaced by
+/// the value inherited from the workspace root.
+///
+/// Produced by [`CargoToml::resolve`]. Paths are relative to the member's
+/// directory, inherited ones included.
+#[derive(Debug, Clone, Default)]
+pub struct ResolvedPackage {
+    /// The package name.
+    pub name: Option<String>,
+    /// The package version.
+    pub version: Option<String>,
+    /// People or organizations considered package authors.
+    pub authors: Option<Vec<String>>,
+    /// The Rust edition used for compilation.
+    pub edition: Option<Edition>,
+    /// The minimum supported Rust toolchain version.
+    pub rust_version: Option<String>,
+    /// A short text blurb about the package.
+    pub description: Option<String>,
+    /// URL to the crate's documentation website.
+    pub documentation: Option<String>,
+    /// The README setting.
+    pub readme: Option<Readme>,
+    /// URL of the package's home page.
+    pub homepage: Option<String>,
+    /// URL to the package's source repository.
+    pub repository: Option<String>,
+    /// SPDX 2.3 license expression.
+    pub license: Option<String>,
+    /// Path to a license text file.
+    pub license_file: Option<String>,
+    /// Searchable keywords for registry discoverability.
+    pub keywords: Option<Vec<String>>,
+    /// Categories from crates.io's predefined list.
+    pub categories: Option<Vec<String>>,
+    /// Gitignore-style patterns for files to exclude when publishing.
+    pub exclude: Option<Vec<String>>,
+    /// Gitignore-style patterns for files to explicitly include when publishing.
+    pub include: Option<Vec<String>>,
+    /// Registries the package may be published to.
+    ///
+    /// `None` means any registry; `publish = false` resolves to an empty list.
+    pub publish: Option<Vec<String>>,
+    /// The build script setting.
+    pub build: Option<BuildScript>,
+    /// Name of the native library being linked by a build script.
+    pub links: Option<String>,
+    /// The default binary selected by `cargo run`.
+    pub default_run: Option<String>,
+    /// Whether the library target is discovered automatically.
+    pub autolib: Option<bool>,
+    /// Whether binary targets are discovered automatically.
+    pub autobins: Option<bool>,
+    /// Whether example targets are discovered automatically.
+    pub autoexamples: Option<bool>,
+    /// Whether test targets are discovered automatically.
+    pub autotests: Option<bool>,
+    /// Whether benchmark targets are discovered automatically.
+    pub autobenches: Option<bool>,
+    /// The dependency resolver version.
+    pub resolver: Option<Resolver>,
+    /// The `package.metadata` table for external tools.
+    pub metadata: Option<facet_value::Value>,
+    /// Regular dependencies, with workspace dependencies merged in.
+    pub dependencies: HashMap<String, DependencyDetail>,
+    /// Development dependencies, with workspace dependencies merged in.
+    pub dev_dependencies: HashMap<String, DependencyDetail>,
+    /// Build script dependencies, with workspace dependencies merged in.
+    pub build_dependencies: HashMap<String, DependencyDetail>,
+    /// Target-specific dependencies, keyed like [`CargoToml::target`].
+    pub target: HashMap<String, ResolvedTargetSpec>,
+    /// Lint configuration, taken from `[workspace.lints]` when inherited.
+    pub lints: Option<Lints>,
+}
+
+/// A resolved `readme` setting.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Readme {
+    /// Path to the README file (`readme = true` resolves to `README.md`).
+    Path(String),
+    /// README detection disabled with `readme = false`.
+    Disabled,
+}
+
+/// A resolved `build` setting.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BuildScript {
+    /// Path to the build script (`build = true` resolves to `build.rs`).
+    Path(String),
+    /// Build script detection disabled with `build = false`.
+    Disabled,
+}
+
+/// Target-specific dependencies with workspace dependencies merged in.
+#[derive(Debug, Clone, Default)]
+pub struct ResolvedTargetSpec {
+    /// Target-specific regular dependencies.
+    pub dependencies: HashMap<String, DependencyDetail>,
+    /// Target-specific development dependencies.
+    pub dev_dependencies: HashMap<String, DependencyDetail>,
+    /// Target-specific build dependencies.
+    pub build_dependencies: HashMap<String, DependencyDetail>,
+}
+
+impl CargoToml {
+    /// Resolve workspace inheritance for this manifest against its workspace `root`.
+    ///
+    /// `root` is the manifest containing the `[workspace]` table; for a root package
+    /// it can be the manifest itself. Fails if a field is marked
+    /// `{ workspace = true }` but the root does not define it.
+    ///
+    /// Like Cargo, inherited `path`, `readme` and `license-file` values are
+    /// rebased from `root_dir` onto `member_dir`, the directories of the two
+    /// manifests, so every path in the result is relative to the member.
+    pub fn resolve(
+        &self,
+        root: &CargoToml,
+        member_dir: &Utf8Path,
+        root_dir: &Utf8Path,
+    ) -> Result<ResolvedPackage, Error> {
+        let workspace = root.workspace.as_ref();
+        let ws = workspace.and_then(|w| w.package.as_ref());
+        let mut resolved = ResolvedPackage::default();
+
+        if let Some(pkg) = &self.package {
+            let string =
+                |field: &Option<StringOrWorkspace>,
+                 key: &str,
+                 get: fn(&WorkspacePackage) -> Option<&Spanned<String>>| {
+                    field
+                        .as_ref()
+                        .map(|f| f.resolve(key, ws.and_then(get)))
+                        .transpose()
+                };
+            let list =
+                |field: &Option<VecOrWorkspace>,
+                 key: &str,
+                 get: fn(&WorkspacePackage) -> Option<&Spanned<Vec<String>>>| {
+                    field
+                        .as_ref()
+                        .map(|f| f.resolve(key, ws.and_then(get)))
+                        .transpose()
+                };
+
+            resolved.name = pkg.name.as_ref().map(|n| n.value.clone());
+            resolved.version = string(&pkg.version, "version", |w| w.version.as_ref())?;
+            resolved.authors = list(&pkg.authors, "authors", |w| w.authors.as_ref())?;
+            resolved.edition = pkg
+                .edition
+                .as_ref()
+                .map(|e| e.resolve(ws.and_then(|w| w.edition.as_ref())))
+                .transpose()?;
+            resolved.rust_version = string(&pkg.rust_version, "rust-version", |w| {
+                w.rust_version.as_ref()
+            })?;
+            resolved.description =
+                string(&pkg.description, "description", |w| w.description.as_ref())?;
+            resolved.documentation = string(&pkg.documentation, "documentation", |w| {
+                w.documentation.as_ref()
+            })?;
+            let ws_readme = ws.and_then(|w| w.readme.as_ref());
+            resolved.readme = pkg
+                .readme
+                .as_ref()
+                .map(|r| r.resolve(ws_readme))
+                .transpose()?;
+            // `readme = true` names the member's own README, so only an
+            // inherited path is rebased.
+            if let Some(StringOrBoolOrWorkspace::Workspace(_)) = &pkg.readme
+                && let Some(StringOrBool::String(_)) = ws_readme
+                && let Some(Readme::Path(path)) = &mut resolved.readme
+            {
+                *path = rebase(path, member_dir, root_dir);
+            }
+            resolved.homepage = string(&pkg.homepage, "homepage", |w| w.homepage.as_ref())?;
+            resolved.repository = string(&pkg.repository, "repository", |w| w.repository.as_ref())?;
+            resolved.license = string(&pkg.license, "license", |w| w.license.as_ref())?;
+            resolved.license_file = string(&pkg.license_file, "license-file", |w| {
+                w.license_file.as_ref()
+            })?;
+            if let Some(StringOrWorkspace::Workspace(_)) = &pkg.license_file
+                && let Some(path) = &mut resolved.license_file
+            {
+                *path = rebase(path, member_dir, root_dir);
+            }
+            resolved.keywords = list(&pkg.keywords, "keywords", |w| w.keywords.as_ref())?;
+            resolved.categories = list(&pkg.categories, "categories", |w| w.categories.as_ref())?;
+            resolved.exclude = list(&pkg.exclude, "exclude", |w| w.exclude.as_ref())?;
+            resolved.include = list(&pkg.include, "include", |w| w.include.as_ref())?;
+            resolved.publish = match &pkg.publish {
+                None => None,
+                Some(p) => p.resolve(ws.and_then(|w| w.publish.as_ref()))?,
+            };
+
+            let value = |field: &Option<Spanned<String>>| field.as_ref().map(|f| f.value.clone());
+            let flag = |field: &Option<Spanned<bool>>| field.as_ref().map(|f| f.value);
+            resolved.build = pkg.build.as_ref().map(|build| match build {
+                StringOrBool::String(path) => BuildScript::Path(path.value.clone()),
+                StringOrBool::Bool(b) if b.value => BuildScript::Path("build.rs".to_owned()),
+                StringOrBool::Bool(_) => BuildScript::Disabled,
+            });
+            resolved.links = value(&pkg.links);
+            resolved.default_run = value(&pkg.default_run);
+            resolved.autolib = flag(&pkg.autolib);
+            resolved.autobins = flag(&pkg.autobins);
+            resolved.autoexamples = flag(&pkg.autoexamples);
+            resolved.autotests = flag(&pkg.autotests);
+            resolved.autobenches = flag(&pkg.autobenches);
+            resolved.resolver = pkg.resolver;
+            resolved.metadata = pkg.metadata.clone();
+        }
+
+        let table = |table: &Option<HashMap<String, Dependency>>| {
+            resolve_table(table.as_ref(), workspace, member_dir, root_dir)
+        };
+        resolved.dependencies = table(&self.dependencies)?;
+        resolved.dev_dependencies = table(&self.dev_dependencies)?;
+        resolved.build_dependencies = table(&self.build_dependencies)?;
+
+        for (key, spec) in self.target.iter().flatten() {
+            let spec = ResolvedTargetSpec {
+                dependencies: table(&spec.dependencies)?,
+                dev_dependencies: table(&spec.dev_dependencies)?,
+                build_dependencies: table(&spec.build_dependencies)?,
+            };
+            resolved.target.insert(key.clone(), spec);
+        }
+
+        resolved.lints = match &self.lints {
+            Some(lints) if lints.workspace.as_ref().is_some_and(|w| w.value) => Some(
+                workspace
+                    .and_then(|w| w.lints.clone())
+                    .ok_or_else(|| missing("workspace.lints", lints.workspace.as_ref()))?,
+            ),
+            other => other.clone(),
+        };
+
+        Ok(resolved)
+    }
+}
+
+fn resolve_table(
+    table: Option<&HashMap<String, Dependency>>,
+    workspace: Option<&Workspace>,
+    member_dir: &Utf8Path,
+    root_dir: &Utf8Path,
+) -> Result<HashMap<String, DependencyDetail>, Error> {
+    table
+        .into_iter()
+        .flatten()
+        .map(|(name, dep)| {
+            let detail = dep.resolve(name, workspace, member_dir, root_dir)?;
+            Ok((name.clone(), detail))
+        })
+        .collect()
+}
+
+impl Dependency {
+    /// Resolve this dependency into a detailed specification.
+    ///
+    /// `{ workspace = true }` entries are looked up by `name` in
+    /// `[workspace.dependencies]` and merged the way Cargo does: features are
+    /// unioned, `optional` comes from the member, and `default-features = true`
+    /// in the member can only re-enable defaults the workspace turned off.
+    ///
+    /// An inherited `path` is rebased from `root_dir` onto `member_dir`, the
+    /// directories of the workspace root and member manifests, unless it is
+    /// relative to a `base`.
+    pub fn resolve(
+        &self,
+        name: &str,
+        workspace: Option<&Workspace>,
+        member_dir: &Utf8Path,
+        root_dir: &Utf8Path,
+    ) -> Result<DependencyDetail, Error> {
+        let member = match self {
+            Dependency::Version(version) => {
+                return Ok(DependencyDetail {
+                    version: Some(version.clone()),
+                    ..Default::default()
+                });
+            }
+            Dependency::Detailed(detail) => return Ok(detail.clone()),
+            Dependency::Workspace(member) => member,
+        };
+
+        let key = format!("workspace.dependencies.{name}");
+        check_marker(&key, &member.workspace)?;
+
+        let mut detail = match workspace
+            .and_then(|w| w.dependencies.as_ref())
+            .and_then(|deps| deps.get(name))
+        {
+            None => return Err(missing(&key, Some(&member.workspace))),
+            Some(Dependency::Version(version)) => DependencyDetail {
+                version: Some(version.clone()),
+                ..Default::default()
+            },
+            Some(Dependency::Detailed(detail)) => detail.clone(),
+            Some(Dependency::Workspace(inner)) => {
+                return Err(Error::InvalidInheritance {
+                    key,
+                    reason: "workspace dependencies cannot themselves be inherited".to_owned(),
+                    span: inner.workspace.span,
+                });
+            }
+        };
+
+        if let Some(features) = &member.features {
+            let merged = detail.features.get_or_insert_with(|| Spanned {
+                value: Vec::new(),
+                span: features.span,
+            });
+            for feature in &features.value {
+                if !merged.value.contains(feature) {
+                    merged.value.push(feature.clone());
+                }
+            }
+        }
+
+        detail.optional = member.optional.clone();
+
+        if detail.base.is_none()
+            && let Some(path) = &mut detail.path
+        {
+            path.value = rebase(&path.value, member_dir, root_dir);
+        }
+
+        let inherited_defaults = detail.default_features.as_ref().is_none_or(|d| d.value);
+        if !inherited_defaults && let Some(default_features) = &member.default_features {
+            detail.default_features = Some(default_features.clone());
+        }
+
+        Ok(detail)
+    }
+}
+
+impl StringOrWorkspace {
+    /// Resolve this value, using `inherited` when it is `{ workspace = true }`.
+    ///
+    /// `key` names the `[workspace.package]` field, for error reporting.
+    pub fn resolve(&self, key: &str, inherited: Option<&Spanned<String>>) -> Result<String, Error> {
+        match self {
+            StringOrWorkspace::String(s) => Ok(s.value.clone()),
+            StringOrWorkspace::Workspace(r) => Ok(inherit(key, r, inherited)?.value.clone()),
+        }
+    }
+}
+
+impl VecOrWorkspace {
+    /// Resolve this value, using `inherited` when it is `{ workspace = true }`.
+    ///
+    /// `key` names the `[workspace.package]` field, for error reporting.
+    pub fn resolve(
+        &self,
+        key: &str,
+        inherited: Option<&Spanned<Vec<String>>>,
+    ) -> Result<Vec<String>, Error> {
+        match self {
+            VecOrWorkspace::Values(v) => Ok(v.value.clone()),
+            VecOrWorkspace::Workspace(r) => Ok(inherit(key, r, inherited)?.value.clone()),
+        }
+    }
+}
+
+impl EditionOrWorkspace {
+    /// Resolve this value, using `inherited` when it is `{ workspace = true }`.
+    pub fn resolve(&self, inherited: Option<&Spanned<Edition>>) -> Result<Edition, Error> {
+        match self {
+            EditionOrWorkspace::Edition(e) => Ok(e.value),
+            EditionOrWorkspace::Workspace(r) => Ok(inherit("edition", r, inherited)?.value),
+        }
+    }
+}
+
+impl StringOrBoolOrWorkspace {
+    /// Resolve a `readme` value, using `inherited` when it is `{ workspace = true }`.
+    pub fn resolve(&self, inherited: Option<&StringOrBool>) -> Result<Readme, Error> {
+        match self {
+            StringOrBoolOrWorkspace::String(s) => Ok(Readme::Path(s.value.clone())),
+            StringOrBoolOrWorkspace::Bool(b) => Ok(Readme::from_bool(b.value)),
+            StringOrBoolOrWorkspace::Workspace(r) => match inherit("readme", r, inherited)? {
+                StringOrBool::String(s) => Ok(Readme::Path(s.value.clone())),
+                StringOrBool::Bool(b) => Ok(Readme::from_bool(b.value)),
+            },
+        }
+    }
+}
+
+impl Readme {
+    fn from_bool(enabled: bool) -> Self {
+        if enabled {
+            Readme::Path("README.md".to_owned())
+        } else {
+            Readme::Disabled
+        }
+    }
+}
+
+impl BoolOrVecOrWorkspace {
+    /// Resolve a `publish` value, using `inherited` when it is `{ workspace = true }`.
+    pub fn resolve(&self, inherited: Option<&BoolOrVec>) -> Result<Option<Vec<String>>, Error> {
+        match self {
+            BoolOrVecOrWorkspace::Bool(b) => Ok(BoolOrVec::Bool(b.clone()).registries()),
+            BoolOrVecOrWorkspace::Vec(v) => Ok(Some(v.value.clone())),
+            BoolOrVecOrWorkspace::Workspace(r) => {
+                Ok(inherit("publish", r, inherited)?.registries())
+            }
+        }
+    }
+}
+
+impl BoolOrVec {
+    /// The registries a `publish` value allows, or `None` for any registry.
+    pub fn registries(&self) -> Option<Vec<String>> {
+        match self {
+            BoolOrVec::Bool(b) if b.value => None,
+            BoolOrVec::Bool(_) => Some(Vec::new()),
+            BoolOrVec::Vec(v) => Some(v.value.clone()),
+        }
+    }
+}
+
+/// Rewrite `path`, relative to `root_dir`, to be relative to `member_dir`.
+///
+/// Falls back to joining `path` onto `root_dir` when there is no relative
+/// path between the two, say when only one of them is absolute.
+fn rebase(path: &str, member_dir: &Utf8Path, root_dir: &Utf8Path) -> String {
+    let member_dir = normalize(member_dir);
+    let target = normalize(&root_dir.join(path));
+    let common = member_dir
+        .components()
+        .zip(target.components())
+        .take_while(|(a, b)| a == b)
+        .count();
+    let climb = member_dir.components().skip(common);
+    if target.is_absolute() != member_dir.is_absolute()
+        || climb
+            .clone()
+            .any(|c| !matches!(c, Utf8Component::Normal(_)))
+    {
+        return target.into_string();
+    }
+    let mut rebased: Utf8PathBuf = climb.map(|_| "..").collect();
+    rebased.extend(target.components().skip(common));
+    match rebased.into_string() {
+        empty if empty.is_empty() => ".".to_owned(),
+        rebased => rebased,
+    }
+}
+
+pub(crate) fn inherit<'a, T>(
+    key: &str,
+    marker: &WorkspaceRef,
+    inherited: Option<&'a T>,
+) -> Result<&'a T, Error> {
+    let key = format!("workspace.package.{key}");
+    check_marker(&key, &marker.workspace)?;
+    inherited.ok_or_else(|| missing(&key, Some(&marker.workspace)))
+}
+
+fn check_marker(key: &str, marker: &Spanned<bool>) -> Result<(), Error> {
+    if marker.value {
+        Ok(())
+    } else {
+        Err(Error::InvalidInheritance {
+            key: key.to_owned(),
+            reason: "`workspace` must be `true`".to_owned(),
+            span: marker.span,
+        })
+    }
+}
+
+fn missing(key: &str, marker: Option<&Spanned<bool>>) -> Error {
+    Error::MissingInheritedKey {
+        key: key.to_owned(),
+        span: marker.and_then(|m| m.span),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ROOT: &str = r#"
+[workspace]
+members = ["member"]
+
+[workspace.package]
+version = "1.2.3"
+edition = "2021"
+license = "MIT OR Apache-2.0"
+publish = false
+
+[workspace.dependencies]
+serde = { version = "1.0", default-features = false, features = ["derive"] }
+anyhow = "1.0"
+"#;
+
+    #[test]
+    fn resolve_inherited_fields() {
+        let root = CargoToml::parse(ROOT).unwrap();
+        let member = CargoToml::parse(
+            r#"
+[package]
+name = "member"
+version.workspace = true
+edition.workspace = true
+license = "MIT"
+publish.workspace = true
+build = false
+links = "z"
+autobins = false
+resolver = "2"
+
+[package.metadata.docs]
+all-features = true
+
+[dependencies]
+serde = { workspace = true, features = ["std"], default-features = true, optional = true }
+anyhow.workspace = true
+"#,
+        )
+        .unwrap();
+
+        let resolved = member.resolve(&root, "member".into(), "".into()).unwrap();
+        assert_eq!(resolved.name.as_deref(), Some("member"));
+        assert_eq!(resolved.version.as_deref(), Some("1.2.3"));
+        assert_eq!(resolved.edition, Some(Edition::E2021));
+        assert_eq!(resolved.license.as_deref(), Some("MIT"));
+        assert_eq!(resolved.publish, Some(vec![]));
+        assert_eq!(resolved.build, Some(BuildScript::Disabled));
+        assert_eq!(resolved.links.as_deref(), Some("z"));
+        assert_eq!(resolved.autobins, Some(false));
+        assert_eq!(resolved.autolib, None);
+        assert_eq!(resolved.resolver, Some(Resolver::V2));
+        assert!(resolved.metadata.is_some());
+
+        let serde = &resolved.dependencies["serde"];
+        assert_eq!(serde.version.as_ref().unwrap().value, "1.0");
+        assert_eq!(serde.features.as_ref().unwrap().value, ["derive", "std"]);
+        assert!(serde.default_features.as_ref().unwrap().value);
+        assert!(serde.optional.as_ref().unwrap().value);
+
+        let anyhow = &resolved.dependencies["anyhow"];
+        assert_eq!(anyhow.version.as_ref().unwrap().value, "1.0");
+    }
+
+    #[test]
+    fn resolve_missing_inherited_key() {
+        let root = CargoToml::parse(ROOT).unwrap();
+        let member = CargoToml::parse(
+            r#"
+[package]
+name = "member"
+rust-version.workspace = true
+"#,
+        )
+        .unwrap();
+
+        match member.resolve(&root, "member".into(), "".into()) {
+            Err(Error::MissingInheritedKey { key, span }) => {
+                assert_eq!(key, "workspace.package.rust-version");
+                assert!(span.is_some());
+            }
+            other => panic!("expected MissingInheritedKey, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn rebase_inherited_paths() {
+        let root = CargoToml::parse(
+            r#"
+[workspace]
+members = ["crates/*"]
+
+[workspace.package]
+readme = "docs/README.md"
+license-file = "LICENSE"
+
+[workspace.dependencies]
+util = { path = "crates/util" }
+shared = { base = "workspace", path = "shared" }
+"#,
+        )
+        .unwrap();
+        let member = CargoToml::parse(
+            r#"
+[package]
+name = "app"
+readme.workspace = true
+license-file.workspace = true
+
+[dependencies]
+util = { workspace = true }
+shared = { workspace = true }
+local = { path = "../local" }
+"#,
+        )
+        .unwrap();
+
+        for (member_dir, root_dir) in [("/ws/crates/app", "/ws"), ("crates/app", ".")] {
+            let resolved = member
+                .resolve(&root, member_dir.into(), root_dir.into())
+                .unwrap();
+            assert_eq!(
+                resolved.readme,
+                Some(Readme::Path("../../docs/README.md".to_owned()))
+            );
+            assert_eq!(resolved.license_file.as_deref(), Some("../../LICENSE"));
+            let path = |name: &str| {
+                resolved.dependencies[name]
+                    .path
+                    .as_ref()
+                    .unwrap()
+                    .value
+                    .clone()
+            };
+            assert_eq!(path("util"), "../util");
+            assert_eq!(path("shared"), "shared");
+            assert_eq!(path("local"), "../local");
+        }
+
+        let resolved = root.resolve(&root, "/ws".into(), "/ws".into()).unwrap();
+        assert!(resolved.dependencies.is_empty());
+    }
+}
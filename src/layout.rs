@@ -0,0 +1,286 @@
+//! The textual layout of TOML documents.
+//!
+//! facet-toml reports where values are but not where keys and tables are;
+//! this scanner recovers that, for editing and for diagnostics.
+
+use std::ops::Range;
+
+/// The layout of a TOML document: where each table header and key/value entry
+/// lives, without interpreting values.
+pub(crate) struct Scan {
+    pub(crate) tables: Vec<TableLayout>,
+    pub(crate) entries: Vec<EntryLayout>,
+}
+
+pub(crate) struct TableLayout {
+    pub(crate) path: Vec<String>,
+    pub(crate) array: bool,
+    /// Start of the header line.
+    pub(crate) start: usize,
+    /// Start of the line after the header.
+    pub(crate) body: usize,
+    /// Start of the next header line, or the end of the document.
+    pub(crate) end: usize,
+}
+
+pub(crate) struct EntryLayout {
+    pub(crate) table: usize,
+    pub(crate) key: Vec<String>,
+    /// The key as written, like `serde.version` or `"cfg(unix)"`.
+    pub(crate) key_span: Range<usize>,
+    /// Start of the comment lines directly above the entry, if any.
+    pub(crate) lead: usize,
+    /// Start of the entry's first line.
+    pub(crate) start: usize,
+    /// The value, without surrounding whitespace or trailing comment.
+    pub(crate) value: Range<usize>,
+    /// Just past the entry's last line, including its newline.
+    pub(crate) end: usize,
+}
+
+pub(crate) enum Location<'a> {
+    Entry(&'a EntryLayout),
+    Table(usize),
+}
+
+impl Scan {
+    pub(crate) fn new(src: &str) -> Self {
+        let bytes = src.as_bytes();
+        let mut tables = vec![TableLayout {
+            path: Vec::new(),
+            array: false,
+            start: 0,
+            body: 0,
+            end: src.len(),
+        }];
+        let mut entries = Vec::new();
+        let mut comments = None;
+        let mut pos = 0;
+
+        while pos < bytes.len() {
+            let line_start = pos;
+            while pos < bytes.len() && matches!(bytes[pos], b' ' | b'\t') {
+                pos += 1;
+            }
+            match bytes.get(pos) {
+                None => break,
+                Some(b'#') => {
+                    comments.get_or_insert(line_start);
+                    pos = line_end(bytes, pos);
+                }
+                Some(b'\n' | b'\r') => {
+                    comments = None;
+                    pos = line_end(bytes, pos);
+                }
+                Some(b'[') => {
+                    comments = None;
+                    let array = bytes.get(pos + 1) == Some(&b'[');
+                    let (path, _) = parse_key(src, pos + if array { 2 } else { 1 });
+                    if let Some(last) = tables.last_mut() {
+                        last.end = line_start;
+                    }
+                    pos = line_end(bytes, pos);
+                    tables.push(TableLayout {
+                        path,
+                        array,
+                        start: line_start,
+                        body: pos,
+                        end: src.len(),
+                    });
+                }
+                Some(_) => {
+                    let (key, after_key) = parse_key(src, pos);
+                    // Not a `key = value` line: the document does not parse,
+                    // and facet-toml reports why.
+                    if bytes.get(after_key) != Some(&b'=') {
+                        comments = None;
+                        pos = line_end(bytes, pos);
+                        continue;
+                    }
+                    let key_span = pos..pos + src[pos..after_key].trim_end().len();
+                    let value_start = skip_ws(bytes, after_key + 1);
+                    let (value_end, end) = scan_value(bytes, value_start);
+                    entries.push(EntryLayout {
+                        table: tables.len() - 1,
+                        key,
+                        key_span,
+                        lead: comments.take().unwrap_or(line_start),
+                        start: line_start,
+                        value: value_start..value_end,
+                        end,
+                    });
+                    pos = end;
+                }
+            }
+        }
+
+        Scan { tables, entries }
+    }
+
+    /// The non-array table with exactly this header path.
+    pub(crate) fn table(&self, path: &[&str]) -> Option<usize> {
+        self.tables.iter().position(|t| !t.array && t.path == path)
+    }
+
+    pub(crate) fn entries_in(&self, table: usize) -> impl Iterator<Item = &EntryLayout> {
+        self.entries.iter().filter(move |e| e.table == table)
+    }
+
+    /// Every place `key` in `table` is defined: `key = ...`, dotted
+    /// `key.field = ...` entries, and `[table.key]` sections.
+    pub(crate) fn locations(&self, table: &[&str], key: &str) -> Vec<Location<'_>> {
+        let mut full = table.to_vec();
+        full.push(key);
+
+        let mut locations: Vec<_> = self
+            .entries
+            .iter()
+            .filter(|e| {
+                let prefix = &self.tables[e.table].path;
+                !self.tables[e.table].array
+                    && prefix.len() <= table.len()
+                    && prefix.iter().chain(&e.key).take(full.len()).eq(&full)
+                    && prefix.len() + e.key.len() >= full.len()
+            })
+            .map(Location::Entry)
+            .collect();
+        locations.extend(
+            self.tables
+                .iter()
+                .enumerate()
+                .filter(|(_, t)| t.path.len() >= full.len() && t.path[..full.len()] == full)
+                .map(|(i, _)| Location::Table(i)),
+        );
+        locations
+    }
+}
+
+impl Location<'_> {
+    /// The full text of the definition: the entry's lines and the comments
+    /// above it, or the whole section.
+    pub(crate) fn range(&self, scan: &Scan) -> Range<usize> {
+        match self {
+            Location::Entry(entry) => entry.lead..entry.end,
+            Location::Table(t) => scan.tables[*t].start..scan.tables[*t].end,
+        }
+    }
+}
+
+/// Parse a dotted key starting at `pos`, returning its segments and the
+/// position just past it (and any trailing whitespace).
+pub(crate) fn parse_key(src: &str, mut pos: usize) -> (Vec<String>, usize) {
+    let bytes = src.as_bytes();
+    let mut segments = Vec::new();
+    loop {
+        pos = skip_ws(bytes, pos);
+        match bytes.get(pos) {
+            Some(&quote @ (b'"' | b'\'')) => {
+                let mut segment = String::new();
+                pos += 1;
+                while let Some(&b) = bytes.get(pos) {
+                    if b == quote {
+                        pos += 1;
+                        break;
+                    }
+                    if b == b'\\' && quote == b'"' {
+                        if let Some(c) = src[pos + 1..].chars().next() {
+                            segment.push(c);
+                            pos += 1 + c.len_utf8();
+                        }
+                        continue;
+                    }
+                    let c = src[pos..].chars().next().unwrap_or_default();
+                    segment.push(c);
+                    pos += c.len_utf8();
+                }
+                segments.push(segment);
+            }
+            _ => {
+                let start = pos;
+                while pos < bytes.len()
+                    && (bytes[pos].is_ascii_alphanumeric() || matches!(bytes[pos], b'_' | b'-'))
+                {
+                    pos += 1;
+                }
+                segments.push(src[start..pos].to_owned());
+            }
+        }
+        pos = skip_ws(bytes, pos);
+        if bytes.get(pos) == Some(&b'.') {
+            pos += 1;
+        } else {
+            return (segments, pos);
+        }
+    }
+}
+
+/// Scan a value starting at `pos`, returning the end of the value itself and
+/// the end of the line it finishes on.
+fn scan_value(bytes: &[u8], mut pos: usize) -> (usize, usize) {
+    let mut depth = 0usize;
+    let mut value_end = pos;
+    while pos < bytes.len() {
+        match bytes[pos] {
+            b'\n' if depth == 0 => return (value_end, pos + 1),
+            b'#' => {
+                while pos < bytes.len() && bytes[pos] != b'\n' {
+                    pos += 1;
+                }
+                continue;
+            }
+            quote @ (b'"' | b'\'') => {
+                pos = skip_string(bytes, pos, quote);
+                value_end = pos;
+                continue;
+            }
+            b'[' | b'{' => depth += 1,
+            b']' | b'}' => depth = depth.saturating_sub(1),
+            b' ' | b'\t' | b'\r' | b'\n' => {
+                pos += 1;
+                continue;
+            }
+            _ => {}
+        }
+        pos += 1;
+        value_end = pos;
+    }
+    (value_end, pos)
+}
+
+/// Skip a string starting at `pos`, returning the position just past it.
+pub(crate) fn skip_string(bytes: &[u8], mut pos: usize, quote: u8) -> usize {
+    let delimiter = [quote; 3];
+    let multiline = bytes[pos..].starts_with(&delimiter);
+    pos += if multiline { 3 } else { 1 };
+    while pos < bytes.len() {
+        if bytes[pos] == b'\\' && quote == b'"' {
+            pos += 2;
+        } else if multiline && bytes[pos..].starts_with(&delimiter) {
+            pos += 3;
+            // Up to two extra quotes may close a multi-line string.
+            while bytes.get(pos) == Some(&quote) {
+                pos += 1;
+            }
+            return pos;
+        } else if !multiline && (bytes[pos] == quote || bytes[pos] == b'\n') {
+            return pos + 1;
+        } else {
+            pos += 1;
+        }
+    }
+    pos
+}
+
+pub(crate) fn skip_ws(bytes: &[u8], mut pos: usize) -> usize {
+    while pos < bytes.len() && matches!(bytes[pos], b' ' | b'\t') {
+        pos += 1;
+    }
+    pos
+}
+
+fn line_end(bytes: &[u8], pos: usize) -> usize {
+    bytes[pos..]
+        .iter()
+        .position(|&b| b == b'\n')
+        .map_or(bytes.len(), |i| pos + i + 1)
+}
@@ -17,11 +17,55 @@
 //! # Ok::<_, facet_cargo_toml::Error>(())
 //! ```
 
+mod artifact;
+mod cfg;
+mod dependencies;
+mod diagnostic;
+mod edit;
+mod features;
+mod graph;
+mod inherit;
+mod layout;
+mod lockcheck;
+mod lockdiff;
 mod lockfile;
 mod manifest;
+mod manifestdiff;
+mod msrv;
+mod profile;
+mod serialize;
+mod source;
+mod spelling;
+mod targets;
+#[cfg(test)]
+mod testdir;
+mod unused;
+mod validate;
+mod version;
+mod workspace;
 
-pub use lockfile::{CRATES_IO_SOURCE, CargoLock, LockPackage};
+pub use artifact::{ArtifactDependency, ArtifactKind};
+pub use cfg::{Cfg, CfgExpr, Platform};
+pub use dependencies::DeclaredDependency;
+pub use diagnostic::Diagnostic;
+pub use edit::{DependencyTable, ManifestEditor};
+pub use features::{Activation, FeatureGraph, FeatureValue};
+pub use graph::LockGraph;
+pub use inherit::{BuildScript, Readme, ResolvedPackage, ResolvedTargetSpec};
+pub use lockcheck::{LockfileCheck, MissingDependency, UnsatisfiedDependency};
+pub use lockdiff::{LockfileDiff, PackageChanges};
+pub use lockfile::{CRATES_IO_SOURCE, CargoLock, LOCKFILE_HEADER, LockDependencyRef, LockPackage};
 pub use manifest::*;
+pub use manifestdiff::{ChangeCategory, ChangeKind, ChangedValue, ManifestChange, ManifestDiff};
+pub use msrv::{EditionMismatch, MemberRustVersion, RustVersion};
+pub use profile::{EffectiveProfile, ProfileUnit};
+pub use semver::{Version, VersionReq};
+pub use source::{DependencySource, GitReference};
+pub use spelling::DeprecatedSpelling;
+pub use targets::{Target, TargetKind, Targets};
+pub use unused::UnusedKey;
+pub use validate::{Severity, ValidationIssue};
+pub use workspace::CargoWorkspace;
 
 use camino::Utf8PathBuf;
 use facet::Facet;
@@ -35,8 +79,93 @@ pub enum Error {
     /// failed to read {path}: {source}
     Io { path: Utf8PathBuf, source: IoError },
 
-    /// parse error: {message}
-    Parse { message: String },
+    /// {diagnostic}
+    Parse { diagnostic: Box<Diagnostic> },
+
+    /// could not find `Cargo.toml` in {path} or any parent directory
+    ManifestNotFound { path: Utf8PathBuf },
+
+    /// {member} is not a member of the workspace rooted at {root}
+    NotAWorkspaceMember {
+        member: Utf8PathBuf,
+        root: Utf8PathBuf,
+    },
+
+    /// `package.workspace` in {member} points to {path}, which has no `[workspace]` table
+    NotAWorkspaceRoot {
+        path: Utf8PathBuf,
+        member: Utf8PathBuf,
+    },
+
+    /// unsupported lockfile format version {version}
+    UnsupportedLockfileVersion { version: u32 },
+
+    /// lockfile dependency `{dependency}` does not match any package
+    DanglingLockDependency { dependency: String },
+
+    /// lockfile dependency `{dependency}` matches more than one package
+    AmbiguousLockDependency { dependency: String },
+
+    /// cannot edit manifest: {message}
+    Edit { message: String },
+
+    /// `{key}` is inherited but not defined in the workspace root
+    MissingInheritedKey { key: String, span: Option<Span> },
+
+    /// invalid workspace inheritance for `{key}`: {reason}
+    InvalidInheritance {
+        key: String,
+        reason: String,
+        span: Option<Span>,
+    },
+
+    /// invalid version `{value}`: {reason}
+    InvalidVersion {
+        value: String,
+        reason: String,
+        span: Option<Span>,
+    },
+
+    /// invalid version requirement `{value}`: {reason}
+    InvalidVersionReq {
+        value: String,
+        reason: String,
+        span: Option<Span>,
+    },
+
+    /// invalid rust-version `{value}`: {reason}
+    InvalidRustVersion {
+        value: String,
+        reason: String,
+        span: Option<Span>,
+    },
+
+    /// package does not have feature `{feature}`
+    UnknownFeature { feature: String },
+
+    /// invalid `cfg` expression `{expression}`: {reason}
+    InvalidCfg {
+        expression: String,
+        reason: String,
+        span: Option<Span>,
+    },
+
+    /// invalid source `{value}`: {reason}
+    InvalidSource { value: String, reason: String },
+
+    /// invalid artifact kind `{value}`: {reason}
+    InvalidArtifact {
+        value: String,
+        reason: String,
+        span: Option<Span>,
+    },
+
+    /// invalid profile `{profile}`: {reason}
+    InvalidProfile {
+        profile: String,
+        reason: String,
+        span: Option<Span>,
+    },
 }
 
 /// Wrapper for `std::io::Error` that implements `Facet`.
@@ -0,0 +1,312 @@
+//! Checking a lockfile against the manifests of its workspace.
+
+use camino::Utf8PathBuf;
+
+use crate::{
+    CargoLock, CargoToml, CargoWorkspace, Dependency, DependencyKind, Error, LockPackage, Span,
+    Spanned,
+};
+
+/// The result of [`CargoWorkspace::check_lockfile`].
+///
+/// An empty report means the lockfile agrees with the manifests, as far as can
+/// be told without contacting a registry.
+#[derive(Debug, Clone, Default)]
+pub struct LockfileCheck<'a> {
+    /// Members with no path package of the same name in the lockfile.
+    pub missing_members: Vec<Utf8PathBuf>,
+    /// Dependencies whose locked versions do not satisfy the manifest
+    /// requirement.
+    pub unsatisfied: Vec<UnsatisfiedDependency<'a>>,
+    /// Dependencies that have no package of that name in the lockfile.
+    pub missing: Vec<MissingDependency>,
+    /// Locked packages not reachable from any workspace member.
+    pub unreachable: Vec<&'a LockPackage>,
+}
+
+/// A dependency whose locked version does not satisfy its requirement.
+#[derive(Debug, Clone)]
+pub struct UnsatisfiedDependency<'a> {
+    /// The manifest the requirement is written in.
+    ///
+    /// For `{ workspace = true }` dependencies this is the workspace root.
+    pub manifest: Utf8PathBuf,
+    /// The member declaring the dependency.
+    pub member: String,
+    /// The dependency table it appears in.
+    pub kind: DependencyKind,
+    /// The `[target.'...']` key, for platform-specific dependencies.
+    pub target: Option<String>,
+    /// The package name, after `package = "..."` renames.
+    pub name: String,
+    /// The version requirement, with its span in `manifest`.
+    pub requirement: Spanned<String>,
+    /// The locked packages of that name the member depends on.
+    pub locked: Vec<&'a LockPackage>,
+}
+
+/// A dependency with no package of that name in the lockfile.
+#[derive(Debug, Clone)]
+pub struct MissingDependency {
+    /// The manifest declaring the dependency.
+    pub manifest: Utf8PathBuf,
+    /// The member declaring the dependency.
+    pub member: String,
+    /// The dependency table it appears in.
+    pub kind: DependencyKind,
+    /// The `[target.'...']` key, for platform-specific dependencies.
+    pub target: Option<String>,
+    /// The package name, after `package = "..."` renames.
+    pub name: String,
+    /// The span of the dependency specification in `manifest`.
+    pub span: Option<Span>,
+}
+
+impl LockfileCheck<'_> {
+    /// Returns true if no problems were found.
+    pub fn is_empty(&self) -> bool {
+        self.missing_members.is_empty()
+            && self.unsatisfied.is_empty()
+            && self.missing.is_empty()
+            && self.unreachable.is_empty()
+    }
+}
+
+impl CargoWorkspace {
+    /// Check `lock` against the manifests of this workspace.
+    ///
+    /// Every dependency of every member, including dev, build and
+    /// platform-specific ones, is looked up among the locked dependencies of
+    /// that member and checked against its version requirement. Lockfile
+    /// entries that no member reaches are reported as unreachable.
+    ///
+    /// Fails if a requirement or locked version is not valid semver, if a
+    /// `{ workspace = true }` dependency is not defined in the root, or if the
+    /// lockfile references packages it does not contain.
+    pub fn check_lockfile<'a>(&self, lock: &'a CargoLock) -> Result<LockfileCheck<'a>, Error> {
+        let graph = lock.graph()?;
+        let mut check = LockfileCheck::default();
+        let mut reachable = vec![false; lock.packages.len()];
+
+        for (manifest_path, manifest) in &self.members {
+            let Some(member) = manifest
+                .package
+                .as_ref()
+                .and_then(|p| p.name.as_ref())
+                .map(|n| n.value.as_str())
+            else {
+                continue;
+            };
+
+            let locked_member = self.find_member(lock, manifest, member)?;
+            let candidates: Vec<&LockPackage> = match locked_member {
+                Some(package) => {
+                    for reached in graph
+                        .transitive_dependencies(package)
+                        .into_iter()
+                        .chain([package])
+                    {
+                        if let Some(node) = graph.node(reached) {
+                            reachable[node] = true;
+                        }
+                    }
+                    graph.dependencies(package)
+                }
+                None => {
+                    check.missing_members.push(manifest_path.clone());
+                    lock.packages.iter().collect()
+                }
+            };
+
+            for declared in manifest.all_dependencies() {
+                let dependency = declared.dependency;
+                let member_dir = manifest_path.parent().unwrap_or(manifest_path);
+                let detail = dependency.resolve(
+                    declared.name,
+                    self.root.workspace.as_ref(),
+                    member_dir,
+                    self.root_dir(),
+                )?;
+                let name = detail.package.as_ref().map_or(declared.name, |p| &p.value);
+                let declared_in = if matches!(dependency, Dependency::Workspace(_)) {
+                    &self.root_manifest
+                } else {
+                    manifest_path
+                };
+
+                let locked: Vec<_> = candidates
+                    .iter()
+                    .copied()
+                    .filter(|p| p.name == *name)
+                    .collect();
+                if locked.is_empty() {
+                    check.missing.push(MissingDependency {
+                        manifest: declared_in.clone(),
+                        member: member.to_owned(),
+                        kind: declared.kind,
+                        target: declared.platform.map(str::to_owned),
+                        name: name.to_owned(),
+                        span: dependency_span(dependency),
+                    });
+                    continue;
+                }
+
+                let (Some(requirement), Some(req)) = (&detail.version, detail.version_req()?)
+                else {
+                    continue;
+                };
+                let mut satisfied = false;
+                for package in &locked {
+                    satisfied |= req.matches(&package.semver_version()?);
+                }
+                if !satisfied {
+                    check.unsatisfied.push(UnsatisfiedDependency {
+                        manifest: declared_in.clone(),
+                        member: member.to_owned(),
+                        kind: declared.kind,
+                        target: declared.platform.map(str::to_owned),
+                        name: name.to_owned(),
+                        requirement: requirement.clone(),
+                        locked,
+                    });
+                }
+            }
+        }
+
+        check.unreachable = lock
+            .packages
+            .iter()
+            .zip(&reachable)
+            .filter(|(_, reachable)| !**reachable)
+            .map(|(package, _)| package)
+            .collect();
+        Ok(check)
+    }
+
+    /// Find the path package for a member, by name and resolved version.
+    fn find_member<'a>(
+        &self,
+        lock: &'a CargoLock,
+        manifest: &CargoToml,
+        name: &str,
+    ) -> Result<Option<&'a LockPackage>, Error> {
+        let inherited = self
+            .root
+            .workspace
+            .as_ref()
+            .and_then(|w| w.package.as_ref())
+            .and_then(|p| p.version.as_ref());
+        // Cargo treats a package without a version as 0.0.0.
+        let version = match manifest.package.as_ref().and_then(|p| p.version.as_ref()) {
+            Some(version) => version.resolve("workspace.package.version", inherited)?,
+            None => "0.0.0".to_owned(),
+        };
+        Ok(lock
+            .packages
+            .iter()
+            .find(|p| p.is_path() && p.name == name && p.version == version))
+    }
+}
+
+/// The span of the most relevant part of a dependency specification.
+pub(crate) fn dependency_span(dependency: &Dependency) -> Option<Span> {
+    match dependency {
+        Dependency::Version(version) => version.span,
+        Dependency::Workspace(workspace) => workspace.workspace.span,
+        Dependency::Detailed(detail) => {
+            [&detail.version, &detail.path, &detail.git, &detail.package]
+                .into_iter()
+                .flatten()
+                .find_map(|value| value.span)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testdir::TestDir;
+
+    #[test]
+    fn stale_lockfile() {
+        let dir = TestDir::new("lock");
+        dir.write(
+            "Cargo.toml",
+            r#"
+[package]
+name = "app"
+version = "0.1.0"
+
+[workspace]
+members = ["crates/*"]
+
+[workspace.dependencies]
+serde = "1.0.100"
+
+[dependencies]
+lib = { path = "crates/lib" }
+serde.workspace = true
+log = "0.4.20"
+rand = "0.8"
+"#,
+        );
+        dir.write(
+            "crates/lib/Cargo.toml",
+            "[package]\nname = \"lib\"\nversion = \"0.2.0\"\n\n[target.'cfg(unix)'.dependencies]\nmemchr = \"2\"\n",
+        );
+
+        let lock = CargoLock::parse(
+            r#"
+version = 4
+
+[[package]]
+name = "app"
+version = "0.1.0"
+dependencies = ["lib", "log", "serde"]
+
+[[package]]
+name = "lib"
+version = "0.2.0"
+dependencies = ["memchr"]
+
+[[package]]
+name = "log"
+version = "0.4.21"
+source = "registry+https://github.com/rust-lang/crates.io-index"
+
+[[package]]
+name = "memchr"
+version = "2.7.1"
+source = "registry+https://github.com/rust-lang/crates.io-index"
+
+[[package]]
+name = "old"
+version = "1.0.0"
+source = "registry+https://github.com/rust-lang/crates.io-index"
+
+[[package]]
+name = "serde"
+version = "1.0.50"
+source = "registry+https://github.com/rust-lang/crates.io-index"
+"#,
+        )
+        .unwrap();
+
+        let workspace = CargoWorkspace::from_root(dir.path().join("Cargo.toml")).unwrap();
+        let check = workspace.check_lockfile(&lock).unwrap();
+
+        assert!(check.missing_members.is_empty());
+        let [unsatisfied] = &check.unsatisfied[..] else {
+            panic!("{:?}", check.unsatisfied);
+        };
+        assert_eq!(unsatisfied.name, "serde");
+        assert_eq!(unsatisfied.requirement.value, "1.0.100");
+        assert_eq!(unsatisfied.manifest, dir.path().join("Cargo.toml"));
+        assert_eq!(unsatisfied.locked[0].version, "1.0.50");
+
+        let missing: Vec<_> = check.missing.iter().map(|m| m.name.as_str()).collect();
+        assert_eq!(missing, ["rand"]);
+        let unreachable: Vec<_> = check.unreachable.iter().map(|p| p.name.as_str()).collect();
+        assert_eq!(unreachable, ["old"]);
+    }
+}
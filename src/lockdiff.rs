@@ -0,0 +1,251 @@
+//! Comparing two lockfiles package by package.
+
+use std::collections::BTreeMap;
+use std::fmt;
+
+use semver::Version;
+
+use crate::{CargoLock, Error, LockPackage};
+
+/// The result of [`CargoLock::diff`].
+#[derive(Debug, Clone, Default)]
+pub struct LockfileDiff<'a> {
+    /// The changed packages, grouped by name, in name order.
+    pub packages: Vec<PackageChanges<'a>>,
+}
+
+/// How the locked versions of one package name changed.
+///
+/// A name can be locked at several versions at once. Old and new versions
+/// are paired up when they are semver-compatible (same major version, or
+/// same minor for `0.x`), or when they are the only versions left unpaired;
+/// anything else is an addition or a removal.
+#[derive(Debug, Clone, Default)]
+pub struct PackageChanges<'a> {
+    /// The package name.
+    pub name: &'a str,
+    /// Versions only in the new lockfile.
+    pub added: Vec<&'a LockPackage>,
+    /// Versions only in the old lockfile.
+    pub removed: Vec<&'a LockPackage>,
+    /// Versions replaced by a newer one, as `(old, new)`.
+    pub upgraded: Vec<(&'a LockPackage, &'a LockPackage)>,
+    /// Versions replaced by an older one, as `(old, new)`.
+    pub downgraded: Vec<(&'a LockPackage, &'a LockPackage)>,
+    /// The same version and source with a different checksum, as `(old,
+    /// new)`. Published crates never change, so this deserves scrutiny.
+    pub checksum_changed: Vec<(&'a LockPackage, &'a LockPackage)>,
+    /// Paired versions whose source changed, as `(old, new)`, like a switch
+    /// from crates.io to a git fork.
+    pub source_changed: Vec<(&'a LockPackage, &'a LockPackage)>,
+}
+
+impl LockfileDiff<'_> {
+    /// Returns true if the lockfiles lock the same packages.
+    pub fn is_empty(&self) -> bool {
+        self.packages.is_empty()
+    }
+}
+
+impl PackageChanges<'_> {
+    fn is_empty(&self) -> bool {
+        self.added.is_empty()
+            && self.removed.is_empty()
+            && self.upgraded.is_empty()
+            && self.downgraded.is_empty()
+            && self.checksum_changed.is_empty()
+            && self.source_changed.is_empty()
+    }
+}
+
+impl CargoLock {
+    /// Compare this lockfile, as the old one, with `new`.
+    ///
+    /// Fails if a package version is not valid semver.
+    pub fn diff<'a>(&'a self, new: &'a CargoLock) -> Result<LockfileDiff<'a>, Error> {
+        let mut names: BTreeMap<&str, (Vec<&LockPackage>, Vec<&LockPackage>)> = BTreeMap::new();
+        for package in &self.packages {
+            names.entry(&package.name).or_default().0.push(package);
+        }
+        for package in &new.packages {
+            names.entry(&package.name).or_default().1.push(package);
+        }
+
+        let mut diff = LockfileDiff::default();
+        for (name, (old, new)) in names {
+            let changes = package_changes(name, old, new)?;
+            if !changes.is_empty() {
+                diff.packages.push(changes);
+            }
+        }
+        Ok(diff)
+    }
+}
+
+fn package_changes<'a>(
+    name: &'a str,
+    mut old: Vec<&'a LockPackage>,
+    mut new: Vec<&'a LockPackage>,
+) -> Result<PackageChanges<'a>, Error> {
+    let mut changes = PackageChanges {
+        name,
+        ..PackageChanges::default()
+    };
+
+    // The same version from the same source, then the same version from
+    // another source.
+    for same_source in [true, false] {
+        old.retain(|o| {
+            let found = new
+                .iter()
+                .position(|n| n.version == o.version && (!same_source || n.source == o.source));
+            let Some(index) = found else {
+                return true;
+            };
+            let n = new.remove(index);
+            if n.source != o.source {
+                changes.source_changed.push((o, n));
+            } else if n.checksum != o.checksum {
+                changes.checksum_changed.push((o, n));
+            }
+            false
+        });
+    }
+
+    // Different versions: compatible ones first, then whatever is left if
+    // it is one of each.
+    let mut old: Vec<_> = old
+        .into_iter()
+        .map(|p| Ok((p.semver_version()?, p)))
+        .collect::<Result<_, Error>>()?;
+    let mut new: Vec<_> = new
+        .into_iter()
+        .map(|p| Ok((p.semver_version()?, p)))
+        .collect::<Result<_, Error>>()?;
+    old.sort_by(|a, b| a.0.cmp(&b.0));
+    new.sort_by(|a, b| a.0.cmp(&b.0));
+    let mut pairs = Vec::new();
+    old.retain(|(old_version, o)| {
+        let found = new
+            .iter()
+            .rposition(|(new_version, _)| compatible(old_version, new_version));
+        let Some(index) = found else {
+            return true;
+        };
+        let (new_version, n) = new.remove(index);
+        pairs.push((old_version.clone(), *o, new_version, n));
+        false
+    });
+    if let ([(old_version, o)], [(new_version, n)]) = (&old[..], &new[..]) {
+        pairs.push((old_version.clone(), *o, new_version.clone(), *n));
+        old.clear();
+        new.clear();
+    }
+
+    for (old_version, o, new_version, n) in pairs {
+        if new_version > old_version {
+            changes.upgraded.push((o, n));
+        } else {
+            changes.downgraded.push((o, n));
+        }
+        if n.source != o.source {
+            changes.source_changed.push((o, n));
+        }
+    }
+    changes.removed = old.into_iter().map(|(_, p)| p).collect();
+    changes.added = new.into_iter().map(|(_, p)| p).collect();
+    Ok(changes)
+}
+
+/// Whether Cargo would consider `b` a compatible update of `a`.
+fn compatible(a: &Version, b: &Version) -> bool {
+    match (a.major, a.minor) {
+        (0, 0) => b.major == 0 && b.minor == 0 && a.patch == b.patch,
+        (0, minor) => b.major == 0 && b.minor == minor,
+        (major, _) => b.major == major,
+    }
+}
+
+/// One line per change, like `+ log 0.4.21` or `^ serde 1.0.100 -> 1.0.200`.
+impl fmt::Display for LockfileDiff<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let source = |p: &LockPackage| p.source.clone().unwrap_or_else(|| "path".to_owned());
+        for changes in &self.packages {
+            let name = changes.name;
+            for p in &changes.added {
+                writeln!(f, "+ {name} {}", p.version)?;
+            }
+            for p in &changes.removed {
+                writeln!(f, "- {name} {}", p.version)?;
+            }
+            for (o, n) in &changes.upgraded {
+                writeln!(f, "^ {name} {} -> {}", o.version, n.version)?;
+            }
+            for (o, n) in &changes.downgraded {
+                writeln!(f, "v {name} {} -> {}", o.version, n.version)?;
+            }
+            for (o, _) in &changes.checksum_changed {
+                writeln!(f, "! {name} {}: checksum changed", o.version)?;
+            }
+            for (o, n) in &changes.source_changed {
+                writeln!(f, "~ {name} {}: {} -> {}", n.version, source(o), source(n))?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lockfile(packages: &[(&str, &str, Option<&str>, Option<&str>)]) -> CargoLock {
+        let mut lock = CargoLock::default();
+        for (name, version, source, checksum) in packages {
+            lock.packages.push(LockPackage {
+                name: name.to_string(),
+                version: version.to_string(),
+                source: source.map(str::to_owned),
+                checksum: checksum.map(str::to_owned),
+                dependencies: Vec::new(),
+            });
+        }
+        lock
+    }
+
+    #[test]
+    fn diff_lockfiles() {
+        let registry = Some(crate::CRATES_IO_SOURCE);
+        let fork = Some("git+https://github.com/example/log#abc123");
+        let old = lockfile(&[
+            ("app", "0.1.0", None, None),
+            ("log", "0.4.20", registry, Some("aa")),
+            ("rand", "0.7.3", registry, Some("bb")),
+            ("rand", "0.8.5", registry, Some("cc")),
+            ("syn", "1.0.109", registry, Some("dd")),
+            ("syn", "2.0.50", registry, Some("ee")),
+            ("time", "0.3.30", registry, Some("ff")),
+        ]);
+        let new = lockfile(&[
+            ("app", "0.1.0", None, None),
+            ("log", "0.4.20", fork, None),
+            ("rand", "0.8.5", registry, Some("cc")),
+            ("serde", "1.0.200", registry, Some("11")),
+            ("syn", "2.0.60", registry, Some("22")),
+            ("time", "0.3.30", registry, Some("99")),
+        ]);
+
+        let diff = old.diff(&new).unwrap();
+        assert_eq!(
+            diff.to_string(),
+            "~ log 0.4.20: registry+https://github.com/rust-lang/crates.io-index -> git+https://github.com/example/log#abc123
+- rand 0.7.3
++ serde 1.0.200
+- syn 1.0.109
+^ syn 2.0.50 -> 2.0.60
+! time 0.3.30: checksum changed
+"
+        );
+        assert!(new.diff(&new).unwrap().is_empty());
+    }
+}
@@ -1,8 +1,14 @@
 //! Cargo.lock types.
 
+use std::collections::BTreeMap;
+use std::fmt::Write;
+
 use camino::Utf8Path;
 use facet::Facet;
 
+use crate::diagnostic::Diagnostic;
+use crate::serialize::{toml_key, toml_string};
+
 /// The crates.io registry source string in Cargo.lock.
 pub const CRATES_IO_SOURCE: &str = "registry+https://github.com/rust-lang/crates.io-index";
 
@@ -17,8 +23,83 @@ pub struct LockPackage {
     pub source: Option<String>,
     /// SHA256 checksum (for registry packages).
     pub checksum: Option<String>,
-    /// Dependencies as "name" or "name version" or "name version (source)".
-    pub dependencies: Vec<String>,
+    /// References to the packages this package depends on.
+    pub dependencies: Vec<LockDependencyRef>,
+}
+
+/// A reference from one lockfile package to another.
+///
+/// Cargo writes these as `"name"`, `"name version"` or `"name version (source)"`,
+/// omitting the version and source when the name alone is unambiguous.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct LockDependencyRef {
+    /// Package name.
+    pub name: String,
+    /// Version string, when needed to disambiguate.
+    pub version: Option<String>,
+    /// Source URL, when needed to disambiguate.
+    pub source: Option<String>,
+}
+
+impl LockDependencyRef {
+    /// Parse a dependency reference from its lockfile string form.
+    pub fn parse(s: &str) -> Result<Self, crate::Error> {
+        let invalid = || crate::Error::Parse {
+            diagnostic: Box::new(Diagnostic::message(format!(
+                "invalid lockfile dependency reference `{s}`"
+            ))),
+        };
+
+        let mut parts = s.splitn(3, ' ');
+        let name = parts.next().filter(|n| !n.is_empty()).ok_or_else(invalid)?;
+        let version = parts.next().map(str::to_owned);
+        let source = match parts.next() {
+            None => None,
+            Some(source) => Some(
+                source
+                    .strip_prefix('(')
+                    .and_then(|s| s.strip_suffix(')'))
+                    .ok_or_else(invalid)?
+                    .to_owned(),
+            ),
+        };
+
+        Ok(LockDependencyRef {
+            name: name.to_owned(),
+            version,
+            source,
+        })
+    }
+
+    /// Returns true if `package` is a candidate for this reference.
+    pub fn matches(&self, package: &LockPackage) -> bool {
+        package.name == self.name
+            && self.version.as_ref().is_none_or(|v| *v == package.version)
+            && self.source.as_ref().is_none_or(|s| {
+                package
+                    .source
+                    .as_deref()
+                    .is_some_and(|p| without_precise(p) == without_precise(s))
+            })
+    }
+}
+
+/// Strip the `#commit` suffix Cargo appends to locked git sources.
+fn without_precise(source: &str) -> &str {
+    source.split_once('#').map_or(source, |(source, _)| source)
+}
+
+impl std::fmt::Display for LockDependencyRef {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.name)?;
+        if let Some(version) = &self.version {
+            write!(f, " {version}")?;
+        }
+        if let Some(source) = &self.source {
+            write!(f, " ({source})")?;
+        }
+        Ok(())
+    }
 }
 
 impl LockPackage {
@@ -33,21 +114,61 @@ impl LockPackage {
     }
 }
 
+/// The comment Cargo writes at the top of every lockfile.
+pub const LOCKFILE_HEADER: [&str; 2] = [
+    "# This file is automatically @generated by Cargo.",
+    "# It is not intended for manual editing.",
+];
+
+/// The `[metadata]` key prefix under which version 1 lockfiles store checksums.
+const CHECKSUM_PREFIX: &str = "checksum ";
+
 /// A parsed `Cargo.lock` file.
 ///
-/// Supports lockfile format versions 3 and 4.
-#[derive(Debug)]
+/// Supports lockfile format versions 1 through 4. Version 1 checksums, stored
+/// in `[metadata]`, are folded into [`LockPackage::checksum`].
+#[derive(Debug, Clone)]
 pub struct CargoLock {
-    /// Lockfile format version (3 or 4).
+    /// Lockfile format version (1 to 4).
+    ///
+    /// Versions 1 and 2 have no `version` key. As in Cargo, a lockfile
+    /// without one is version 1 if `[metadata]` has `checksum` keys, and
+    /// version 2 otherwise.
     pub version: u32,
+    /// Comment lines at the top of the file, written back verbatim.
+    pub header: Vec<String>,
     /// All packages in the lockfile.
     pub packages: Vec<LockPackage>,
+    /// `[[patch.unused]]` entries: patches that did not apply to any dependency.
+    pub unused_patches: Vec<LockPackage>,
+    /// The `[metadata]` table.
+    pub metadata: BTreeMap<String, String>,
+}
+
+impl Default for CargoLock {
+    /// An empty version 4 lockfile with Cargo's header comment.
+    fn default() -> Self {
+        CargoLock {
+            version: 4,
+            header: LOCKFILE_HEADER.map(str::to_owned).to_vec(),
+            packages: Vec::new(),
+            unused_patches: Vec::new(),
+            metadata: BTreeMap::new(),
+        }
+    }
 }
 
 #[derive(Facet, Debug)]
 struct RawLockfile {
     version: Option<u32>,
     package: Option<Vec<RawPackage>>,
+    patch: Option<RawPatch>,
+    metadata: Option<BTreeMap<String, String>>,
+}
+
+#[derive(Facet, Debug)]
+struct RawPatch {
+    unused: Option<Vec<RawPackage>>,
 }
 
 #[derive(Facet, Debug)]
@@ -59,6 +180,23 @@ struct RawPackage {
     dependencies: Option<Vec<String>>,
 }
 
+impl RawPackage {
+    fn into_package(self) -> Result<LockPackage, crate::Error> {
+        Ok(LockPackage {
+            name: self.name,
+            version: self.version,
+            source: self.source,
+            checksum: self.checksum,
+            dependencies: self
+                .dependencies
+                .unwrap_or_default()
+                .iter()
+                .map(|d| LockDependencyRef::parse(d))
+                .collect::<Result<_, _>>()?,
+        })
+    }
+}
+
 impl CargoLock {
     /// Parse a `Cargo.lock` file from disk.
     pub fn from_path(path: impl AsRef<Utf8Path>) -> Result<Self, crate::Error> {
@@ -67,31 +205,139 @@ impl CargoLock {
             path: path.to_owned(),
             source: crate::IoError::from(source),
         })?;
-        Self::parse(&contents)
+        Self::parse(&contents).map_err(|e| e.with_path(path))
     }
 
     /// Parse `Cargo.lock` content from a string.
     pub fn parse(contents: &str) -> Result<Self, crate::Error> {
         let raw: RawLockfile = facet_toml::from_str(contents).map_err(|e| crate::Error::Parse {
-            message: e.to_string(),
+            diagnostic: Box::new(Diagnostic::from_toml(contents, e)),
         })?;
 
-        let version = raw.version.unwrap_or(3);
+        let header = contents
+            .lines()
+            .take_while(|line| line.starts_with('#'))
+            .map(str::to_owned)
+            .collect();
 
-        let packages = raw
+        let mut packages: Vec<LockPackage> = raw
             .package
             .unwrap_or_default()
             .into_iter()
-            .map(|p| LockPackage {
-                name: p.name,
-                version: p.version,
-                source: p.source,
-                checksum: p.checksum,
-                dependencies: p.dependencies.unwrap_or_default(),
-            })
-            .collect();
+            .map(RawPackage::into_package)
+            .collect::<Result<_, _>>()?;
+        let unused_patches = raw
+            .patch
+            .and_then(|p| p.unused)
+            .unwrap_or_default()
+            .into_iter()
+            .map(RawPackage::into_package)
+            .collect::<Result<_, _>>()?;
+
+        let mut metadata = raw.metadata.unwrap_or_default();
+        let version = match raw.version {
+            Some(version @ 1..=4) => version,
+            Some(version) => return Err(crate::Error::UnsupportedLockfileVersion { version }),
+            // Like Cargo, only checksums in `[metadata]` mark version 1.
+            None if metadata.keys().any(|k| k.starts_with(CHECKSUM_PREFIX)) => 1,
+            None => 2,
+        };
+
+        if version == 1 {
+            let checksums: Vec<_> = metadata
+                .keys()
+                .filter(|k| k.starts_with(CHECKSUM_PREFIX))
+                .cloned()
+                .collect();
+            for key in checksums {
+                let checksum = metadata.remove(&key).filter(|c| c != "<none>");
+                let id = LockDependencyRef::parse(&key[CHECKSUM_PREFIX.len()..])?;
+                if let Some(package) = packages.iter_mut().find(|p| id.matches(p)) {
+                    package.checksum = checksum;
+                }
+            }
+        }
+
+        Ok(CargoLock {
+            version,
+            header,
+            packages,
+            unused_patches,
+            metadata,
+        })
+    }
+
+    /// Serialize to the exact text Cargo would write for this lockfile.
+    ///
+    /// Packages are written in the order of [`CargoLock::packages`]; Cargo keeps
+    /// them sorted by name, version and source. Parsing a lockfile and writing it
+    /// back produces identical bytes.
+    ///
+    /// Version 1 lockfiles get their checksums written to `[metadata]`, with
+    /// `<none>` for non-path packages that have no checksum.
+    pub fn to_toml_string(&self) -> String {
+        let legacy = self.version == 1;
+        let mut out = String::new();
+        for line in &self.header {
+            out.push_str(line);
+            out.push('\n');
+        }
+        if self.version >= 3 {
+            writeln!(out, "version = {}\n", self.version).unwrap();
+        }
+
+        for package in &self.packages {
+            out.push_str("[[package]]\n");
+            write_package(&mut out, package, !legacy);
+            out.push('\n');
+        }
+
+        for package in &self.unused_patches {
+            out.push_str("[[patch.unused]]\n");
+            write_package(&mut out, package, !legacy);
+            out.push('\n');
+        }
+
+        let mut metadata = self.metadata.clone();
+        if legacy {
+            for package in self.packages.iter().filter(|p| !p.is_path()) {
+                // Like dependency references, these keys leave out the commit
+                // of git sources.
+                let id = LockDependencyRef {
+                    name: package.name.clone(),
+                    version: Some(package.version.clone()),
+                    source: package
+                        .source
+                        .as_deref()
+                        .map(|s| without_precise(s).to_owned()),
+                };
+                let checksum = package.checksum.as_deref().unwrap_or("<none>");
+                metadata.insert(format!("{CHECKSUM_PREFIX}{id}"), checksum.to_owned());
+            }
+        }
+        if !metadata.is_empty() {
+            out.push_str("[metadata]\n");
+            for (key, value) in &metadata {
+                writeln!(out, "{} = {}", toml_key(key), toml_string(value)).unwrap();
+            }
+        }
+
+        // Cargo trims trailing blank lines from everything but version 1.
+        if !legacy {
+            while out.ends_with("\n\n") {
+                out.pop();
+            }
+        }
+        out
+    }
 
-        Ok(CargoLock { version, packages })
+    /// Write this lockfile to disk, in the format of [`CargoLock::to_toml_string`].
+    pub fn write_to_path(&self, path: impl AsRef<Utf8Path>) -> Result<(), crate::Error> {
+        let path = path.as_ref();
+        std::fs::write(path, self.to_toml_string()).map_err(|source| crate::Error::Io {
+            path: path.to_owned(),
+            source: crate::IoError::from(source),
+        })
     }
 
     /// Find a package by name.
@@ -105,6 +351,65 @@ impl CargoLock {
             .iter()
             .find(|p| p.name == name && p.version == version)
     }
+
+    /// Resolve a dependency reference to the package it points at.
+    ///
+    /// Path packages never carry a source, so a reference without one prefers
+    /// the single path package among its candidates, as Cargo does. Fails if no
+    /// package matches, or if several still do.
+    pub fn resolve(&self, dependency: &LockDependencyRef) -> Result<&LockPackage, crate::Error> {
+        let candidates: Vec<_> = self
+            .packages
+            .iter()
+            .filter(|p| dependency.matches(p))
+            .collect();
+        match candidates[..] {
+            [] => Err(crate::Error::DanglingLockDependency {
+                dependency: dependency.to_string(),
+            }),
+            [package] => Ok(package),
+            _ => {
+                let mut paths = candidates.iter().filter(|p| p.is_path());
+                match (dependency.source.is_none(), paths.next(), paths.next()) {
+                    (true, Some(package), None) => Ok(package),
+                    _ => Err(crate::Error::AmbiguousLockDependency {
+                        dependency: dependency.to_string(),
+                    }),
+                }
+            }
+        }
+    }
+
+    /// Resolve every dependency of `package` to the packages they point at.
+    pub fn dependencies_of(
+        &self,
+        package: &LockPackage,
+    ) -> Result<Vec<&LockPackage>, crate::Error> {
+        package
+            .dependencies
+            .iter()
+            .map(|d| self.resolve(d))
+            .collect()
+    }
+}
+
+/// Write one `[[package]]` body for [`CargoLock::to_toml_string`].
+fn write_package(out: &mut String, package: &LockPackage, with_checksum: bool) {
+    writeln!(out, "name = {}", toml_string(&package.name)).unwrap();
+    writeln!(out, "version = {}", toml_string(&package.version)).unwrap();
+    if let Some(source) = &package.source {
+        writeln!(out, "source = {}", toml_string(source)).unwrap();
+    }
+    if let Some(checksum) = package.checksum.as_ref().filter(|_| with_checksum) {
+        writeln!(out, "checksum = {}", toml_string(checksum)).unwrap();
+    }
+    if !package.dependencies.is_empty() {
+        out.push_str("dependencies = [\n");
+        for dependency in &package.dependencies {
+            writeln!(out, " {},", toml_string(&dependency.to_string())).unwrap();
+        }
+        out.push_str("]\n");
+    }
 }
 
 #[cfg(test)]
@@ -168,5 +473,118 @@ checksum = "b2969dcb958b36655471fc61f7e416fa76033bdd4bfed0678d8fee1e2d07a1f0"
 "#;
         let lockfile = CargoLock::parse(contents).unwrap();
         assert_eq!(lockfile.version, 3);
+
+        let myapp = lockfile.find_by_name("myapp").unwrap();
+        let deps = lockfile.dependencies_of(myapp).unwrap();
+        assert_eq!(deps[0].name, "aho-corasick");
+    }
+
+    #[test]
+    fn resolve_dependency_refs() {
+        let contents = r#"
+version = 4
+
+[[package]]
+name = "myapp"
+version = "0.1.0"
+dependencies = [
+    "syn 1.0.109",
+    "syn 2.0.48 (registry+https://github.com/rust-lang/crates.io-index)",
+]
+
+[[package]]
+name = "syn"
+version = "1.0.109"
+source = "registry+https://github.com/rust-lang/crates.io-index"
+
+[[package]]
+name = "syn"
+version = "2.0.48"
+source = "registry+https://github.com/rust-lang/crates.io-index"
+"#;
+        let lockfile = CargoLock::parse(contents).unwrap();
+        let myapp = lockfile.find_by_name("myapp").unwrap();
+        assert_eq!(myapp.dependencies[1].version.as_deref(), Some("2.0.48"));
+        assert_eq!(
+            myapp.dependencies[1].source.as_deref(),
+            Some(CRATES_IO_SOURCE)
+        );
+
+        let deps = lockfile.dependencies_of(myapp).unwrap();
+        assert_eq!(deps[0].version, "1.0.109");
+        assert_eq!(deps[1].version, "2.0.48");
+
+        let ambiguous = LockDependencyRef::parse("syn").unwrap();
+        assert!(matches!(
+            lockfile.resolve(&ambiguous),
+            Err(crate::Error::AmbiguousLockDependency { .. })
+        ));
+        let dangling = LockDependencyRef::parse("quote 1.0.0").unwrap();
+        assert!(matches!(
+            lockfile.resolve(&dangling),
+            Err(crate::Error::DanglingLockDependency { .. })
+        ));
+    }
+
+    #[test]
+    fn parse_v1_lockfile() {
+        let contents = r#"[[package]]
+name = "myapp"
+version = "0.1.0"
+dependencies = [
+ "aho-corasick 1.1.2 (registry+https://github.com/rust-lang/crates.io-index)",
+]
+
+[[package]]
+name = "aho-corasick"
+version = "1.1.2"
+source = "registry+https://github.com/rust-lang/crates.io-index"
+
+[metadata]
+"checksum aho-corasick 1.1.2 (registry+https://github.com/rust-lang/crates.io-index)" = "b2969dcb958b36655471fc61f7e416fa76033bdd4bfed0678d8fee1e2d07a1f0"
+"#;
+        let lockfile = CargoLock::parse(contents).unwrap();
+        assert_eq!(lockfile.version, 1);
+        assert!(lockfile.metadata.is_empty());
+        let ac = lockfile.find_by_name("aho-corasick").unwrap();
+        assert_eq!(
+            ac.checksum.as_deref(),
+            Some("b2969dcb958b36655471fc61f7e416fa76033bdd4bfed0678d8fee1e2d07a1f0")
+        );
+        assert_eq!(lockfile.to_toml_string(), contents);
+    }
+
+    #[test]
+    fn parse_v2_lockfile() {
+        let contents = r#"
+[[package]]
+name = "myapp"
+version = "0.1.0"
+dependencies = [
+ "aho-corasick",
+]
+
+[[package]]
+name = "aho-corasick"
+version = "1.1.2"
+source = "registry+https://github.com/rust-lang/crates.io-index"
+checksum = "b2969dcb958b36655471fc61f7e416fa76033bdd4bfed0678d8fee1e2d07a1f0"
+"#;
+        assert_eq!(CargoLock::parse(contents).unwrap().version, 2);
+
+        // No checksums and no short references, but no `[metadata]` either.
+        let contents = "[[package]]\nname = \"myapp\"\nversion = \"0.1.0\"\n";
+        let lockfile = CargoLock::parse(contents).unwrap();
+        assert_eq!(lockfile.version, 2);
+        assert_eq!(lockfile.to_toml_string(), contents);
+    }
+
+    #[test]
+    fn reject_future_lockfile_version() {
+        let result = CargoLock::parse("version = 5\n");
+        assert!(matches!(
+            result,
+            Err(crate::Error::UnsupportedLockfileVersion { version: 5 })
+        ));
     }
 }
@@ -26,6 +26,9 @@ pub struct Spanned<T> {
 #[derive(Facet, Debug, Clone)]
 #[facet(rename_all = "kebab-case")]
 pub struct CargoToml {
+    /// Unstable Cargo features the manifest opts into, from `cargo-features`.
+    pub cargo_features: Option<Vec<Spanned<CargoFeature>>>,
+
     /// The `[package]` section containing crate metadata.
     pub package: Option<Package>,
 
@@ -36,9 +39,11 @@ pub struct CargoToml {
     pub dependencies: Option<HashMap<String, Dependency>>,
 
     /// Development dependencies from `[dev-dependencies]`.
+    #[facet(alias = "dev_dependencies")]
     pub dev_dependencies: Option<HashMap<String, Dependency>>,
 
     /// Build script dependencies from `[build-dependencies]`.
+    #[facet(alias = "build_dependencies")]
     pub build_dependencies: Option<HashMap<String, Dependency>>,
 
     /// Target-specific dependencies from `[target.'cfg(...)'.dependencies]`.
@@ -62,17 +67,24 @@ pub struct CargoToml {
     pub example: Option<Vec<ExampleTarget>>,
 
     /// Feature flags from `[features]`.
-    pub features: Option<HashMap<String, Vec<String>>>,
+    pub features: Option<HashMap<String, Vec<Spanned<String>>>>,
 
     /// Dependency patches from `[patch]`.
     pub patch: Option<HashMap<String, HashMap<String, Dependency>>>,
 
+    /// Dependency replacements from `[replace]`, keyed by package ID spec
+    /// like `"foo:1.0.0"`. Deprecated in favor of `[patch]`.
+    pub replace: Option<HashMap<String, Dependency>>,
+
     /// Build profiles from `[profile.*]`.
     pub profile: Option<HashMap<String, Profile>>,
 
     /// Lint configuration from `[lints]`.
     pub lints: Option<Lints>,
 
+    /// Build hints from `[hints]`.
+    pub hints: Option<Hints>,
+
     /// Badges from `[badges]` (deprecated).
     pub badges: Option<HashMap<String, Badge>>,
 }
@@ -116,11 +128,11 @@ pub struct Package {
     /// Name of the native library being linked by a build script.
     pub links: Option<Spanned<String>>,
     /// Gitignore-style patterns for files to exclude when publishing.
-    pub exclude: Option<Spanned<Vec<String>>>,
+    pub exclude: Option<VecOrWorkspace>,
     /// Gitignore-style patterns for files to explicitly include when publishing.
-    pub include: Option<Spanned<Vec<String>>>,
+    pub include: Option<VecOrWorkspace>,
     /// Controls publishing to registries; array of registry names or `false` to prevent publishing.
-    pub publish: Option<BoolOrVec>,
+    pub publish: Option<BoolOrVecOrWorkspace>,
     /// A table for external tool configuration, ignored by Cargo.
     pub metadata: Option<facet_value::Value>,
     /// The default binary selected by `cargo run` when multiple binaries exist.
@@ -214,6 +226,40 @@ pub enum BoolOrVec {
     Vec(Spanned<Vec<String>>),
 }
 
+/// A value that can be a string or an array of strings.
+#[derive(Facet, Debug, Clone)]
+#[repr(u8)]
+#[facet(untagged)]
+pub enum StringOrVec {
+    /// A single string.
+    String(Spanned<String>),
+    /// An array of strings.
+    Vec(Vec<Spanned<String>>),
+}
+
+impl StringOrVec {
+    /// The strings, whichever form they were written in.
+    pub fn values(&self) -> &[Spanned<String>] {
+        match self {
+            StringOrVec::String(value) => std::slice::from_ref(value),
+            StringOrVec::Vec(values) => values,
+        }
+    }
+}
+
+/// A value that can be a boolean, array of strings, or inherited from workspace.
+#[derive(Facet, Debug, Clone)]
+#[repr(u8)]
+#[facet(untagged)]
+pub enum BoolOrVecOrWorkspace {
+    /// Inherited from `[workspace.package]`.
+    Workspace(WorkspaceRef),
+    /// A boolean value.
+    Bool(Spanned<bool>),
+    /// An array of strings.
+    Vec(Spanned<Vec<String>>),
+}
+
 /// Rust edition year.
 #[derive(Facet, Debug, Clone, Copy, PartialEq, Eq, Hash)]
 #[repr(u8)]
@@ -247,6 +293,174 @@ pub enum Resolver {
     V3,
 }
 
+/// A feature named in `cargo-features`.
+///
+/// Features this crate does not know yet, say from a newer nightly Cargo, are
+/// kept as [`CargoFeature::Unknown`] and treated as unstable. Features that
+/// have since been stabilized are still accepted, with a warning.
+#[derive(Facet, Debug, Clone, PartialEq, Eq, Hash)]
+#[repr(u8)]
+pub enum CargoFeature {
+    /// A feature for testing Cargo's own feature gates.
+    #[facet(rename = "test-dummy-unstable")]
+    TestDummyUnstable,
+    /// `metabuild` build scripts.
+    #[facet(rename = "metabuild")]
+    Metabuild,
+    /// `public = true` dependencies.
+    #[facet(rename = "public-dependency")]
+    PublicDependency,
+    /// `forced-target` and `default-target` in `[package]`.
+    #[facet(rename = "per-package-target")]
+    PerPackageTarget,
+    /// `filename` for `[[bin]]` targets.
+    #[facet(rename = "different-binary-name")]
+    DifferentBinaryName,
+    /// `codegen-backend` in profiles.
+    #[facet(rename = "codegen-backend")]
+    CodegenBackend,
+    /// `rustflags` in profiles.
+    #[facet(rename = "profile-rustflags")]
+    ProfileRustflags,
+    /// `trim-paths` in profiles.
+    #[facet(rename = "trim-paths")]
+    TrimPaths,
+    /// Package names with `::` namespaces.
+    #[facet(rename = "open-namespaces")]
+    OpenNamespaces,
+    /// `base` for path dependencies.
+    #[facet(rename = "path-bases")]
+    PathBases,
+    /// Editions that are not stable yet.
+    #[facet(rename = "unstable-editions")]
+    UnstableEditions,
+    /// A list of scripts in `package.build`.
+    #[facet(rename = "multiple-build-scripts")]
+    MultipleBuildScripts,
+    /// A feature for testing Cargo's own feature gates; stable since 1.0.
+    #[facet(rename = "test-dummy-stable")]
+    TestDummyStable,
+    /// Stable since 1.31.
+    #[facet(rename = "edition")]
+    Edition,
+    /// Stable since 1.31.
+    #[facet(rename = "rename-dependency")]
+    RenameDependency,
+    /// Stable since 1.34.
+    #[facet(rename = "alternative-registries")]
+    AlternativeRegistries,
+    /// Stable since 1.37.
+    #[facet(rename = "default-run")]
+    DefaultRun,
+    /// Stable since 1.41.
+    #[facet(rename = "profile-overrides")]
+    ProfileOverrides,
+    /// Stable since 1.51.
+    #[facet(rename = "resolver")]
+    Resolver,
+    /// Stable since 1.56.
+    #[facet(rename = "rust-version")]
+    RustVersion,
+    /// Stable since 1.56.
+    #[facet(rename = "edition2021")]
+    Edition2021,
+    /// Stable since 1.57.
+    #[facet(rename = "named-profiles")]
+    NamedProfiles,
+    /// Stable since 1.58.
+    #[facet(rename = "strip")]
+    Strip,
+    /// Stable since 1.64.
+    #[facet(rename = "workspace-inheritance")]
+    WorkspaceInheritance,
+    /// Stable since 1.85.
+    #[facet(rename = "edition2024")]
+    Edition2024,
+    /// A feature this crate does not know.
+    #[facet(other)]
+    Unknown(String),
+}
+
+impl CargoFeature {
+    /// The name as written in `cargo-features`.
+    pub fn name(&self) -> &str {
+        match self {
+            CargoFeature::TestDummyUnstable => "test-dummy-unstable",
+            CargoFeature::Metabuild => "metabuild",
+            CargoFeature::PublicDependency => "public-dependency",
+            CargoFeature::PerPackageTarget => "per-package-target",
+            CargoFeature::DifferentBinaryName => "different-binary-name",
+            CargoFeature::CodegenBackend => "codegen-backend",
+            CargoFeature::ProfileRustflags => "profile-rustflags",
+            CargoFeature::TrimPaths => "trim-paths",
+            CargoFeature::OpenNamespaces => "open-namespaces",
+            CargoFeature::PathBases => "path-bases",
+            CargoFeature::UnstableEditions => "unstable-editions",
+            CargoFeature::MultipleBuildScripts => "multiple-build-scripts",
+            CargoFeature::TestDummyStable => "test-dummy-stable",
+            CargoFeature::Edition => "edition",
+            CargoFeature::RenameDependency => "rename-dependency",
+            CargoFeature::AlternativeRegistries => "alternative-registries",
+            CargoFeature::DefaultRun => "default-run",
+            CargoFeature::ProfileOverrides => "profile-overrides",
+            CargoFeature::Resolver => "resolver",
+            CargoFeature::RustVersion => "rust-version",
+            CargoFeature::Edition2021 => "edition2021",
+            CargoFeature::NamedProfiles => "named-profiles",
+            CargoFeature::Strip => "strip",
+            CargoFeature::WorkspaceInheritance => "workspace-inheritance",
+            CargoFeature::Edition2024 => "edition2024",
+            CargoFeature::Unknown(name) => name,
+        }
+    }
+
+    /// The release that stabilized the feature, like `"1.85"`, or `None` if
+    /// it still needs a nightly Cargo or is unknown.
+    pub fn stabilized_in(&self) -> Option<&'static str> {
+        match self {
+            CargoFeature::TestDummyUnstable
+            | CargoFeature::Metabuild
+            | CargoFeature::PublicDependency
+            | CargoFeature::PerPackageTarget
+            | CargoFeature::DifferentBinaryName
+            | CargoFeature::CodegenBackend
+            | CargoFeature::ProfileRustflags
+            | CargoFeature::TrimPaths
+            | CargoFeature::OpenNamespaces
+            | CargoFeature::PathBases
+            | CargoFeature::UnstableEditions
+            | CargoFeature::MultipleBuildScripts
+            | CargoFeature::Unknown(_) => None,
+            CargoFeature::TestDummyStable => Some("1.0"),
+            CargoFeature::Edition | CargoFeature::RenameDependency => Some("1.31"),
+            CargoFeature::AlternativeRegistries => Some("1.34"),
+            CargoFeature::DefaultRun => Some("1.37"),
+            CargoFeature::ProfileOverrides => Some("1.41"),
+            CargoFeature::Resolver => Some("1.51"),
+            CargoFeature::RustVersion | CargoFeature::Edition2021 => Some("1.56"),
+            CargoFeature::NamedProfiles => Some("1.57"),
+            CargoFeature::Strip => Some("1.58"),
+            CargoFeature::WorkspaceInheritance => Some("1.64"),
+            CargoFeature::Edition2024 => Some("1.85"),
+        }
+    }
+
+    /// Whether the feature still needs a nightly Cargo.
+    pub fn is_unstable(&self) -> bool {
+        self.stabilized_in().is_none()
+    }
+}
+
+/// The `[hints]` section: suggestions for how dependents should build the
+/// package, which Cargo may ignore.
+#[derive(Facet, Debug, Clone, Default)]
+#[facet(rename_all = "kebab-case")]
+pub struct Hints {
+    /// Most of the package's items are expected to go unused by dependents,
+    /// so Cargo may build it in a way that speeds up compiling dependents.
+    pub mostly_unused: Option<Spanned<bool>>,
+}
+
 /// The `[workspace]` section of a Cargo.toml.
 #[derive(Facet, Debug, Clone)]
 #[facet(rename_all = "kebab-case")]
@@ -299,6 +513,10 @@ pub struct WorkspacePackage {
     pub keywords: Option<Spanned<Vec<String>>>,
     /// Categories from crates.io's predefined list.
     pub categories: Option<Spanned<Vec<String>>>,
+    /// Gitignore-style patterns for files to exclude when publishing.
+    pub exclude: Option<Spanned<Vec<String>>>,
+    /// Gitignore-style patterns for files to explicitly include when publishing.
+    pub include: Option<Spanned<Vec<String>>>,
     /// Controls publishing to registries.
     pub publish: Option<BoolOrVec>,
 }
@@ -317,6 +535,28 @@ pub enum Dependency {
     Detailed(DependencyDetail),
 }
 
+/// The kind of dependency table an entry appears in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum DependencyKind {
+    /// `[dependencies]`.
+    Normal,
+    /// `[dev-dependencies]`.
+    Development,
+    /// `[build-dependencies]`.
+    Build,
+}
+
+impl DependencyKind {
+    /// The TOML table name for this kind, e.g. `dev-dependencies`.
+    pub fn table_name(self) -> &'static str {
+        match self {
+            DependencyKind::Normal => "dependencies",
+            DependencyKind::Development => "dev-dependencies",
+            DependencyKind::Build => "build-dependencies",
+        }
+    }
+}
+
 /// Detailed dependency specification.
 #[derive(Facet, Debug, Clone, Default)]
 #[facet(rename_all = "kebab-case")]
@@ -325,6 +565,9 @@ pub struct DependencyDetail {
     pub version: Option<Spanned<String>>,
     /// A file system path to a local crate directory.
     pub path: Option<Spanned<String>>,
+    /// The name of a `[path-bases]` entry in `.cargo/config.toml` that `path`
+    /// is relative to (unstable).
+    pub base: Option<Spanned<String>>,
     /// A URL to a Git repository containing the crate source code.
     pub git: Option<Spanned<String>>,
     /// The Git branch to use when fetching a Git dependency.
@@ -342,11 +585,20 @@ pub struct DependencyDetail {
     /// A list of crate features to enable for this dependency.
     pub features: Option<Spanned<Vec<String>>>,
     /// Whether to include the dependency's default features.
+    #[facet(alias = "default_features")]
     pub default_features: Option<Spanned<bool>>,
     /// Whether this dependency is optional (enabled via features).
     pub optional: Option<Spanned<bool>>,
     /// Whether this dependency is part of the crate's public API (unstable).
     pub public: Option<Spanned<bool>>,
+    /// The artifacts to build the dependency as, making this an artifact
+    /// dependency (unstable); see [`DependencyDetail::artifact_kinds`].
+    pub artifact: Option<StringOrVec>,
+    /// For an artifact dependency, whether to also depend on its library.
+    pub lib: Option<Spanned<bool>>,
+    /// For an artifact dependency, the target triple to build the artifacts
+    /// for, or `"target"` for the target being built.
+    pub target: Option<Spanned<String>>,
     /// Additional metadata for external tools.
     pub metadata: Option<facet_value::Value>,
 }
@@ -362,6 +614,7 @@ pub struct WorkspaceDependency {
     /// Override the optional setting from the workspace dependency.
     pub optional: Option<Spanned<bool>>,
     /// Override the default-features setting from the workspace dependency.
+    #[facet(alias = "default_features")]
     pub default_features: Option<Spanned<bool>>,
 }
 
@@ -373,10 +626,10 @@ pub struct TargetSpec {
     #[facet(default)]
     pub dependencies: Option<HashMap<String, Dependency>>,
     /// Target-specific development dependencies.
-    #[facet(default)]
+    #[facet(default, alias = "dev_dependencies")]
     pub dev_dependencies: Option<HashMap<String, Dependency>>,
     /// Target-specific build dependencies.
-    #[facet(default)]
+    #[facet(default, alias = "build_dependencies")]
     pub build_dependencies: Option<HashMap<String, Dependency>>,
 }
 
@@ -399,12 +652,14 @@ pub struct LibTarget {
     /// Deprecated and unused.
     pub plugin: Option<Spanned<bool>>,
     /// Whether the library is a procedural macro.
+    #[facet(alias = "proc_macro")]
     pub proc_macro: Option<Spanned<bool>>,
     /// Whether to use the libtest harness for `#[test]` functions.
     pub harness: Option<Spanned<bool>>,
     /// The Rust edition the target will use.
     pub edition: Option<Spanned<Edition>>,
     /// The crate types to generate (e.g., `lib`, `rlib`, `dylib`, `cdylib`, `staticlib`).
+    #[facet(alias = "crate_type")]
     pub crate_type: Option<Spanned<Vec<String>>>,
     /// Features required for the target to be built.
     pub required_features: Option<Spanned<Vec<String>>>,
@@ -515,6 +770,7 @@ pub struct ExampleTarget {
     /// Features required for the target to be built.
     pub required_features: Option<Spanned<Vec<String>>>,
     /// The crate types to generate for this example.
+    #[facet(alias = "crate_type")]
     pub crate_type: Option<Spanned<Vec<String>>>,
 }
 
@@ -558,10 +814,22 @@ pub struct Profile {
 #[repr(u8)]
 #[facet(untagged)]
 pub enum OptLevel {
+    /// Size optimization ("s" or "z").
+    Named(Spanned<OptLevelName>),
     /// Numeric optimization level (0-3).
     Number(Spanned<u8>),
-    /// Size optimization ("s" or "z").
-    String(Spanned<String>),
+}
+
+/// The named optimization levels.
+#[derive(Facet, Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u8)]
+pub enum OptLevelName {
+    /// Optimize for binary size.
+    #[facet(rename = "s")]
+    S,
+    /// Optimize for binary size, and also turn off loop vectorization.
+    #[facet(rename = "z")]
+    Z,
 }
 
 /// Debug information level.
@@ -569,12 +837,33 @@ pub enum OptLevel {
 #[repr(u8)]
 #[facet(untagged)]
 pub enum DebugLevel {
+    /// Named debug level ("none", "line-tables-only", "full", ...).
+    Named(Spanned<DebugLevelName>),
     /// Boolean debug info (true = full, false = none).
     Bool(Spanned<bool>),
     /// Numeric debug level (0, 1, or 2).
     Number(Spanned<u8>),
-    /// Named debug level ("line-tables-only", "line-directives-only").
-    String(Spanned<String>),
+}
+
+/// The named debug information levels.
+#[derive(Facet, Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u8)]
+pub enum DebugLevelName {
+    /// No debug info, like `false` or `0`.
+    #[facet(rename = "none")]
+    None,
+    /// Line info directives only.
+    #[facet(rename = "line-directives-only")]
+    LineDirectivesOnly,
+    /// Line tables only.
+    #[facet(rename = "line-tables-only")]
+    LineTablesOnly,
+    /// Debug info without type or variable-level information, like `1`.
+    #[facet(rename = "limited")]
+    Limited,
+    /// Full debug info, like `true` or `2`.
+    #[facet(rename = "full")]
+    Full,
 }
 
 /// Link-time optimization setting.
@@ -582,14 +871,29 @@ pub enum DebugLevel {
 #[repr(u8)]
 #[facet(untagged)]
 pub enum Lto {
-    /// Boolean LTO (true = "fat", false = disabled).
-    Bool(Spanned<bool>),
     /// Named LTO mode ("thin", "fat", "off").
-    String(Spanned<String>),
+    Named(Spanned<LtoName>),
+    /// Boolean LTO (true = "fat", false = thin LTO of the local crate only).
+    Bool(Spanned<bool>),
+}
+
+/// The named LTO modes.
+#[derive(Facet, Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u8)]
+pub enum LtoName {
+    /// Thin LTO across all crates in the dependency graph.
+    #[facet(rename = "thin")]
+    Thin,
+    /// Full LTO across all crates in the dependency graph, like `true`.
+    #[facet(rename = "fat")]
+    Fat,
+    /// No LTO at all.
+    #[facet(rename = "off")]
+    Off,
 }
 
 /// Panic strategy.
-#[derive(Facet, Debug, Clone, Copy)]
+#[derive(Facet, Debug, Clone, Copy, PartialEq, Eq)]
 #[repr(u8)]
 pub enum PanicStrategy {
     /// Unwind the stack on panic.
@@ -608,10 +912,25 @@ pub enum PanicStrategy {
 #[repr(u8)]
 #[facet(untagged)]
 pub enum StripLevel {
+    /// Named strip level ("none", "debuginfo", "symbols").
+    Named(Spanned<StripLevelName>),
     /// Boolean strip (true = all symbols, false = none).
     Bool(Spanned<bool>),
-    /// Named strip level ("none", "debuginfo", "symbols").
-    String(Spanned<String>),
+}
+
+/// The named strip levels.
+#[derive(Facet, Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u8)]
+pub enum StripLevelName {
+    /// Strip nothing, like `false`.
+    #[facet(rename = "none")]
+    None,
+    /// Strip debug info.
+    #[facet(rename = "debuginfo")]
+    Debuginfo,
+    /// Strip debug info and symbols, like `true`.
+    #[facet(rename = "symbols")]
+    Symbols,
 }
 
 /// Per-package profile overrides.
@@ -666,20 +985,20 @@ pub struct Lints {
 #[repr(u8)]
 #[facet(untagged)]
 pub enum LintLevel {
+    /// A plain level, like `"deny"`.
+    Level(Spanned<LintLevelString>),
     /// Detailed config with priority.
     Config(LintConfig),
-    /// Forbid the lint (error, cannot be overridden).
-    #[facet(rename = "forbid")]
-    Forbid,
-    /// Deny the lint (error).
-    #[facet(rename = "deny")]
-    Deny,
-    /// Warn on the lint.
-    #[facet(rename = "warn")]
-    Warn,
-    /// Allow the lint (silence it).
-    #[facet(rename = "allow")]
-    Allow,
+}
+
+impl LintLevel {
+    /// The level, whichever form it was written in.
+    pub fn level(&self) -> &Spanned<LintLevelString> {
+        match self {
+            LintLevel::Level(level) => level,
+            LintLevel::Config(config) => &config.level,
+        }
+    }
 }
 
 /// Detailed lint configuration with priority.
@@ -695,7 +1014,7 @@ pub struct LintConfig {
 }
 
 /// Simple lint level string.
-#[derive(Facet, Debug, Clone, Copy)]
+#[derive(Facet, Debug, Clone, Copy, PartialEq, Eq)]
 #[repr(u8)]
 pub enum LintLevelString {
     /// Forbid the lint.
@@ -723,9 +1042,12 @@ pub struct Badge {
 impl CargoToml {
     /// Parse a `Cargo.toml` from a string.
     pub fn parse(contents: &str) -> Result<Self, crate::Error> {
-        facet_toml::from_str(contents).map_err(|e| crate::Error::Parse {
-            message: e.to_string(),
-        })
+        let manifest: Self = facet_toml::from_str(contents).map_err(|e| crate::Error::Parse {
+            diagnostic: Box::new(crate::Diagnostic::from_toml(contents, e)),
+        })?;
+        crate::cfg::check_target_keys(contents, &manifest)?;
+        crate::spelling::reject_both_spellings(contents)?;
+        Ok(manifest)
     }
 
     /// Parse a `Cargo.toml` from a file path.
@@ -735,6 +1057,20 @@ impl CargoToml {
             path: path.to_owned(),
             source: crate::IoError::from(source),
         })?;
-        Self::parse(&contents)
+        Self::parse(&contents).map_err(|e| e.with_path(path))
+    }
+
+    /// The `cargo-features` entries that still need a nightly Cargo.
+    pub fn unstable_features(&self) -> impl Iterator<Item = &Spanned<CargoFeature>> {
+        self.cargo_features
+            .iter()
+            .flatten()
+            .filter(|feature| feature.value.is_unstable())
+    }
+
+    /// Whether only a nightly Cargo accepts this manifest, because of its
+    /// `cargo-features`.
+    pub fn requires_nightly(&self) -> bool {
+        self.unstable_features().next().is_some()
     }
 }
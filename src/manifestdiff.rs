@@ -0,0 +1,671 @@
+//! Comparing two manifests key by key.
+
+use std::borrow::Cow;
+use std::collections::{BTreeSet, HashMap};
+use std::fmt;
+
+use crate::lockcheck::dependency_span;
+use crate::serialize::{ToToml, inline_toml, toml_key};
+use crate::{
+    BoolOrVec, BoolOrVecOrWorkspace, CargoToml, DebugLevel, Dependency, DependencyDetail,
+    EditionOrWorkspace, LintLevel, Lints, Lto, OptLevel, Profile, Resolver, Span, Spanned,
+    StringOrBool, StringOrBoolOrWorkspace, StringOrVec, StringOrWorkspace, StripLevel,
+    VecOrWorkspace, Workspace, WorkspaceRef,
+};
+
+/// What part of a manifest a [`ManifestChange`] is in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ChangeCategory {
+    /// `[package]` or `[workspace.package]` metadata, like `license`,
+    /// `edition` or `rust-version`.
+    Package,
+    /// A dependency, in any dependency table including
+    /// `[workspace.dependencies]`.
+    Dependency,
+    /// A `[features]` entry.
+    Feature,
+    /// A `[profile.*]` setting.
+    Profile,
+    /// A `[lints]` or `[workspace.lints]` level.
+    Lint,
+}
+
+/// Whether a [`ManifestChange`] adds, removes or changes a value.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ChangeKind {
+    /// The key is only in the new manifest.
+    Added,
+    /// The key is only in the old manifest.
+    Removed,
+    /// The key is in both, with different values.
+    Changed,
+}
+
+/// A value on one side of a [`ManifestChange`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ChangedValue {
+    /// The value as inline TOML, like `"1.0"` or `{ workspace = true }`.
+    pub value: String,
+    /// Where the value is in its manifest.
+    pub span: Option<Span>,
+}
+
+/// One key whose value differs between two manifests.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ManifestChange {
+    /// What part of the manifest the key is in.
+    pub category: ChangeCategory,
+    /// Dotted path of the key, like `dependencies.serde.version` or
+    /// `profile.release.lto`.
+    pub key: String,
+    /// The value in the old manifest, unless the key was added.
+    pub old: Option<ChangedValue>,
+    /// The value in the new manifest, unless the key was removed.
+    pub new: Option<ChangedValue>,
+}
+
+impl ManifestChange {
+    /// Whether the key was added, removed or changed.
+    pub fn kind(&self) -> ChangeKind {
+        match (&self.old, &self.new) {
+            (None, _) => ChangeKind::Added,
+            (_, None) => ChangeKind::Removed,
+            _ => ChangeKind::Changed,
+        }
+    }
+}
+
+/// The result of [`CargoToml::diff`].
+#[derive(Debug, Clone, Default)]
+pub struct ManifestDiff {
+    /// The changes, grouped by category in manifest order: package metadata,
+    /// dependencies, features, profiles, then lints. Keys are sorted within
+    /// each table.
+    pub changes: Vec<ManifestChange>,
+}
+
+impl ManifestDiff {
+    /// Returns true if the manifests agree on every key compared.
+    pub fn is_empty(&self) -> bool {
+        self.changes.is_empty()
+    }
+
+    /// The changes in one category.
+    pub fn of_category(&self, category: ChangeCategory) -> impl Iterator<Item = &ManifestChange> {
+        self.changes.iter().filter(move |c| c.category == category)
+    }
+}
+
+impl CargoToml {
+    /// Compare this manifest, as the old one, with `new`.
+    ///
+    /// Dependencies written in the same form are compared field by field, so
+    /// a requirement change is reported as `<table>.<name>.version`; switching
+    /// between forms, like `"1"` to `{ workspace = true }`, is reported on the
+    /// dependency as a whole. `[workspace.package]` changes are reported as
+    /// package metadata, under `workspace.package.<key>`.
+    ///
+    /// Not compared: `cargo-features`, targets, `[patch]`, `[replace]`,
+    /// `[hints]`, badges, `package.metadata`, and `workspace.members`,
+    /// `exclude`, `default-members`, `resolver` and `metadata`.
+    pub fn diff(&self, new: &CargoToml) -> ManifestDiff {
+        let mut differ = Differ::default();
+        differ.package(self, new);
+        let (old_workspace, new_workspace) = (self.workspace.as_ref(), new.workspace.as_ref());
+        differ.workspace_package(old_workspace, new_workspace);
+
+        differ.dependencies(
+            "dependencies",
+            self.dependencies.as_ref(),
+            new.dependencies.as_ref(),
+        );
+        differ.dependencies(
+            "dev-dependencies",
+            self.dev_dependencies.as_ref(),
+            new.dev_dependencies.as_ref(),
+        );
+        differ.dependencies(
+            "build-dependencies",
+            self.build_dependencies.as_ref(),
+            new.build_dependencies.as_ref(),
+        );
+        let (old_targets, new_targets) = (self.target.as_ref(), new.target.as_ref());
+        for platform in keys(old_targets, new_targets) {
+            let old = old_targets.and_then(|t| t.get(platform));
+            let new = new_targets.and_then(|t| t.get(platform));
+            let prefix = format!("target.{}", toml_key(platform));
+            differ.dependencies(
+                &format!("{prefix}.dependencies"),
+                old.and_then(|t| t.dependencies.as_ref()),
+                new.and_then(|t| t.dependencies.as_ref()),
+            );
+            differ.dependencies(
+                &format!("{prefix}.dev-dependencies"),
+                old.and_then(|t| t.dev_dependencies.as_ref()),
+                new.and_then(|t| t.dev_dependencies.as_ref()),
+            );
+            differ.dependencies(
+                &format!("{prefix}.build-dependencies"),
+                old.and_then(|t| t.build_dependencies.as_ref()),
+                new.and_then(|t| t.build_dependencies.as_ref()),
+            );
+        }
+        differ.dependencies(
+            "workspace.dependencies",
+            old_workspace.and_then(|w| w.dependencies.as_ref()),
+            new_workspace.and_then(|w| w.dependencies.as_ref()),
+        );
+
+        differ.map(
+            ChangeCategory::Feature,
+            "features",
+            self.features.as_ref(),
+            new.features.as_ref(),
+        );
+
+        let (old_profiles, new_profiles) = (self.profile.as_ref(), new.profile.as_ref());
+        for name in keys(old_profiles, new_profiles) {
+            differ.profile(
+                &format!("profile.{}", toml_key(name)),
+                old_profiles.and_then(|p| p.get(name)),
+                new_profiles.and_then(|p| p.get(name)),
+            );
+        }
+
+        differ.lints("lints", self.lints.as_ref(), new.lints.as_ref());
+        differ.lints(
+            "workspace.lints",
+            old_workspace.and_then(|w| w.lints.as_ref()),
+            new_workspace.and_then(|w| w.lints.as_ref()),
+        );
+
+        ManifestDiff {
+            changes: differ.changes,
+        }
+    }
+}
+
+/// Compare the `$field`s of two `Option<&Struct>`s, as `$prefix.$key`.
+macro_rules! fields {
+    ($differ:expr, $category:expr, $prefix:expr, $old:expr, $new:expr, {
+        $($field:ident: $key:literal),* $(,)?
+    }) => {
+        $(
+            $differ.value(
+                $category,
+                format!("{}.{}", $prefix, $key),
+                $old.and_then(|o| o.$field.as_ref()),
+                $new.and_then(|n| n.$field.as_ref()),
+            );
+        )*
+    };
+}
+
+#[derive(Default)]
+struct Differ {
+    changes: Vec<ManifestChange>,
+}
+
+impl Differ {
+    fn value<T: Located>(
+        &mut self,
+        category: ChangeCategory,
+        key: String,
+        old: Option<&T>,
+        new: Option<&T>,
+    ) {
+        let side = |value: &T| ChangedValue {
+            value: inline_toml(value),
+            span: value.span(),
+        };
+        let (old, new) = (old.map(side), new.map(side));
+        if old.as_ref().map(|v| &v.value) != new.as_ref().map(|v| &v.value) {
+            self.changes.push(ManifestChange {
+                category,
+                key,
+                old,
+                new,
+            });
+        }
+    }
+
+    fn map<T: Located>(
+        &mut self,
+        category: ChangeCategory,
+        prefix: &str,
+        old: Option<&HashMap<String, T>>,
+        new: Option<&HashMap<String, T>>,
+    ) {
+        for key in keys(old, new) {
+            self.value(
+                category,
+                format!("{prefix}.{}", toml_key(key)),
+                old.and_then(|m| m.get(key)),
+                new.and_then(|m| m.get(key)),
+            );
+        }
+    }
+
+    fn package(&mut self, old: &CargoToml, new: &CargoToml) {
+        let (old, new) = (old.package.as_ref(), new.package.as_ref());
+        fields!(self, ChangeCategory::Package, "package", old, new, {
+            name: "name",
+            version: "version",
+            authors: "authors",
+            edition: "edition",
+            rust_version: "rust-version",
+            description: "description",
+            documentation: "documentation",
+            readme: "readme",
+            homepage: "homepage",
+            repository: "repository",
+            license: "license",
+            license_file: "license-file",
+            keywords: "keywords",
+            categories: "categories",
+            workspace: "workspace",
+            build: "build",
+            links: "links",
+            exclude: "exclude",
+            include: "include",
+            publish: "publish",
+            default_run: "default-run",
+            autolib: "autolib",
+            autobins: "autobins",
+            autoexamples: "autoexamples",
+            autotests: "autotests",
+            autobenches: "autobenches",
+            resolver: "resolver",
+        });
+    }
+
+    fn workspace_package(&mut self, old: Option<&Workspace>, new: Option<&Workspace>) {
+        let (old, new) = (
+            old.and_then(|w| w.package.as_ref()),
+            new.and_then(|w| w.package.as_ref()),
+        );
+        fields!(self, ChangeCategory::Package, "workspace.package", old, new, {
+            version: "version",
+            authors: "authors",
+            edition: "edition",
+            rust_version: "rust-version",
+            description: "description",
+            documentation: "documentation",
+            readme: "readme",
+            homepage: "homepage",
+            repository: "repository",
+            license: "license",
+            license_file: "license-file",
+            keywords: "keywords",
+            categories: "categories",
+            exclude: "exclude",
+            include: "include",
+            publish: "publish",
+        });
+    }
+
+    fn dependencies(
+        &mut self,
+        table: &str,
+        old: Option<&HashMap<String, Dependency>>,
+        new: Option<&HashMap<String, Dependency>>,
+    ) {
+        let category = ChangeCategory::Dependency;
+        for name in keys(old, new) {
+            let key = format!("{table}.{}", toml_key(name));
+            let (old, new) = (old.and_then(|m| m.get(name)), new.and_then(|m| m.get(name)));
+            match (old, new) {
+                (Some(Dependency::Workspace(old)), Some(Dependency::Workspace(new))) => {
+                    let (old, new) = (Some(old), Some(new));
+                    self.value(
+                        category,
+                        format!("{key}.workspace"),
+                        old.map(|o| &o.workspace),
+                        new.map(|n| &n.workspace),
+                    );
+                    fields!(self, category, key, old, new, {
+                        features: "features",
+                        optional: "optional",
+                        default_features: "default-features",
+                    });
+                }
+                (
+                    Some(old @ (Dependency::Version(_) | Dependency::Detailed(_))),
+                    Some(new @ (Dependency::Version(_) | Dependency::Detailed(_))),
+                ) => {
+                    let (old, new) = (detail(old), detail(new));
+                    let (old, new) = (Some(&*old), Some(&*new));
+                    fields!(self, category, key, old, new, {
+                        version: "version",
+                        path: "path",
+                        base: "base",
+                        git: "git",
+                        branch: "branch",
+                        tag: "tag",
+                        rev: "rev",
+                        registry: "registry",
+                        registry_index: "registry-index",
+                        package: "package",
+                        features: "features",
+                        default_features: "default-features",
+                        optional: "optional",
+                        public: "public",
+                        artifact: "artifact",
+                        lib: "lib",
+                        target: "target",
+                        metadata: "metadata",
+                    });
+                }
+                (old, new) => self.value(category, key, old, new),
+            }
+        }
+    }
+
+    fn profile(&mut self, prefix: &str, old: Option<&Profile>, new: Option<&Profile>) {
+        let category = ChangeCategory::Profile;
+        fields!(self, category, prefix, old, new, {
+            inherits: "inherits",
+            opt_level: "opt-level",
+            debug: "debug",
+            split_debuginfo: "split-debuginfo",
+            strip: "strip",
+            debug_assertions: "debug-assertions",
+            overflow_checks: "overflow-checks",
+            lto: "lto",
+            panic: "panic",
+            incremental: "incremental",
+            codegen_units: "codegen-units",
+            rpath: "rpath",
+        });
+
+        let (old_packages, new_packages) = (
+            old.and_then(|p| p.package.as_ref()),
+            new.and_then(|p| p.package.as_ref()),
+        );
+        for name in keys(old_packages, new_packages) {
+            let (old, new) = (
+                old_packages.and_then(|p| p.get(name)),
+                new_packages.and_then(|p| p.get(name)),
+            );
+            let prefix = format!("{prefix}.package.{}", toml_key(name));
+            fields!(self, category, prefix, old, new, {
+                opt_level: "opt-level",
+                debug: "debug",
+                debug_assertions: "debug-assertions",
+                overflow_checks: "overflow-checks",
+                codegen_units: "codegen-units",
+            });
+        }
+
+        let (old, new) = (
+            old.and_then(|p| p.build_override.as_ref()),
+            new.and_then(|p| p.build_override.as_ref()),
+        );
+        fields!(self, category, format!("{prefix}.build-override"), old, new, {
+            opt_level: "opt-level",
+            debug: "debug",
+            debug_assertions: "debug-assertions",
+            overflow_checks: "overflow-checks",
+            codegen_units: "codegen-units",
+            incremental: "incremental",
+        });
+    }
+
+    fn lints(&mut self, prefix: &str, old: Option<&Lints>, new: Option<&Lints>) {
+        let category = ChangeCategory::Lint;
+        fields!(self, category, prefix, old, new, { workspace: "workspace" });
+        fn tools(lints: Option<&Lints>) -> [Option<&HashMap<String, LintLevel>>; 3] {
+            lints.map_or([None; 3], |l| {
+                [l.rust.as_ref(), l.clippy.as_ref(), l.rustdoc.as_ref()]
+            })
+        }
+        for ((tool, old), new) in ["rust", "clippy", "rustdoc"]
+            .into_iter()
+            .zip(tools(old))
+            .zip(tools(new))
+        {
+            self.map(category, &format!("{prefix}.{tool}"), old, new);
+        }
+    }
+}
+
+/// The keys of two optional maps, in order.
+fn keys<'a, T>(
+    old: Option<&'a HashMap<String, T>>,
+    new: Option<&'a HashMap<String, T>>,
+) -> BTreeSet<&'a String> {
+    old.into_iter().chain(new).flat_map(|m| m.keys()).collect()
+}
+
+/// A `"1.0"` dependency as the table it abbreviates, so it compares field
+/// by field with `{ version = "1.0", ... }`.
+fn detail(dependency: &Dependency) -> Cow<'_, DependencyDetail> {
+    match dependency {
+        Dependency::Detailed(detail) => Cow::Borrowed(detail),
+        Dependency::Version(version) => Cow::Owned(DependencyDetail {
+            version: Some(version.clone()),
+            ..DependencyDetail::default()
+        }),
+        Dependency::Workspace(_) => unreachable!("workspace dependencies are compared as such"),
+    }
+}
+
+/// A manifest value that knows where it was parsed from.
+trait Located: ToToml {
+    fn span(&self) -> Option<Span>;
+}
+
+impl<T: ToToml> Located for Spanned<T> {
+    fn span(&self) -> Option<Span> {
+        self.span
+    }
+}
+
+/// A feature's list, spanning its first to its last value.
+impl<T: ToToml> Located for Vec<Spanned<T>> {
+    fn span(&self) -> Option<Span> {
+        let (first, last) = (self.first()?.span?, self.last()?.span?);
+        Some(Span {
+            offset: first.offset,
+            len: (last.offset + last.len).saturating_sub(first.offset),
+        })
+    }
+}
+
+impl Located for WorkspaceRef {
+    fn span(&self) -> Option<Span> {
+        self.workspace.span
+    }
+}
+
+impl Located for Dependency {
+    fn span(&self) -> Option<Span> {
+        dependency_span(self)
+    }
+}
+
+impl Located for LintLevel {
+    fn span(&self) -> Option<Span> {
+        self.level().span
+    }
+}
+
+impl Located for Resolver {
+    fn span(&self) -> Option<Span> {
+        None
+    }
+}
+
+impl Located for facet_value::Value {
+    fn span(&self) -> Option<Span> {
+        None
+    }
+}
+
+/// `Located` for an untagged enum whose variants are all `Located`.
+macro_rules! located_enum {
+    ($($ty:ident { $($variant:ident),* })*) => {
+        $(
+            impl Located for $ty {
+                fn span(&self) -> Option<Span> {
+                    match self {
+                        $($ty::$variant(value) => value.span(),)*
+                    }
+                }
+            }
+        )*
+    };
+}
+
+located_enum! {
+    StringOrWorkspace { Workspace, String }
+    VecOrWorkspace { Workspace, Values }
+    EditionOrWorkspace { Edition, Workspace }
+    StringOrBoolOrWorkspace { Workspace, Bool, String }
+    StringOrBool { String, Bool }
+    BoolOrVec { Bool, Vec }
+    StringOrVec { String, Vec }
+    BoolOrVecOrWorkspace { Workspace, Bool, Vec }
+    OptLevel { Named, Number }
+    DebugLevel { Named, Bool, Number }
+    Lto { Named, Bool }
+    StripLevel { Named, Bool }
+}
+
+/// One line per change, like `+ dependencies.log = "0.4"` or
+/// `~ profile.release.lto = "thin" -> true`.
+impl fmt::Display for ManifestDiff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for change in &self.changes {
+            match (&change.old, &change.new) {
+                (None, Some(new)) => writeln!(f, "+ {} = {}", change.key, new.value)?,
+                (Some(old), None) => writeln!(f, "- {} = {}", change.key, old.value)?,
+                (Some(old), Some(new)) => {
+                    writeln!(f, "~ {} = {} -> {}", change.key, old.value, new.value)?
+                }
+                (None, None) => {}
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn diff_manifests() {
+        let old_toml = r#"
+[package]
+name = "app"
+edition = "2021"
+license = "MIT"
+
+[dependencies]
+serde = "1.0"
+log = "0.4"
+tokio = { version = "1", features = ["rt"] }
+
+[features]
+default = ["std"]
+std = []
+
+[profile.release]
+lto = "thin"
+
+[lints.rust]
+unsafe_code = "warn"
+"#;
+        let new_toml = r#"
+[package]
+name = "app"
+edition = "2024"
+license = "MIT OR Apache-2.0"
+rust-version = "1.85"
+
+[dependencies]
+serde = { version = "1.0.200", features = ["derive"] }
+tokio = { version = "1", features = ["rt"] }
+anyhow = "1"
+
+[features]
+default = ["std", "tracing"]
+std = []
+
+[profile.release]
+lto = true
+codegen-units = 1
+
+[lints.rust]
+unsafe_code = "forbid"
+"#;
+        let old = CargoToml::parse(old_toml).unwrap();
+        let new = CargoToml::parse(new_toml).unwrap();
+        let diff = old.diff(&new);
+        assert_eq!(
+            diff.to_string(),
+            r#"~ package.edition = "2021" -> "2024"
++ package.rust-version = "1.85"
+~ package.license = "MIT" -> "MIT OR Apache-2.0"
++ dependencies.anyhow = "1"
+- dependencies.log = "0.4"
+~ dependencies.serde.version = "1.0" -> "1.0.200"
++ dependencies.serde.features = ["derive"]
+~ features.default = ["std"] -> ["std", "tracing"]
+~ profile.release.lto = "thin" -> true
++ profile.release.codegen-units = 1
+~ lints.rust.unsafe_code = "warn" -> "forbid"
+"#
+        );
+
+        let text = |source: &str, value: &Option<ChangedValue>| {
+            let span = value.as_ref().unwrap().span.unwrap();
+            source[span.offset as usize..][..span.len as usize].to_owned()
+        };
+        for change in &diff.changes {
+            if let Some(old) = &change.old {
+                assert!(
+                    old.value.contains(&text(old_toml, &change.old)),
+                    "{}",
+                    change.key
+                );
+            }
+            if let Some(new) = &change.new {
+                assert!(
+                    new.value.contains(&text(new_toml, &change.new)),
+                    "{}",
+                    change.key
+                );
+            }
+        }
+        assert_eq!(diff.of_category(ChangeCategory::Dependency).count(), 4);
+        assert_eq!(diff.changes[3].kind(), ChangeKind::Added);
+        assert!(new.diff(&new).is_empty());
+    }
+
+    #[test]
+    fn diff_workspace_package() {
+        let old = CargoToml::parse(
+            "[workspace]\nmembers = [\"a\"]\n\n[workspace.package]\nedition = \"2021\"\nlicense = \"MIT\"\n",
+        )
+        .unwrap();
+        let new = CargoToml::parse(
+            "[workspace]\nmembers = [\"a\", \"b\"]\n\n[workspace.package]\nedition = \"2024\"\nrust-version = \"1.85\"\nlicense = \"MIT\"\npublish = false\n",
+        )
+        .unwrap();
+        let diff = old.diff(&new);
+        assert_eq!(
+            diff.to_string(),
+            r#"~ workspace.package.edition = "2021" -> "2024"
++ workspace.package.rust-version = "1.85"
++ workspace.package.publish = false
+"#
+        );
+        assert!(
+            diff.changes
+                .iter()
+                .all(|c| c.category == ChangeCategory::Package)
+        );
+    }
+}
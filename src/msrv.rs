@@ -0,0 +1,418 @@
+//! The `rust-version` key: the oldest Rust toolchain a package supports.
+//!
+//! Like package versions, `rust-version` is kept as written and parsed on
+//! demand.
+
+use std::cmp::Ordering;
+use std::fmt;
+use std::hash::{Hash, Hasher};
+
+use camino::Utf8PathBuf;
+use semver::Version;
+
+use crate::inherit::inherit;
+use crate::{
+    CargoWorkspace, Edition, EditionOrWorkspace, Error, Package, Span, Spanned, StringOrWorkspace,
+    WorkspacePackage,
+};
+
+/// A `rust-version`, like `1.85` or `1.85.1`.
+///
+/// Unlike a package version, the patch number is optional and pre-release
+/// and build metadata are not allowed. A missing patch number compares as
+/// zero, so `1.85` and `1.85.0` are equal.
+#[derive(Debug, Clone, Copy)]
+pub struct RustVersion {
+    /// The major version.
+    pub major: u64,
+    /// The minor version.
+    pub minor: u64,
+    /// The patch version, if written.
+    pub patch: Option<u64>,
+}
+
+impl RustVersion {
+    /// Parse a `rust-version` value, like `1.85`.
+    pub fn parse(value: &str) -> Result<Self, Error> {
+        Self::parse_at(value, None)
+    }
+
+    fn parse_at(value: &str, span: Option<Span>) -> Result<Self, Error> {
+        let invalid = |reason: &str| Error::InvalidRustVersion {
+            value: value.to_owned(),
+            reason: reason.to_owned(),
+            span,
+        };
+        let expected = "expected a version like \"1.32\"";
+        if value.contains('-') {
+            return Err(invalid(&format!(
+                "unexpected pre-release field, {expected}"
+            )));
+        }
+        if value.contains('+') {
+            return Err(invalid(&format!(
+                "unexpected build metadata field, {expected}"
+            )));
+        }
+        let number = |part: &str| {
+            // Leading zeros are not allowed, as in semver.
+            let digits = !part.is_empty() && part.bytes().all(|b| b.is_ascii_digit());
+            if !digits || (part.len() > 1 && part.starts_with('0')) {
+                return None;
+            }
+            part.parse().ok()
+        };
+        let parts: Vec<_> = value.split('.').map(number).collect();
+        match parts[..] {
+            [Some(major), Some(minor)] => Ok(RustVersion {
+                major,
+                minor,
+                patch: None,
+            }),
+            [Some(major), Some(minor), Some(patch)] => Ok(RustVersion {
+                major,
+                minor,
+                patch: Some(patch),
+            }),
+            _ => Err(invalid(expected)),
+        }
+    }
+
+    /// This version as a semver version, with a missing patch number as zero.
+    pub fn to_version(&self) -> Version {
+        Version::new(self.major, self.minor, self.patch.unwrap_or(0))
+    }
+
+    fn key(&self) -> (u64, u64, u64) {
+        (self.major, self.minor, self.patch.unwrap_or(0))
+    }
+}
+
+impl PartialEq for RustVersion {
+    fn eq(&self, other: &Self) -> bool {
+        self.key() == other.key()
+    }
+}
+
+impl Eq for RustVersion {}
+
+impl Hash for RustVersion {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.key().hash(state);
+    }
+}
+
+impl PartialOrd for RustVersion {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for RustVersion {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.key().cmp(&other.key())
+    }
+}
+
+impl fmt::Display for RustVersion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}.{}", self.major, self.minor)?;
+        if let Some(patch) = self.patch {
+            write!(f, ".{patch}")?;
+        }
+        Ok(())
+    }
+}
+
+impl Edition {
+    /// The first Rust release supporting this edition.
+    pub fn first_rust_version(self) -> RustVersion {
+        let minor = match self {
+            Edition::E2015 => 0,
+            Edition::E2018 => 31,
+            Edition::E2021 => 56,
+            Edition::E2024 => 85,
+        };
+        RustVersion {
+            major: 1,
+            minor,
+            patch: Some(0),
+        }
+    }
+}
+
+impl fmt::Display for Edition {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Edition::E2015 => "2015",
+            Edition::E2018 => "2018",
+            Edition::E2021 => "2021",
+            Edition::E2024 => "2024",
+        })
+    }
+}
+
+/// Cargo's error for a `rust-version` older than the first release supporting
+/// `edition`.
+pub(crate) fn edition_mismatch(rust_version: RustVersion, edition: Edition) -> String {
+    format!(
+        "rust-version {rust_version} is older than first version ({}) required by the \
+         specified edition ({edition})",
+        edition.first_rust_version()
+    )
+}
+
+impl Spanned<String> {
+    /// Parse this value as a `rust-version`.
+    pub fn to_rust_version(&self) -> Result<RustVersion, Error> {
+        RustVersion::parse_at(&self.value, self.span)
+    }
+}
+
+impl Package {
+    /// The `rust-version`, with its span.
+    ///
+    /// Returns `None` when it is unset or inherited from the workspace; see
+    /// [`CargoWorkspace::rust_versions`] for inherited values.
+    pub fn msrv(&self) -> Result<Option<Spanned<RustVersion>>, Error> {
+        match &self.rust_version {
+            Some(StringOrWorkspace::String(version)) => spanned(version).map(Some),
+            _ => Ok(None),
+        }
+    }
+}
+
+impl WorkspacePackage {
+    /// The `[workspace.package]` `rust-version`, with its span.
+    pub fn msrv(&self) -> Result<Option<Spanned<RustVersion>>, Error> {
+        self.rust_version.as_ref().map(spanned).transpose()
+    }
+}
+
+fn spanned(version: &Spanned<String>) -> Result<Spanned<RustVersion>, Error> {
+    Ok(Spanned {
+        value: version.to_rust_version()?,
+        span: version.span,
+    })
+}
+
+/// A member's `rust-version`, with workspace inheritance resolved.
+#[derive(Debug, Clone)]
+pub struct MemberRustVersion {
+    /// The member's manifest.
+    pub member: Utf8PathBuf,
+    /// The manifest the `rust-version` is written in.
+    ///
+    /// For `rust-version.workspace = true` this is the workspace root.
+    pub manifest: Utf8PathBuf,
+    /// The version, with its span in `manifest`.
+    pub rust_version: Spanned<RustVersion>,
+}
+
+/// A member whose edition needs a newer toolchain than its `rust-version`,
+/// which Cargo rejects.
+#[derive(Debug, Clone)]
+pub struct EditionMismatch {
+    /// The member and its `rust-version`.
+    pub rust_version: MemberRustVersion,
+    /// The manifest the edition is written in.
+    pub edition_manifest: Utf8PathBuf,
+    /// The edition, with its span in `edition_manifest`.
+    pub edition: Spanned<Edition>,
+}
+
+impl fmt::Display for EditionMismatch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let rust_version = self.rust_version.rust_version.value;
+        f.write_str(&edition_mismatch(rust_version, self.edition.value))
+    }
+}
+
+impl CargoWorkspace {
+    /// The `rust-version` of every member that has one, in member order.
+    ///
+    /// Fails on an invalid version, or if a member inherits `rust-version`
+    /// and the root does not define it.
+    pub fn rust_versions(&self) -> Result<Vec<MemberRustVersion>, Error> {
+        let inherited = self.inherited_package();
+        let mut found = Vec::new();
+        for (member, manifest) in &self.members {
+            let Some(package) = &manifest.package else {
+                continue;
+            };
+            let (declared_in, version) = match &package.rust_version {
+                None => continue,
+                Some(StringOrWorkspace::String(version)) => (member, version),
+                Some(StringOrWorkspace::Workspace(marker)) => {
+                    let version = inherited.and_then(|p| p.rust_version.as_ref());
+                    (
+                        &self.root_manifest,
+                        inherit("rust-version", marker, version)?,
+                    )
+                }
+            };
+            found.push(MemberRustVersion {
+                member: member.clone(),
+                manifest: declared_in.clone(),
+                rust_version: spanned(version)?,
+            });
+        }
+        Ok(found)
+    }
+
+    /// The members whose `rust-version` is newer than `toolchain`, so that
+    /// building them with it fails.
+    pub fn members_requiring_newer(
+        &self,
+        toolchain: RustVersion,
+    ) -> Result<Vec<MemberRustVersion>, Error> {
+        let mut found = self.rust_versions()?;
+        found.retain(|member| member.rust_version.value > toolchain);
+        Ok(found)
+    }
+
+    /// The members whose edition was released after their `rust-version`.
+    ///
+    /// Members without an edition use 2015, which every version supports.
+    pub fn edition_mismatches(&self) -> Result<Vec<EditionMismatch>, Error> {
+        let inherited = self.inherited_package();
+        let mut found = Vec::new();
+        for rust_version in self.rust_versions()? {
+            let package = self.members[&rust_version.member].package.as_ref();
+            let (edition_manifest, edition) = match package.and_then(|p| p.edition.as_ref()) {
+                None => continue,
+                Some(EditionOrWorkspace::Edition(edition)) => (&rust_version.member, edition),
+                Some(EditionOrWorkspace::Workspace(marker)) => {
+                    let edition = inherited.and_then(|p| p.edition.as_ref());
+                    (&self.root_manifest, inherit("edition", marker, edition)?)
+                }
+            };
+            if rust_version.rust_version.value < edition.value.first_rust_version() {
+                found.push(EditionMismatch {
+                    edition_manifest: edition_manifest.clone(),
+                    edition: edition.clone(),
+                    rust_version,
+                });
+            }
+        }
+        Ok(found)
+    }
+
+    /// The root's `[workspace.package]`, which members inherit from.
+    fn inherited_package(&self) -> Option<&WorkspacePackage> {
+        self.root
+            .workspace
+            .as_ref()
+            .and_then(|w| w.package.as_ref())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testdir::TestDir;
+
+    #[test]
+    fn parse_rust_versions() {
+        let version = RustVersion::parse("1.85").unwrap();
+        assert_eq!(version.to_string(), "1.85");
+        assert_eq!(version, RustVersion::parse("1.85.0").unwrap());
+        assert!(version < RustVersion::parse("1.85.1").unwrap());
+        assert!(RustVersion::parse("1.9").unwrap() < RustVersion::parse("1.10").unwrap());
+        assert_eq!(version.to_version(), Version::new(1, 85, 0));
+
+        for invalid in [
+            "1",
+            "1.85.0.1",
+            "1.x",
+            "01.85",
+            "",
+            "^1.85",
+            "1.85.0-nightly",
+        ] {
+            assert!(RustVersion::parse(invalid).is_err(), "{invalid}");
+        }
+        let Err(error) = RustVersion::parse("1.86.0-beta.1") else {
+            panic!("expected an error");
+        };
+        assert_eq!(
+            error.to_string(),
+            "invalid rust-version `1.86.0-beta.1`: unexpected pre-release field, expected a version like \"1.32\""
+        );
+    }
+
+    #[test]
+    fn workspace_rust_versions() {
+        let dir = TestDir::new("msrv");
+        dir.write(
+            "Cargo.toml",
+            r#"
+[workspace]
+members = ["crates/*"]
+
+[workspace.package]
+edition = "2024"
+rust-version = "1.80"
+"#,
+        );
+        dir.write(
+            "crates/inherits/Cargo.toml",
+            "[package]\nname = \"inherits\"\nedition.workspace = true\nrust-version.workspace = true\n",
+        );
+        dir.write(
+            "crates/newer/Cargo.toml",
+            "[package]\nname = \"newer\"\nedition = \"2021\"\nrust-version = \"1.88\"\n",
+        );
+        dir.write(
+            "crates/unset/Cargo.toml",
+            "[package]\nname = \"unset\"\nedition = \"2024\"\n",
+        );
+
+        let workspace = CargoWorkspace::from_root(dir.path().join("Cargo.toml")).unwrap();
+        let versions: Vec<_> = workspace
+            .rust_versions()
+            .unwrap()
+            .into_iter()
+            .map(|m| {
+                (
+                    m.member.strip_prefix(dir.path()).unwrap().to_string(),
+                    m.rust_version.value.to_string(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            versions,
+            [
+                ("crates/inherits/Cargo.toml".to_owned(), "1.80".to_owned()),
+                ("crates/newer/Cargo.toml".to_owned(), "1.88".to_owned()),
+            ]
+        );
+
+        let toolchain = RustVersion::parse("1.85").unwrap();
+        let newer = workspace.members_requiring_newer(toolchain).unwrap();
+        let [newer] = &newer[..] else {
+            panic!("{newer:?}");
+        };
+        assert_eq!(newer.member, dir.path().join("crates/newer/Cargo.toml"));
+
+        let mismatches = workspace.edition_mismatches().unwrap();
+        let [mismatch] = &mismatches[..] else {
+            panic!("{mismatches:?}");
+        };
+        assert_eq!(
+            mismatch.rust_version.manifest,
+            dir.path().join("Cargo.toml")
+        );
+        assert_eq!(mismatch.edition_manifest, dir.path().join("Cargo.toml"));
+        let root = std::fs::read_to_string(dir.path().join("Cargo.toml")).unwrap();
+        let span = mismatch.rust_version.rust_version.span.unwrap();
+        assert_eq!(
+            &root[span.offset as usize..][..span.len as usize],
+            "\"1.80\""
+        );
+        assert_eq!(
+            mismatch.to_string(),
+            "rust-version 1.80 is older than first version (1.85.0) required by the specified edition (2024)"
+        );
+    }
+}
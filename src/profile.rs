@@ -0,0 +1,418 @@
+//! Effective profile settings: `[profile.*]` tables, their `inherits` chains,
+//! package overrides and build overrides, layered over Cargo's defaults.
+
+use crate::{
+    BuildOverride, CargoToml, CargoWorkspace, DebugLevel, DebugLevelName, Error, Lto, OptLevel,
+    PackageProfile, PanicStrategy, Profile, Spanned, StripLevel, StripLevelName,
+};
+
+/// What an effective profile is computed for.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ProfileUnit<'a> {
+    /// The package being compiled, which selects `[profile.*.package.<name>]`
+    /// overrides. `None` applies no package overrides.
+    pub package: Option<&'a str>,
+    /// Whether `package` is a workspace member. `[profile.*.package."*"]`
+    /// only applies to packages that are not.
+    pub workspace_member: bool,
+    /// Whether the unit is a build script, a proc macro, or one of their
+    /// dependencies, which `build-override` applies to.
+    pub for_build: bool,
+}
+
+/// The settings a profile resolves to, with nothing left unspecified.
+///
+/// Each value keeps the span of the manifest entry that decided it; values
+/// that come from Cargo's defaults have no span.
+#[derive(Debug, Clone)]
+pub struct EffectiveProfile {
+    /// The name of the profile.
+    pub name: String,
+    /// The built-in profile at the end of the `inherits` chain, `dev` or
+    /// `release`.
+    pub root: &'static str,
+    /// The optimization level.
+    pub opt_level: OptLevel,
+    /// The amount of debug information.
+    pub debug: DebugLevel,
+    /// Where debug information goes; `None` leaves it to the platform.
+    pub split_debuginfo: Option<Spanned<String>>,
+    /// Whether `cfg(debug_assertions)` is enabled.
+    pub debug_assertions: Spanned<bool>,
+    /// Whether integer overflow panics.
+    pub overflow_checks: Spanned<bool>,
+    /// The link-time optimization mode.
+    pub lto: Lto,
+    /// The panic strategy. Cargo always unwinds for the `test` and `bench`
+    /// profiles and for build units.
+    pub panic: Spanned<PanicStrategy>,
+    /// Whether incremental compilation is enabled.
+    pub incremental: Spanned<bool>,
+    /// The number of code generation units.
+    pub codegen_units: Spanned<u32>,
+    /// Whether rpath is enabled.
+    pub rpath: Spanned<bool>,
+    /// What gets stripped from the binary.
+    pub strip: StripLevel,
+}
+
+/// Every setting a profile table may specify.
+#[derive(Debug, Clone, Default)]
+struct Layer {
+    opt_level: Option<OptLevel>,
+    debug: Option<DebugLevel>,
+    split_debuginfo: Option<Spanned<String>>,
+    debug_assertions: Option<Spanned<bool>>,
+    overflow_checks: Option<Spanned<bool>>,
+    lto: Option<Lto>,
+    panic: Option<Spanned<PanicStrategy>>,
+    incremental: Option<Spanned<bool>>,
+    codegen_units: Option<Spanned<u32>>,
+    rpath: Option<Spanned<bool>>,
+    strip: Option<StripLevel>,
+}
+
+impl From<&Profile> for Layer {
+    fn from(profile: &Profile) -> Self {
+        Layer {
+            opt_level: profile.opt_level.clone(),
+            debug: profile.debug.clone(),
+            split_debuginfo: profile.split_debuginfo.clone(),
+            debug_assertions: profile.debug_assertions.clone(),
+            overflow_checks: profile.overflow_checks.clone(),
+            lto: profile.lto.clone(),
+            panic: profile.panic.clone(),
+            incremental: profile.incremental.clone(),
+            codegen_units: profile.codegen_units.clone(),
+            rpath: profile.rpath.clone(),
+            strip: profile.strip.clone(),
+        }
+    }
+}
+
+impl From<&PackageProfile> for Layer {
+    fn from(profile: &PackageProfile) -> Self {
+        Layer {
+            opt_level: profile.opt_level.clone(),
+            debug: profile.debug.clone(),
+            debug_assertions: profile.debug_assertions.clone(),
+            overflow_checks: profile.overflow_checks.clone(),
+            codegen_units: profile.codegen_units.clone(),
+            ..Layer::default()
+        }
+    }
+}
+
+impl From<&BuildOverride> for Layer {
+    fn from(profile: &BuildOverride) -> Self {
+        Layer {
+            opt_level: profile.opt_level.clone(),
+            debug: profile.debug.clone(),
+            debug_assertions: profile.debug_assertions.clone(),
+            overflow_checks: profile.overflow_checks.clone(),
+            codegen_units: profile.codegen_units.clone(),
+            incremental: profile.incremental.clone(),
+            ..Layer::default()
+        }
+    }
+}
+
+impl CargoToml {
+    /// Compute the settings of `profile` for `unit`.
+    ///
+    /// Follows `inherits` up to `dev` or `release` (`test` and `doc` inherit
+    /// from `dev`, `bench` from `release`), then picks each setting from the
+    /// first of these that has it:
+    ///
+    /// 1. `[profile.*.package.<name>]` for `unit.package`,
+    /// 2. `[profile.*.package."*"]`, unless the package is a workspace member,
+    /// 3. `[profile.*.build-override]` and Cargo's build defaults, for build
+    ///    units,
+    /// 4. `[profile.*]`,
+    /// 5. Cargo's defaults for `dev` or `release`.
+    ///
+    /// Within each step the profile itself comes before the ones it inherits
+    /// from. Cargo only reads profiles from the workspace root, so call this on
+    /// the root manifest. As in Cargo, `panic` is always `unwind` for the
+    /// `test` and `bench` profiles and for build units.
+    ///
+    /// Fails if the profile is not defined, a custom profile has no
+    /// `inherits`, or the chain loops.
+    pub fn effective_profile(
+        &self,
+        profile: &str,
+        unit: ProfileUnit<'_>,
+    ) -> Result<EffectiveProfile, Error> {
+        let (chain, root) = self.profile_chain(profile)?;
+
+        let mut layers: Vec<Layer> = Vec::new();
+        if let Some(package) = unit.package {
+            let named = chain.iter().filter_map(|p| {
+                p.package.iter().flatten().find_map(|(spec, overrides)| {
+                    let name = spec.split_once('@').map_or(spec.as_str(), |(name, _)| name);
+                    (name == package).then_some(overrides)
+                })
+            });
+            layers.extend(named.map(Layer::from));
+            if !unit.workspace_member {
+                let glob = chain.iter().filter_map(|p| p.package.as_ref()?.get("*"));
+                layers.extend(glob.map(Layer::from));
+            }
+        }
+        if unit.for_build {
+            let overrides = chain.iter().filter_map(|p| p.build_override.as_ref());
+            layers.extend(overrides.map(Layer::from));
+            layers.push(build_defaults(&chain));
+        }
+        layers.extend(chain.iter().copied().map(Layer::from));
+        layers.push(defaults(root));
+
+        macro_rules! pick {
+            ($field:ident) => {
+                layers.iter().find_map(|layer| layer.$field.clone())
+            };
+        }
+        let debug = pick!(debug).expect("defaults set `debug`");
+        let strip = pick!(strip).unwrap_or_else(|| {
+            // Cargo strips debug info when none is generated.
+            let level = if is_debug_off(&debug) {
+                StripLevelName::Debuginfo
+            } else {
+                StripLevelName::None
+            };
+            StripLevel::Named(default(level))
+        });
+        Ok(EffectiveProfile {
+            name: profile.to_owned(),
+            root,
+            opt_level: pick!(opt_level).expect("defaults set `opt-level`"),
+            debug,
+            split_debuginfo: pick!(split_debuginfo),
+            debug_assertions: pick!(debug_assertions).expect("defaults set `debug-assertions`"),
+            overflow_checks: pick!(overflow_checks).expect("defaults set `overflow-checks`"),
+            lto: pick!(lto).expect("defaults set `lto`"),
+            panic: if matches!(profile, "test" | "bench") || unit.for_build {
+                default(PanicStrategy::Unwind)
+            } else {
+                pick!(panic).expect("defaults set `panic`")
+            },
+            incremental: pick!(incremental).expect("defaults set `incremental`"),
+            codegen_units: pick!(codegen_units).expect("defaults set `codegen-units`"),
+            rpath: pick!(rpath).expect("defaults set `rpath`"),
+            strip,
+        })
+    }
+
+    /// The `[profile.*]` tables `profile` inherits from, starting with its
+    /// own, and the built-in profile the chain ends at.
+    fn profile_chain(&self, profile: &str) -> Result<(Vec<&Profile>, &'static str), Error> {
+        let mut chain = Vec::new();
+        let mut names = Vec::new();
+        let mut name = profile;
+        loop {
+            if names.contains(&name) {
+                return Err(Error::InvalidProfile {
+                    profile: profile.to_owned(),
+                    reason: format!("`inherits` loops: {} -> {name}", names.join(" -> ")),
+                    span: chain
+                        .last()
+                        .and_then(|p: &&Profile| p.inherits.as_ref()?.span),
+                });
+            }
+            names.push(name);
+
+            let table = self.profile.as_ref().and_then(|p| p.get(name));
+            chain.extend(table);
+            let parent = match (name, table.and_then(|t| t.inherits.as_ref())) {
+                ("dev", _) => return Ok((chain, "dev")),
+                ("release", _) => return Ok((chain, "release")),
+                (_, Some(inherits)) => &inherits.value,
+                ("test" | "doc", None) => "dev",
+                ("bench", None) => "release",
+                (_, None) => {
+                    let reason = match table {
+                        Some(_) => format!("profile `{name}` is missing an `inherits` directive"),
+                        None => format!("profile `{name}` is not defined"),
+                    };
+                    return Err(Error::InvalidProfile {
+                        profile: profile.to_owned(),
+                        reason,
+                        span: None,
+                    });
+                }
+            };
+            name = parent;
+        }
+    }
+}
+
+impl CargoWorkspace {
+    /// Compute the settings of `profile` for `package`, from the root
+    /// manifest.
+    ///
+    /// `package` counts as a workspace member if any member has that name.
+    /// See [`CargoToml::effective_profile`].
+    pub fn effective_profile(
+        &self,
+        profile: &str,
+        package: &str,
+        for_build: bool,
+    ) -> Result<EffectiveProfile, Error> {
+        let workspace_member = self.members.values().any(|member| {
+            member
+                .package
+                .as_ref()
+                .and_then(|p| p.name.as_ref())
+                .is_some_and(|name| name.value == package)
+        });
+        self.root.effective_profile(
+            profile,
+            ProfileUnit {
+                package: Some(package),
+                workspace_member,
+                for_build,
+            },
+        )
+    }
+}
+
+/// A value Cargo picks, which has no span.
+fn default<T>(value: T) -> Spanned<T> {
+    Spanned { value, span: None }
+}
+
+/// Cargo's settings for the built-in `dev` and `release` profiles.
+fn defaults(root: &str) -> Layer {
+    let dev = root == "dev";
+    Layer {
+        opt_level: Some(OptLevel::Number(default(if dev { 0 } else { 3 }))),
+        debug: Some(DebugLevel::Bool(default(dev))),
+        split_debuginfo: None,
+        debug_assertions: Some(default(dev)),
+        overflow_checks: Some(default(dev)),
+        lto: Some(Lto::Bool(default(false))),
+        panic: Some(default(PanicStrategy::Unwind)),
+        incremental: Some(default(dev)),
+        codegen_units: Some(default(if dev { 256 } else { 16 })),
+        rpath: Some(default(false)),
+        strip: None,
+    }
+}
+
+/// Cargo's settings for build units, below any `build-override`.
+///
+/// Build units get no debug info unless a profile in the chain asks for it.
+fn build_defaults(chain: &[&Profile]) -> Layer {
+    let debug = chain.iter().all(|p| p.debug.is_none());
+    Layer {
+        opt_level: Some(OptLevel::Number(default(0))),
+        debug: debug.then(|| DebugLevel::Bool(default(false))),
+        codegen_units: Some(default(256)),
+        ..Layer::default()
+    }
+}
+
+fn is_debug_off(debug: &DebugLevel) -> bool {
+    match debug {
+        DebugLevel::Named(name) => name.value == DebugLevelName::None,
+        DebugLevel::Bool(enabled) => !enabled.value,
+        DebugLevel::Number(level) => level.value == 0,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{LtoName, OptLevelName};
+
+    const MANIFEST: &str = r#"
+[profile.release-lto]
+inherits = "release"
+lto = "fat"
+codegen-units = 1
+
+[profile.release]
+debug = "line-tables-only"
+
+[profile.release.package."*"]
+opt-level = "s"
+
+[profile.release.package.foo]
+opt-level = 1
+
+[profile.release.build-override]
+overflow-checks = true
+
+[profile.dev]
+strip = true
+panic = "abort"
+"#;
+
+    #[test]
+    fn effective_profiles() {
+        let manifest = CargoToml::parse(MANIFEST).unwrap();
+        let span = |span: Option<crate::Span>| {
+            span.map(|s| &MANIFEST[s.offset as usize..][..s.len as usize])
+        };
+
+        let profile = manifest
+            .effective_profile("release-lto", ProfileUnit::default())
+            .unwrap();
+        assert_eq!(profile.root, "release");
+        assert!(matches!(profile.opt_level, OptLevel::Number(ref n) if n.value == 3));
+        assert!(matches!(profile.lto, Lto::Named(ref n) if n.value == LtoName::Fat));
+        assert_eq!(profile.codegen_units.value, 1);
+        assert!(!profile.debug_assertions.value);
+        let DebugLevel::Named(debug) = &profile.debug else {
+            panic!("{:?}", profile.debug);
+        };
+        assert_eq!(span(debug.span), Some("\"line-tables-only\""));
+        assert!(
+            matches!(profile.strip, StripLevel::Named(ref s) if s.value == StripLevelName::None)
+        );
+
+        let dependency = ProfileUnit {
+            package: Some("serde"),
+            ..ProfileUnit::default()
+        };
+        let profile = manifest
+            .effective_profile("release-lto", dependency)
+            .unwrap();
+        assert!(matches!(profile.opt_level, OptLevel::Named(ref n) if n.value == OptLevelName::S));
+
+        let foo = ProfileUnit {
+            package: Some("foo"),
+            workspace_member: true,
+            for_build: true,
+        };
+        let profile = manifest.effective_profile("release-lto", foo).unwrap();
+        assert!(matches!(profile.opt_level, OptLevel::Number(ref n) if n.value == 1));
+        assert_eq!(span(profile.overflow_checks.span), Some("true"));
+        assert_eq!(profile.codegen_units.value, 256);
+
+        let profile = manifest
+            .effective_profile("test", ProfileUnit::default())
+            .unwrap();
+        assert_eq!(profile.root, "dev");
+        assert!(profile.incremental.value);
+        assert!(matches!(profile.strip, StripLevel::Bool(ref b) if b.value));
+        assert_eq!(profile.panic.value, PanicStrategy::Unwind);
+        assert_eq!(profile.panic.span, None);
+
+        let profile = manifest
+            .effective_profile("dev", ProfileUnit::default())
+            .unwrap();
+        assert_eq!(profile.panic.value, PanicStrategy::Abort);
+        let build = ProfileUnit {
+            for_build: true,
+            ..ProfileUnit::default()
+        };
+        let profile = manifest.effective_profile("dev", build).unwrap();
+        assert_eq!(profile.panic.value, PanicStrategy::Unwind);
+
+        assert!(matches!(
+            manifest.effective_profile("missing", ProfileUnit::default()),
+            Err(Error::InvalidProfile { .. })
+        ));
+    }
+}
@@ -0,0 +1,991 @@
+//! Serializing manifests back to TOML.
+
+use std::collections::HashMap;
+use std::fmt::Write;
+
+use camino::Utf8Path;
+use facet_value::DestructuredRef;
+
+use crate::{
+    Badge, BenchTarget, BinTarget, BoolOrVec, BoolOrVecOrWorkspace, BuildOverride, CargoFeature,
+    CargoToml, DebugLevel, DebugLevelName, Dependency, DependencyDetail, Edition,
+    EditionOrWorkspace, Error, ExampleTarget, Hints, IoError, LibTarget, LintConfig, LintLevel,
+    LintLevelString, Lints, Lto, LtoName, OptLevel, OptLevelName, Package, PackageProfile,
+    PanicStrategy, Profile, Resolver, Spanned, StringOrBool, StringOrBoolOrWorkspace, StringOrVec,
+    StringOrWorkspace, StripLevel, StripLevelName, TargetSpec, TestTarget, VecOrWorkspace,
+    Workspace, WorkspaceDependency, WorkspacePackage, WorkspaceRef,
+};
+
+/// Dependency tables longer than this are written as `[dependencies.name]` sections.
+const MAX_INLINE_WIDTH: usize = 80;
+
+impl CargoToml {
+    /// Serialize to canonical Cargo-style TOML.
+    ///
+    /// Keys are kebab-case and sorted within maps, short dependency
+    /// specifications are written as inline tables, and workspace inheritance
+    /// uses the `key.workspace = true` shorthand. Comments and the original
+    /// layout are not preserved; see [`crate::ManifestEditor`] for that.
+    pub fn to_toml_string(&self) -> String {
+        render_document(&self.to_toml())
+    }
+
+    /// Write [`CargoToml::to_toml_string`] to `path`.
+    pub fn write_to_path(&self, path: impl AsRef<Utf8Path>) -> Result<(), Error> {
+        let path = path.as_ref();
+        std::fs::write(path, self.to_toml_string()).map_err(|source| Error::Io {
+            path: path.to_owned(),
+            source: IoError::from(source),
+        })
+    }
+}
+
+impl Dependency {
+    /// Serialize this dependency specification as an inline TOML value, such as
+    /// `"1.0"` or `{ version = "1.0", features = ["derive"] }`.
+    pub fn to_toml_string(&self) -> String {
+        render_inline(&self.to_toml())
+    }
+}
+
+impl Profile {
+    /// Serialize the body of this `[profile.*]` table, with sub-table headers
+    /// relative to the profile.
+    pub fn to_toml_string(&self) -> String {
+        render_document(&self.to_toml())
+    }
+}
+
+impl Lints {
+    /// Serialize the body of this `[lints]` table, with sub-table headers
+    /// relative to it.
+    pub fn to_toml_string(&self) -> String {
+        render_document(&self.to_toml())
+    }
+}
+
+/// A TOML value, with a hint for how tables are laid out.
+pub(crate) enum Toml {
+    String(String),
+    Integer(i64),
+    /// An integer outside `i64`, as its exact decimal digits.
+    BigInteger(String),
+    Float(f64),
+    Bool(bool),
+    Datetime(String),
+    Array(Vec<Toml>),
+    /// A table written as a `[section]` when possible.
+    Table(Vec<(String, Toml)>),
+    /// A table written as `{ key = value }`.
+    InlineTable(Vec<(String, Toml)>),
+    /// A table written as dotted keys, like `version.workspace = true`.
+    Dotted(Vec<(String, Toml)>),
+}
+
+/// Builds a table from optional fields, skipping the unset ones.
+#[derive(Default)]
+struct TableBuilder(Vec<(String, Toml)>);
+
+impl TableBuilder {
+    fn field<T: ToToml>(mut self, key: &str, value: &Option<T>) -> Self {
+        if let Some(value) = value {
+            self.0.push((key.to_owned(), value.to_toml()));
+        }
+        self
+    }
+
+    fn build(self) -> Toml {
+        Toml::Table(self.0)
+    }
+}
+
+pub(crate) trait ToToml {
+    fn to_toml(&self) -> Toml;
+}
+
+impl ToToml for String {
+    fn to_toml(&self) -> Toml {
+        Toml::String(self.clone())
+    }
+}
+
+impl ToToml for bool {
+    fn to_toml(&self) -> Toml {
+        Toml::Bool(*self)
+    }
+}
+
+impl ToToml for u8 {
+    fn to_toml(&self) -> Toml {
+        Toml::Integer(i64::from(*self))
+    }
+}
+
+impl ToToml for u32 {
+    fn to_toml(&self) -> Toml {
+        Toml::Integer(i64::from(*self))
+    }
+}
+
+impl ToToml for i32 {
+    fn to_toml(&self) -> Toml {
+        Toml::Integer(i64::from(*self))
+    }
+}
+
+impl<T: ToToml> ToToml for &T {
+    fn to_toml(&self) -> Toml {
+        (*self).to_toml()
+    }
+}
+
+impl<T: ToToml> ToToml for Vec<T> {
+    fn to_toml(&self) -> Toml {
+        Toml::Array(self.iter().map(ToToml::to_toml).collect())
+    }
+}
+
+impl<T: ToToml> ToToml for HashMap<String, T> {
+    fn to_toml(&self) -> Toml {
+        let mut entries: Vec<_> = self.iter().map(|(k, v)| (k.clone(), v.to_toml())).collect();
+        entries.sort_by(|a, b| a.0.cmp(&b.0));
+        Toml::Table(entries)
+    }
+}
+
+impl<T: ToToml> ToToml for Spanned<T> {
+    fn to_toml(&self) -> Toml {
+        self.value.to_toml()
+    }
+}
+
+impl ToToml for facet_value::Value {
+    fn to_toml(&self) -> Toml {
+        match self.destructure_ref() {
+            DestructuredRef::Bool(b) => Toml::Bool(b),
+            DestructuredRef::Number(n) => match (n.to_i64(), n.to_f64()) {
+                (Some(i), _) if n.is_integer() => Toml::Integer(i),
+                (_, Some(f)) if n.is_float() => Toml::Float(f),
+                // Beyond TOML's 64-bit integers: keep the exact digits rather
+                // than rounding to a float.
+                _ => Toml::BigInteger(format!("{n:?}")),
+            },
+            DestructuredRef::String(s) => Toml::String(s.as_str().to_owned()),
+            // TOML has no null: a bare null is written as an empty table, and
+            // null elements and entries are left out like unset fields.
+            DestructuredRef::Null => Toml::Table(Vec::new()),
+            DestructuredRef::Array(a) => Toml::Array(
+                a.into_iter()
+                    .filter(|v| !v.is_null())
+                    .map(ToToml::to_toml)
+                    .collect(),
+            ),
+            DestructuredRef::Object(o) => Toml::Table(
+                o.iter()
+                    .filter(|(_, v)| !v.is_null())
+                    .map(|(k, v)| (k.as_str().to_owned(), v.to_toml()))
+                    .collect(),
+            ),
+            DestructuredRef::DateTime(dt) => {
+                let mut out = String::new();
+                if dt.has_date() {
+                    write!(out, "{:04}-{:02}-{:02}", dt.year(), dt.month(), dt.day()).unwrap();
+                }
+                if dt.has_time() {
+                    if dt.has_date() {
+                        out.push('T');
+                    }
+                    write!(
+                        out,
+                        "{:02}:{:02}:{:02}",
+                        dt.hour(),
+                        dt.minute(),
+                        dt.second()
+                    )
+                    .unwrap();
+                    if dt.nanos() != 0 {
+                        write!(out, ".{:09}", dt.nanos()).unwrap();
+                    }
+                }
+                match dt.offset_minutes() {
+                    Some(0) => out.push('Z'),
+                    Some(m) => {
+                        let sign = if m < 0 { '-' } else { '+' };
+                        write!(out, "{sign}{:02}:{:02}", m.abs() / 60, m.abs() % 60).unwrap();
+                    }
+                    None => {}
+                }
+                Toml::Datetime(out)
+            }
+            // Kinds parsing TOML never produces, like bytes or UUIDs, have no
+            // TOML form; their debug text is written as a string.
+            _ => Toml::String(format!("{self:?}")),
+        }
+    }
+}
+
+impl ToToml for Edition {
+    fn to_toml(&self) -> Toml {
+        Toml::String(self.to_string())
+    }
+}
+
+impl ToToml for CargoFeature {
+    fn to_toml(&self) -> Toml {
+        Toml::String(self.name().to_owned())
+    }
+}
+
+impl ToToml for Resolver {
+    fn to_toml(&self) -> Toml {
+        let resolver = match self {
+            Resolver::V1 => "1",
+            Resolver::V2 => "2",
+            Resolver::V3 => "3",
+        };
+        Toml::String(resolver.to_owned())
+    }
+}
+
+impl ToToml for PanicStrategy {
+    fn to_toml(&self) -> Toml {
+        let strategy = match self {
+            PanicStrategy::Unwind => "unwind",
+            PanicStrategy::Abort => "abort",
+            PanicStrategy::ImmediateAbort => "immediate-abort",
+        };
+        Toml::String(strategy.to_owned())
+    }
+}
+
+impl ToToml for OptLevelName {
+    fn to_toml(&self) -> Toml {
+        let level = match self {
+            OptLevelName::S => "s",
+            OptLevelName::Z => "z",
+        };
+        Toml::String(level.to_owned())
+    }
+}
+
+impl ToToml for DebugLevelName {
+    fn to_toml(&self) -> Toml {
+        let level = match self {
+            DebugLevelName::None => "none",
+            DebugLevelName::LineDirectivesOnly => "line-directives-only",
+            DebugLevelName::LineTablesOnly => "line-tables-only",
+            DebugLevelName::Limited => "limited",
+            DebugLevelName::Full => "full",
+        };
+        Toml::String(level.to_owned())
+    }
+}
+
+impl ToToml for LtoName {
+    fn to_toml(&self) -> Toml {
+        let lto = match self {
+            LtoName::Thin => "thin",
+            LtoName::Fat => "fat",
+            LtoName::Off => "off",
+        };
+        Toml::String(lto.to_owned())
+    }
+}
+
+impl ToToml for StripLevelName {
+    fn to_toml(&self) -> Toml {
+        let level = match self {
+            StripLevelName::None => "none",
+            StripLevelName::Debuginfo => "debuginfo",
+            StripLevelName::Symbols => "symbols",
+        };
+        Toml::String(level.to_owned())
+    }
+}
+
+impl ToToml for LintLevelString {
+    fn to_toml(&self) -> Toml {
+        let level = match self {
+            LintLevelString::Forbid => "forbid",
+            LintLevelString::Deny => "deny",
+            LintLevelString::Warn => "warn",
+            LintLevelString::Allow => "allow",
+        };
+        Toml::String(level.to_owned())
+    }
+}
+
+impl ToToml for WorkspaceRef {
+    fn to_toml(&self) -> Toml {
+        Toml::Dotted(vec![("workspace".to_owned(), self.workspace.to_toml())])
+    }
+}
+
+impl ToToml for StringOrWorkspace {
+    fn to_toml(&self) -> Toml {
+        match self {
+            StringOrWorkspace::Workspace(r) => r.to_toml(),
+            StringOrWorkspace::String(s) => s.to_toml(),
+        }
+    }
+}
+
+impl ToToml for VecOrWorkspace {
+    fn to_toml(&self) -> Toml {
+        match self {
+            VecOrWorkspace::Workspace(r) => r.to_toml(),
+            VecOrWorkspace::Values(v) => v.to_toml(),
+        }
+    }
+}
+
+impl ToToml for EditionOrWorkspace {
+    fn to_toml(&self) -> Toml {
+        match self {
+            EditionOrWorkspace::Edition(e) => e.to_toml(),
+            EditionOrWorkspace::Workspace(r) => r.to_toml(),
+        }
+    }
+}
+
+impl ToToml for StringOrBoolOrWorkspace {
+    fn to_toml(&self) -> Toml {
+        match self {
+            StringOrBoolOrWorkspace::Workspace(r) => r.to_toml(),
+            StringOrBoolOrWorkspace::Bool(b) => b.to_toml(),
+            StringOrBoolOrWorkspace::String(s) => s.to_toml(),
+        }
+    }
+}
+
+impl ToToml for StringOrBool {
+    fn to_toml(&self) -> Toml {
+        match self {
+            StringOrBool::String(s) => s.to_toml(),
+            StringOrBool::Bool(b) => b.to_toml(),
+        }
+    }
+}
+
+impl ToToml for StringOrVec {
+    fn to_toml(&self) -> Toml {
+        match self {
+            StringOrVec::String(s) => s.to_toml(),
+            StringOrVec::Vec(v) => v.to_toml(),
+        }
+    }
+}
+
+impl ToToml for BoolOrVec {
+    fn to_toml(&self) -> Toml {
+        match self {
+            BoolOrVec::Bool(b) => b.to_toml(),
+            BoolOrVec::Vec(v) => v.to_toml(),
+        }
+    }
+}
+
+impl ToToml for BoolOrVecOrWorkspace {
+    fn to_toml(&self) -> Toml {
+        match self {
+            BoolOrVecOrWorkspace::Workspace(r) => r.to_toml(),
+            BoolOrVecOrWorkspace::Bool(b) => b.to_toml(),
+            BoolOrVecOrWorkspace::Vec(v) => v.to_toml(),
+        }
+    }
+}
+
+impl ToToml for OptLevel {
+    fn to_toml(&self) -> Toml {
+        match self {
+            OptLevel::Number(n) => n.to_toml(),
+            OptLevel::Named(n) => n.to_toml(),
+        }
+    }
+}
+
+impl ToToml for DebugLevel {
+    fn to_toml(&self) -> Toml {
+        match self {
+            DebugLevel::Bool(b) => b.to_toml(),
+            DebugLevel::Number(n) => n.to_toml(),
+            DebugLevel::Named(n) => n.to_toml(),
+        }
+    }
+}
+
+impl ToToml for Lto {
+    fn to_toml(&self) -> Toml {
+        match self {
+            Lto::Bool(b) => b.to_toml(),
+            Lto::Named(n) => n.to_toml(),
+        }
+    }
+}
+
+impl ToToml for StripLevel {
+    fn to_toml(&self) -> Toml {
+        match self {
+            StripLevel::Bool(b) => b.to_toml(),
+            StripLevel::Named(n) => n.to_toml(),
+        }
+    }
+}
+
+impl ToToml for CargoToml {
+    fn to_toml(&self) -> Toml {
+        TableBuilder::default()
+            .field("cargo-features", &self.cargo_features)
+            .field("package", &self.package)
+            .field("lib", &self.lib)
+            .field("bin", &self.bin)
+            .field("example", &self.example)
+            .field("test", &self.test)
+            .field("bench", &self.bench)
+            .field("features", &self.features)
+            .field("dependencies", &self.dependencies)
+            .field("dev-dependencies", &self.dev_dependencies)
+            .field("build-dependencies", &self.build_dependencies)
+            .field("target", &self.target)
+            .field("badges", &self.badges)
+            .field("lints", &self.lints)
+            .field("hints", &self.hints)
+            .field("workspace", &self.workspace)
+            .field("profile", &self.profile)
+            .field("patch", &self.patch)
+            .field("replace", &self.replace)
+            .build()
+    }
+}
+
+impl ToToml for Package {
+    fn to_toml(&self) -> Toml {
+        TableBuilder::default()
+            .field("name", &self.name)
+            .field("version", &self.version)
+            .field("authors", &self.authors)
+            .field("edition", &self.edition)
+            .field("rust-version", &self.rust_version)
+            .field("description", &self.description)
+            .field("documentation", &self.documentation)
+            .field("readme", &self.readme)
+            .field("homepage", &self.homepage)
+            .field("repository", &self.repository)
+            .field("license", &self.license)
+            .field("license-file", &self.license_file)
+            .field("keywords", &self.keywords)
+            .field("categories", &self.categories)
+            .field("workspace", &self.workspace)
+            .field("build", &self.build)
+            .field("links", &self.links)
+            .field("exclude", &self.exclude)
+            .field("include", &self.include)
+            .field("publish", &self.publish)
+            .field("default-run", &self.default_run)
+            .field("autolib", &self.autolib)
+            .field("autobins", &self.autobins)
+            .field("autoexamples", &self.autoexamples)
+            .field("autotests", &self.autotests)
+            .field("autobenches", &self.autobenches)
+            .field("resolver", &self.resolver)
+            .field("metadata", &self.metadata)
+            .build()
+    }
+}
+
+impl ToToml for Workspace {
+    fn to_toml(&self) -> Toml {
+        TableBuilder::default()
+            .field("members", &self.members)
+            .field("exclude", &self.exclude)
+            .field("default-members", &self.default_members)
+            .field("resolver", &self.resolver)
+            .field("package", &self.package)
+            .field("dependencies", &self.dependencies)
+            .field("lints", &self.lints)
+            .field("metadata", &self.metadata)
+            .build()
+    }
+}
+
+impl ToToml for WorkspacePackage {
+    fn to_toml(&self) -> Toml {
+        TableBuilder::default()
+            .field("version", &self.version)
+            .field("authors", &self.authors)
+            .field("edition", &self.edition)
+            .field("rust-version", &self.rust_version)
+            .field("description", &self.description)
+            .field("documentation", &self.documentation)
+            .field("readme", &self.readme)
+            .field("homepage", &self.homepage)
+            .field("repository", &self.repository)
+            .field("license", &self.license)
+            .field("license-file", &self.license_file)
+            .field("keywords", &self.keywords)
+            .field("categories", &self.categories)
+            .field("exclude", &self.exclude)
+            .field("include", &self.include)
+            .field("publish", &self.publish)
+            .build()
+    }
+}
+
+impl ToToml for Dependency {
+    fn to_toml(&self) -> Toml {
+        match self {
+            Dependency::Version(version) => version.to_toml(),
+            Dependency::Workspace(workspace) => workspace.to_toml(),
+            Dependency::Detailed(detail) => detail.to_toml(),
+        }
+    }
+}
+
+impl ToToml for WorkspaceDependency {
+    fn to_toml(&self) -> Toml {
+        let entries = TableBuilder::default()
+            .field("workspace", &Some(&self.workspace))
+            .field("features", &self.features)
+            .field("optional", &self.optional)
+            .field("default-features", &self.default_features)
+            .0;
+        if entries.len() == 1 {
+            Toml::Dotted(entries)
+        } else {
+            Toml::InlineTable(entries)
+        }
+    }
+}
+
+impl ToToml for DependencyDetail {
+    fn to_toml(&self) -> Toml {
+        let entries = TableBuilder::default()
+            .field("version", &self.version)
+            .field("path", &self.path)
+            .field("base", &self.base)
+            .field("git", &self.git)
+            .field("branch", &self.branch)
+            .field("tag", &self.tag)
+            .field("rev", &self.rev)
+            .field("registry", &self.registry)
+            .field("registry-index", &self.registry_index)
+            .field("package", &self.package)
+            .field("features", &self.features)
+            .field("default-features", &self.default_features)
+            .field("optional", &self.optional)
+            .field("public", &self.public)
+            .field("artifact", &self.artifact)
+            .field("lib", &self.lib)
+            .field("target", &self.target)
+            .field("metadata", &self.metadata)
+            .0;
+
+        let inline = Toml::InlineTable(entries);
+        match inline {
+            Toml::InlineTable(entries)
+                if self.metadata.is_some() || render_inline_len(&entries) > MAX_INLINE_WIDTH =>
+            {
+                Toml::Table(entries)
+            }
+            inline => inline,
+        }
+    }
+}
+
+impl ToToml for TargetSpec {
+    fn to_toml(&self) -> Toml {
+        TableBuilder::default()
+            .field("dependencies", &self.dependencies)
+            .field("dev-dependencies", &self.dev_dependencies)
+            .field("build-dependencies", &self.build_dependencies)
+            .build()
+    }
+}
+
+impl ToToml for LibTarget {
+    fn to_toml(&self) -> Toml {
+        TableBuilder::default()
+            .field("name", &self.name)
+            .field("path", &self.path)
+            .field("test", &self.test)
+            .field("doctest", &self.doctest)
+            .field("bench", &self.bench)
+            .field("doc", &self.doc)
+            .field("plugin", &self.plugin)
+            .field("proc-macro", &self.proc_macro)
+            .field("harness", &self.harness)
+            .field("edition", &self.edition)
+            .field("crate-type", &self.crate_type)
+            .field("required-features", &self.required_features)
+            .field("doc-scrape-examples", &self.doc_scrape_examples)
+            .build()
+    }
+}
+
+macro_rules! target_to_toml {
+    ($($ty:ty),*) => {$(
+        impl ToToml for $ty {
+            fn to_toml(&self) -> Toml {
+                TableBuilder::default()
+                    .field("name", &self.name)
+                    .field("path", &self.path)
+                    .field("test", &self.test)
+                    .field("doctest", &self.doctest)
+                    .field("bench", &self.bench)
+                    .field("doc", &self.doc)
+                    .field("plugin", &self.plugin)
+                    .field("harness", &self.harness)
+                    .field("edition", &self.edition)
+                    .field("required-features", &self.required_features)
+                    .build()
+            }
+        }
+    )*};
+}
+
+target_to_toml!(BinTarget, TestTarget, BenchTarget);
+
+impl ToToml for ExampleTarget {
+    fn to_toml(&self) -> Toml {
+        TableBuilder::default()
+            .field("name", &self.name)
+            .field("path", &self.path)
+            .field("test", &self.test)
+            .field("doctest", &self.doctest)
+            .field("bench", &self.bench)
+            .field("doc", &self.doc)
+            .field("plugin", &self.plugin)
+            .field("harness", &self.harness)
+            .field("edition", &self.edition)
+            .field("crate-type", &self.crate_type)
+            .field("required-features", &self.required_features)
+            .build()
+    }
+}
+
+impl ToToml for Profile {
+    fn to_toml(&self) -> Toml {
+        TableBuilder::default()
+            .field("inherits", &self.inherits)
+            .field("opt-level", &self.opt_level)
+            .field("debug", &self.debug)
+            .field("split-debuginfo", &self.split_debuginfo)
+            .field("strip", &self.strip)
+            .field("debug-assertions", &self.debug_assertions)
+            .field("overflow-checks", &self.overflow_checks)
+            .field("lto", &self.lto)
+            .field("panic", &self.panic)
+            .field("incremental", &self.incremental)
+            .field("codegen-units", &self.codegen_units)
+            .field("rpath", &self.rpath)
+            .field("package", &self.package)
+            .field("build-override", &self.build_override)
+            .build()
+    }
+}
+
+impl ToToml for PackageProfile {
+    fn to_toml(&self) -> Toml {
+        TableBuilder::default()
+            .field("opt-level", &self.opt_level)
+            .field("debug", &self.debug)
+            .field("debug-assertions", &self.debug_assertions)
+            .field("overflow-checks", &self.overflow_checks)
+            .field("codegen-units", &self.codegen_units)
+            .build()
+    }
+}
+
+impl ToToml for BuildOverride {
+    fn to_toml(&self) -> Toml {
+        TableBuilder::default()
+            .field("opt-level", &self.opt_level)
+            .field("debug", &self.debug)
+            .field("debug-assertions", &self.debug_assertions)
+            .field("overflow-checks", &self.overflow_checks)
+            .field("codegen-units", &self.codegen_units)
+            .field("incremental", &self.incremental)
+            .build()
+    }
+}
+
+impl ToToml for Lints {
+    fn to_toml(&self) -> Toml {
+        TableBuilder::default()
+            .field("workspace", &self.workspace)
+            .field("rust", &self.rust)
+            .field("clippy", &self.clippy)
+            .field("rustdoc", &self.rustdoc)
+            .build()
+    }
+}
+
+impl ToToml for LintLevel {
+    fn to_toml(&self) -> Toml {
+        match self {
+            LintLevel::Level(level) => level.to_toml(),
+            LintLevel::Config(config) => config.to_toml(),
+        }
+    }
+}
+
+impl ToToml for LintConfig {
+    fn to_toml(&self) -> Toml {
+        let entries = TableBuilder::default()
+            .field("level", &Some(&self.level))
+            .field("priority", &self.priority)
+            .field("check-cfg", &self.check_cfg)
+            .0;
+        Toml::InlineTable(entries)
+    }
+}
+
+impl ToToml for Hints {
+    fn to_toml(&self) -> Toml {
+        TableBuilder::default()
+            .field("mostly-unused", &self.mostly_unused)
+            .build()
+    }
+}
+
+impl ToToml for Badge {
+    fn to_toml(&self) -> Toml {
+        self.attributes.to_toml()
+    }
+}
+
+/// Render a document: the top-level table, with no header of its own.
+fn render_document(value: &Toml) -> String {
+    let mut sections = Vec::new();
+    match value {
+        Toml::Table(entries) => render_table(&mut sections, &[], entries, false),
+        other => sections.push(render_inline(other)),
+    }
+    sections.retain(|s| !s.is_empty());
+    sections.join("\n")
+}
+
+/// Render a table as a `[section]` (plus its sub-sections), appending each
+/// section's text to `sections`.
+fn render_table(
+    sections: &mut Vec<String>,
+    path: &[String],
+    entries: &[(String, Toml)],
+    array_element: bool,
+) {
+    let mut body = String::new();
+    let mut children = Vec::new();
+    for (key, value) in entries {
+        match value {
+            Toml::Table(_) => children.push((key, value)),
+            Toml::Array(items) if !items.is_empty() && items.iter().all(is_section) => {
+                children.push((key, value));
+            }
+            Toml::Dotted(fields) => {
+                for (field, value) in fields {
+                    let line = format!(
+                        "{}.{} = {}",
+                        toml_key(key),
+                        toml_key(field),
+                        render_inline(value)
+                    );
+                    body.push_str(&line);
+                    body.push('\n');
+                }
+            }
+            value => {
+                writeln!(body, "{} = {}", toml_key(key), render_inline(value)).unwrap();
+            }
+        }
+    }
+
+    let header = path
+        .iter()
+        .map(|p| toml_key(p))
+        .collect::<Vec<_>>()
+        .join(".");
+    if array_element {
+        sections.push(format!("[[{header}]]\n{body}"));
+    } else if !path.is_empty() && (!body.is_empty() || children.is_empty()) {
+        sections.push(format!("[{header}]\n{body}"));
+    } else {
+        sections.push(body);
+    }
+
+    for (key, value) in children {
+        let mut child = path.to_vec();
+        child.push(key.clone());
+        match value {
+            Toml::Table(entries) => render_table(sections, &child, entries, false),
+            Toml::Array(items) => {
+                for item in items {
+                    if let Toml::Table(entries) = item {
+                        render_table(sections, &child, entries, true);
+                    }
+                }
+            }
+            _ => unreachable!(),
+        }
+    }
+}
+
+fn render_inline_len(entries: &[(String, Toml)]) -> usize {
+    entries
+        .iter()
+        .map(|(k, v)| toml_key(k).len() + render_inline(v).len() + 5)
+        .sum::<usize>()
+        + 2
+}
+
+fn is_section(value: &Toml) -> bool {
+    matches!(value, Toml::Table(_))
+}
+
+/// Render a value on a single line, as it would appear after `key = `.
+pub(crate) fn inline_toml(value: &impl ToToml) -> String {
+    render_inline(&value.to_toml())
+}
+
+/// Render a value on a single line.
+fn render_inline(value: &Toml) -> String {
+    match value {
+        Toml::String(s) => toml_string(s),
+        Toml::Integer(i) => i.to_string(),
+        Toml::BigInteger(digits) => digits.clone(),
+        Toml::Float(f) if f.is_nan() => "nan".to_owned(),
+        Toml::Float(f) if f.is_infinite() => if *f > 0.0 { "inf" } else { "-inf" }.to_owned(),
+        Toml::Float(f) => format!("{f:?}"),
+        Toml::Bool(b) => b.to_string(),
+        Toml::Datetime(dt) => dt.clone(),
+        Toml::Array(items) => {
+            let items: Vec<_> = items.iter().map(render_inline).collect();
+            format!("[{}]", items.join(", "))
+        }
+        Toml::Table(entries) | Toml::InlineTable(entries) | Toml::Dotted(entries) => {
+            if entries.is_empty() {
+                return "{}".to_owned();
+            }
+            let entries: Vec<_> = entries
+                .iter()
+                .map(|(k, v)| format!("{} = {}", toml_key(k), render_inline(v)))
+                .collect();
+            format!("{{ {} }}", entries.join(", "))
+        }
+    }
+}
+
+/// Format a TOML key, quoting it unless it is a valid bare key.
+///
+/// Keys containing double quotes, such as `cfg(target_os = "linux")`, are
+/// written as literal strings when possible.
+pub(crate) fn toml_key(key: &str) -> String {
+    let bare = !key.is_empty()
+        && key
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+    if bare {
+        key.to_owned()
+    } else if key.contains('"') && !key.contains('\'') && !key.contains(char::is_control) {
+        format!("'{key}'")
+    } else {
+        toml_string(key)
+    }
+}
+
+/// Format a TOML basic string.
+pub(crate) fn toml_string(value: &str) -> String {
+    let mut out = String::with_capacity(value.len() + 2);
+    out.push('"');
+    for c in value.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\t' => out.push_str("\\t"),
+            '\r' => out.push_str("\\r"),
+            c if c.is_control() => write!(out, "\\u{:04X}", c as u32).unwrap(),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn serialize_manifest() {
+        let manifest = CargoToml::parse(
+            r#"
+[dependencies]
+serde = { version = "1.0", features = ["derive"] }
+log = { version = "0.4" }
+anyhow = { workspace = true }
+
+[dependencies.big]
+version = "1"
+git = "https://example.com/a/very/long/repository/url/that/does/not/fit.git"
+branch = "main"
+
+[package]
+name = "demo"
+version.workspace = true
+
+[target.'cfg(unix)'.dependencies]
+libc = "0.2"
+
+[profile.release.package."*"]
+opt-level = 2
+"#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            manifest.to_toml_string(),
+            r#"[package]
+name = "demo"
+version.workspace = true
+
+[dependencies]
+anyhow.workspace = true
+log = { version = "0.4" }
+serde = { version = "1.0", features = ["derive"] }
+
+[dependencies.big]
+version = "1"
+git = "https://example.com/a/very/long/repository/url/that/does/not/fit.git"
+branch = "main"
+
+[target."cfg(unix)".dependencies]
+libc = "0.2"
+
+[profile.release.package."*"]
+opt-level = 2
+"#
+        );
+
+        let serde = &manifest.dependencies.as_ref().unwrap()["serde"];
+        assert_eq!(
+            serde.to_toml_string(),
+            r#"{ version = "1.0", features = ["derive"] }"#
+        );
+    }
+
+    #[test]
+    fn serialize_value_kinds() {
+        use facet_value::{VNumber, Value, value};
+
+        let value = value!({
+            "unset": null,
+            "list": [1, null, 2.5],
+            "big": (Value::from(VNumber::from_u64(u64::MAX))),
+        });
+        assert_eq!(
+            render_inline(&value.to_toml()),
+            r#"{ list = [1, 2.5], big = 18446744073709551615 }"#
+        );
+        assert_eq!(render_inline(&Value::NULL.to_toml()), "{}");
+    }
+}
@@ -0,0 +1,428 @@
+//! Where dependencies come from, as declared in manifests and recorded in
+//! lockfiles.
+
+use crate::{CRATES_IO_SOURCE, CargoToml, DeclaredDependency, Dependency, Error, LockPackage};
+
+/// The sparse index URL of crates.io, as lockfiles record it.
+const CRATES_IO_SPARSE: &str = "sparse+https://index.crates.io/";
+
+/// Where a dependency comes from.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum DependencySource {
+    /// A registry. Both fields are `None` for crates.io.
+    Registry {
+        /// The name of a registry configured in `.cargo/config.toml`.
+        name: Option<String>,
+        /// The index URL, prefixed with `sparse+` for sparse registries.
+        index: Option<String>,
+    },
+    /// A git repository.
+    Git {
+        /// The repository URL.
+        url: String,
+        /// The branch, tag or revision asked for.
+        reference: GitReference,
+        /// The commit it resolved to; only lockfiles record this.
+        precise: Option<String>,
+    },
+    /// A local directory: the `path` of a manifest dependency, or the URL of a
+    /// `path+` lockfile source.
+    Path(String),
+    /// `{ workspace = true }`; see [`Dependency::resolve`].
+    Workspace,
+}
+
+/// Which revision of a git repository to use.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum GitReference {
+    /// The head of the repository's default branch.
+    DefaultBranch,
+    /// The head of a branch.
+    Branch(String),
+    /// A tag.
+    Tag(String),
+    /// A commit hash or other revision.
+    Rev(String),
+}
+
+impl GitReference {
+    /// Whether the reference can move to another commit without the
+    /// manifest changing: the default branch or a named branch.
+    pub fn is_moving(&self) -> bool {
+        matches!(self, GitReference::DefaultBranch | GitReference::Branch(_))
+    }
+}
+
+impl DependencySource {
+    /// crates.io, the default registry.
+    pub fn crates_io() -> Self {
+        DependencySource::Registry {
+            name: None,
+            index: None,
+        }
+    }
+
+    /// Whether this is crates.io.
+    pub fn is_crates_io(&self) -> bool {
+        *self == Self::crates_io()
+    }
+
+    /// Whether this is a git source on a branch, which can move.
+    pub fn is_moving(&self) -> bool {
+        matches!(self, DependencySource::Git { reference, .. } if reference.is_moving())
+    }
+
+    /// Parse a lockfile `source` string, like `registry+https://...`,
+    /// `sparse+https://...`, `git+https://...?branch=main#<commit>` or
+    /// `path+file:///...`.
+    ///
+    /// Git references are percent-decoded, as Cargo encodes them, so
+    /// `?branch=feature%2Fx` is the branch `feature/x`.
+    ///
+    /// Both crates.io index URLs parse to [`DependencySource::crates_io`].
+    pub fn from_lock(source: &str) -> Result<Self, Error> {
+        let invalid = |reason: &str| Error::InvalidSource {
+            value: source.to_owned(),
+            reason: reason.to_owned(),
+        };
+        if source == CRATES_IO_SOURCE || source == CRATES_IO_SPARSE {
+            return Ok(Self::crates_io());
+        }
+        let Some((kind, url)) = source.split_once('+') else {
+            return Err(invalid("expected `<kind>+<url>`"));
+        };
+        if url.is_empty() {
+            return Err(invalid("the URL is empty"));
+        }
+        match kind {
+            "registry" => Ok(DependencySource::Registry {
+                name: None,
+                index: Some(url.to_owned()),
+            }),
+            "sparse" => Ok(DependencySource::Registry {
+                name: None,
+                index: Some(source.to_owned()),
+            }),
+            "path" => Ok(DependencySource::Path(url.to_owned())),
+            "git" => {
+                let (url, precise) = match url.split_once('#') {
+                    Some((url, precise)) => (url, Some(precise.to_owned())),
+                    None => (url, None),
+                };
+                let (url, reference) = match url.split_once('?') {
+                    None => (url, GitReference::DefaultBranch),
+                    Some((url, query)) => {
+                        let reference = match query.split_once('=') {
+                            Some(("branch", branch)) => GitReference::Branch(decode(branch)),
+                            Some(("tag", tag)) => GitReference::Tag(decode(tag)),
+                            Some(("rev", rev)) => GitReference::Rev(decode(rev)),
+                            _ => {
+                                return Err(invalid("expected `branch`, `tag` or `rev` after `?`"));
+                            }
+                        };
+                        (url, reference)
+                    }
+                };
+                Ok(DependencySource::Git {
+                    url: url.to_owned(),
+                    reference,
+                    precise,
+                })
+            }
+            _ => Err(invalid(&format!(
+                "unknown source kind `{kind}`; expected `registry`, `sparse`, `git` or `path`"
+            ))),
+        }
+    }
+
+    /// The lockfile `source` string, the inverse of
+    /// [`DependencySource::from_lock`].
+    ///
+    /// `None` for a registry known only by name and for
+    /// [`DependencySource::Workspace`], which lockfiles cannot express.
+    pub fn to_lock(&self) -> Option<String> {
+        match self {
+            DependencySource::Registry {
+                name: None,
+                index: None,
+            } => Some(CRATES_IO_SOURCE.to_owned()),
+            DependencySource::Registry {
+                index: Some(index), ..
+            } if index.starts_with("sparse+") => Some(index.clone()),
+            DependencySource::Registry {
+                index: Some(index), ..
+            } => Some(format!("registry+{index}")),
+            DependencySource::Registry { name: Some(_), .. } => None,
+            DependencySource::Git {
+                url,
+                reference,
+                precise,
+            } => {
+                let mut source = format!("git+{url}");
+                match reference {
+                    GitReference::DefaultBranch => {}
+                    GitReference::Branch(branch) => {
+                        source.push_str(&format!("?branch={}", encode(branch)));
+                    }
+                    GitReference::Tag(tag) => source.push_str(&format!("?tag={}", encode(tag))),
+                    GitReference::Rev(rev) => source.push_str(&format!("?rev={}", encode(rev))),
+                }
+                if let Some(precise) = precise {
+                    source.push_str(&format!("#{precise}"));
+                }
+                Some(source)
+            }
+            DependencySource::Path(path) => Some(format!("path+{path}")),
+            DependencySource::Workspace => None,
+        }
+    }
+
+    /// Whether a lockfile package from `locked` can be the one this manifest
+    /// source asks for.
+    ///
+    /// `locked` is `None` for path packages, which lockfiles record without a
+    /// source. Registries named in `.cargo/config.toml` match any registry
+    /// other than crates.io, since their URL is not in the manifest. Git
+    /// URLs are compared without a trailing `/` or `.git`.
+    pub fn matches_lock(&self, locked: Option<&DependencySource>) -> bool {
+        match (self, locked) {
+            (DependencySource::Path(_), None | Some(DependencySource::Path(_))) => true,
+            (_, None) => false,
+            (DependencySource::Workspace, _) => false,
+            (
+                DependencySource::Registry { name, index },
+                Some(
+                    locked @ DependencySource::Registry {
+                        index: locked_index,
+                        ..
+                    },
+                ),
+            ) => match (name, index) {
+                (Some(_), _) => !locked.is_crates_io(),
+                (None, Some(index)) => {
+                    Some(index.as_str()) == locked_index.as_deref()
+                        || (is_crates_io_index(index) && locked.is_crates_io())
+                }
+                (None, None) => locked.is_crates_io(),
+            },
+            (
+                DependencySource::Git { url, reference, .. },
+                Some(DependencySource::Git {
+                    url: locked_url,
+                    reference: locked_reference,
+                    ..
+                }),
+            ) => {
+                canonical_git_url(url) == canonical_git_url(locked_url)
+                    && reference == locked_reference
+            }
+            _ => false,
+        }
+    }
+}
+
+impl Dependency {
+    /// Where the dependency comes from.
+    ///
+    /// A `path` wins over `git` and registries, as it does for local builds;
+    /// without `path`, `git` or a registry, it comes from crates.io.
+    pub fn source(&self) -> DependencySource {
+        let detail = match self {
+            Dependency::Version(_) => return DependencySource::crates_io(),
+            Dependency::Workspace(_) => return DependencySource::Workspace,
+            Dependency::Detailed(detail) => detail,
+        };
+        let value =
+            |field: &Option<crate::Spanned<String>>| field.as_ref().map(|v| v.value.clone());
+        if let Some(path) = value(&detail.path) {
+            return DependencySource::Path(path);
+        }
+        if let Some(url) = value(&detail.git) {
+            let reference = if let Some(branch) = value(&detail.branch) {
+                GitReference::Branch(branch)
+            } else if let Some(tag) = value(&detail.tag) {
+                GitReference::Tag(tag)
+            } else if let Some(rev) = value(&detail.rev) {
+                GitReference::Rev(rev)
+            } else {
+                GitReference::DefaultBranch
+            };
+            return DependencySource::Git {
+                url,
+                reference,
+                precise: None,
+            };
+        }
+        DependencySource::Registry {
+            name: value(&detail.registry),
+            index: value(&detail.registry_index),
+        }
+    }
+}
+
+impl LockPackage {
+    /// The parsed `source`, or `None` for path packages.
+    pub fn dependency_source(&self) -> Result<Option<DependencySource>, Error> {
+        self.source
+            .as_deref()
+            .map(DependencySource::from_lock)
+            .transpose()
+    }
+}
+
+impl CargoToml {
+    /// Git dependencies that follow a branch rather than a tag or revision,
+    /// so the commit they build can change without the manifest changing.
+    pub fn moving_git_dependencies(&self) -> impl Iterator<Item = DeclaredDependency<'_>> {
+        self.all_dependencies()
+            .filter(|declared| declared.dependency.source().is_moving())
+    }
+}
+
+fn is_crates_io_index(index: &str) -> bool {
+    index == CRATES_IO_SPARSE || Some(index) == CRATES_IO_SOURCE.strip_prefix("registry+")
+}
+
+/// Encode a git reference for a lockfile query string the way Cargo does,
+/// as `application/x-www-form-urlencoded`.
+fn encode(value: &str) -> String {
+    let mut encoded = String::with_capacity(value.len());
+    for byte in value.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'*' | b'-' | b'.' | b'_' => {
+                encoded.push(byte as char)
+            }
+            b' ' => encoded.push('+'),
+            _ => encoded.push_str(&format!("%{byte:02X}")),
+        }
+    }
+    encoded
+}
+
+/// Decode a git reference from a lockfile query string; malformed escapes
+/// are kept as written.
+fn decode(value: &str) -> String {
+    let bytes = value.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let hex = bytes
+            .get(i + 1..i + 3)
+            .and_then(|hex| std::str::from_utf8(hex).ok())
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+        match (bytes[i], hex) {
+            (b'%', Some(byte)) => {
+                decoded.push(byte);
+                i += 3;
+                continue;
+            }
+            (b'+', _) => decoded.push(b' '),
+            (byte, _) => decoded.push(byte),
+        }
+        i += 1;
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+fn canonical_git_url(url: &str) -> &str {
+    let url = url.trim_end_matches('/');
+    url.strip_suffix(".git").unwrap_or(url)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lockfile_sources() {
+        assert!(
+            DependencySource::from_lock(CRATES_IO_SOURCE)
+                .unwrap()
+                .is_crates_io()
+        );
+        assert!(
+            DependencySource::from_lock(CRATES_IO_SPARSE)
+                .unwrap()
+                .is_crates_io()
+        );
+
+        let source = "git+https://github.com/facet-rs/facet?branch=main#08b29a0d";
+        let git = DependencySource::from_lock(source).unwrap();
+        assert_eq!(
+            git,
+            DependencySource::Git {
+                url: "https://github.com/facet-rs/facet".to_owned(),
+                reference: GitReference::Branch("main".to_owned()),
+                precise: Some("08b29a0d".to_owned()),
+            }
+        );
+        assert!(git.is_moving());
+        assert_eq!(git.to_lock().as_deref(), Some(source));
+
+        let sparse = "sparse+https://registry.example.com/index/";
+        assert_eq!(
+            DependencySource::from_lock(sparse)
+                .unwrap()
+                .to_lock()
+                .as_deref(),
+            Some(sparse)
+        );
+        assert!(DependencySource::from_lock("svn+https://example.com").is_err());
+    }
+
+    #[test]
+    fn manifest_sources() {
+        let manifest = CargoToml::parse(
+            r#"
+[dependencies]
+serde = "1"
+facet = { git = "https://github.com/facet-rs/facet.git", branch = "main" }
+pinned = { git = "https://github.com/example/pinned", rev = "abc123" }
+local = { path = "../local", version = "0.1" }
+internal = { version = "1", registry = "company" }
+"#,
+        )
+        .unwrap();
+        let source = |name: &str| manifest.dependencies.as_ref().unwrap()[name].source();
+
+        assert!(source("serde").is_crates_io());
+        assert_eq!(
+            source("local"),
+            DependencySource::Path("../local".to_owned())
+        );
+        let locked = DependencySource::from_lock(
+            "git+https://github.com/facet-rs/facet?branch=main#08b29a0d",
+        )
+        .unwrap();
+        assert!(source("facet").matches_lock(Some(&locked)));
+        assert!(!source("pinned").matches_lock(Some(&locked)));
+        assert_eq!(source("internal").to_lock(), None);
+        assert!(source("internal").matches_lock(Some(
+            &DependencySource::from_lock("sparse+https://company.example/index/").unwrap()
+        )));
+
+        let moving: Vec<_> = manifest.moving_git_dependencies().map(|d| d.name).collect();
+        assert_eq!(moving, ["facet"]);
+    }
+
+    #[test]
+    fn encoded_git_references() {
+        let manifest = CargoToml::parse(
+            r#"
+[dependencies]
+facet = { git = "https://github.com/facet-rs/facet", branch = "feature/x y" }
+"#,
+        )
+        .unwrap();
+        let declared = manifest.dependencies.as_ref().unwrap()["facet"].source();
+
+        let source = "git+https://github.com/facet-rs/facet?branch=feature%2Fx+y#08b29a0d";
+        let locked = DependencySource::from_lock(source).unwrap();
+        let DependencySource::Git { reference, .. } = &locked else {
+            panic!("expected a git source");
+        };
+        assert_eq!(*reference, GitReference::Branch("feature/x y".to_owned()));
+        assert!(declared.matches_lock(Some(&locked)));
+        assert_eq!(locked.to_lock().as_deref(), Some(source));
+    }
+}
@@ -0,0 +1,183 @@
+//! The underscore spellings Cargo still accepts for a few keys, like
+//! `dev_dependencies` for `dev-dependencies`.
+
+use facet::Facet;
+
+use crate::serialize::toml_key;
+use crate::unused::{keys, resolve};
+use crate::{CargoToml, Diagnostic, Error, Span};
+
+/// A key written with its deprecated underscore spelling.
+///
+/// Cargo accepts `default_features`, `dev_dependencies`,
+/// `build_dependencies`, `crate_type` and `proc_macro` before the 2024
+/// edition, with a warning.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DeprecatedSpelling {
+    /// Dotted path of the key as written, like
+    /// `dependencies.serde.default_features`.
+    pub key: String,
+    /// The spelling as written, like `default_features`.
+    pub written: String,
+    /// The preferred spelling, like `default-features`.
+    pub preferred: String,
+    /// The span of the key where it is first written.
+    pub span: Option<Span>,
+}
+
+/// The underscore spellings declared as aliases in the manifest model.
+const UNDERSCORE_KEYS: [&str; 5] = [
+    "default_features",
+    "dev_dependencies",
+    "build_dependencies",
+    "crate_type",
+    "proc_macro",
+];
+
+impl CargoToml {
+    /// Find the keys in a `Cargo.toml` written with an underscore spelling,
+    /// in source order.
+    ///
+    /// Fails if a table sets a key with both spellings: Cargo would only warn
+    /// and use one of them, so the other value would be silently lost. See
+    /// [`CargoToml::spelling_issues`] for Cargo's warnings about them.
+    pub fn deprecated_spellings(contents: &str) -> Result<Vec<DeprecatedSpelling>, Error> {
+        if !UNDERSCORE_KEYS.iter().any(|key| contents.contains(key)) {
+            return Ok(Vec::new());
+        }
+        deprecated_spellings(contents)
+    }
+}
+
+/// Fail if a table sets a key with both spellings, without scanning the keys
+/// of manifests that use no underscore spelling.
+pub(crate) fn reject_both_spellings(source: &str) -> Result<(), Error> {
+    CargoToml::deprecated_spellings(source).map(drop)
+}
+
+fn deprecated_spellings(source: &str) -> Result<Vec<DeprecatedSpelling>, Error> {
+    let keys = keys(source);
+    let mut found: Vec<DeprecatedSpelling> = Vec::new();
+    for (path, range) in &keys {
+        let Ok(Some((depth, preferred))) = resolve(CargoToml::SHAPE, path) else {
+            continue;
+        };
+        let written = &path[depth];
+        let key: Vec<_> = path[..=depth].iter().map(|s| toml_key(s)).collect();
+        let key = key.join(".");
+        if found.iter().any(|f| f.key == key) {
+            continue;
+        }
+
+        // Point at the segment itself, not the whole header or dotted key.
+        let text = &source[range.clone()];
+        let span = match text.find(written.as_str()) {
+            Some(at) => range.start + at..range.start + at + written.len(),
+            None => range.clone(),
+        };
+        let span = Some(Span {
+            offset: span.start as u32,
+            len: span.len() as u32,
+        });
+
+        let both = keys.iter().any(|(other, _)| {
+            other.len() > depth && other[..depth] == path[..depth] && other[depth] == preferred
+        });
+        if both {
+            let message = format!("`{written}` and `{preferred}` are both set");
+            let mut diagnostic = Diagnostic::new(message, source, span);
+            diagnostic.help = Some(format!("remove `{written}`"));
+            return Err(Error::Parse {
+                diagnostic: Box::new(diagnostic),
+            });
+        }
+
+        found.push(DeprecatedSpelling {
+            key,
+            written: written.clone(),
+            preferred: preferred.to_owned(),
+            span,
+        });
+    }
+    Ok(found)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Dependency;
+
+    #[test]
+    fn underscore_spellings() {
+        let source = r#"
+[package]
+name = "old"
+version = "0.1.0"
+
+[lib]
+proc_macro = true
+crate_type = ["rlib"]
+
+[dependencies]
+serde = { version = "1", default_features = false }
+
+[dev_dependencies]
+insta = "1"
+
+[target.'cfg(unix)'.build_dependencies]
+cc = "1"
+"#;
+        let manifest = CargoToml::parse(source).unwrap();
+        let lib = manifest.lib.as_ref().unwrap();
+        assert!(lib.proc_macro.as_ref().unwrap().value);
+        assert_eq!(lib.crate_type.as_ref().unwrap().value, ["rlib"]);
+        let Some(Dependency::Detailed(serde)) =
+            &manifest.dependencies.as_ref().unwrap().get("serde")
+        else {
+            panic!("expected a detailed dependency");
+        };
+        assert!(!serde.default_features.as_ref().unwrap().value);
+        assert!(manifest.dev_dependencies.unwrap().contains_key("insta"));
+
+        let spellings = CargoToml::deprecated_spellings(source).unwrap();
+        let found: Vec<_> = spellings
+            .iter()
+            .map(|d| {
+                let span = d.span.unwrap();
+                let text = &source[span.offset as usize..][..span.len as usize];
+                (d.key.as_str(), text, d.preferred.as_str())
+            })
+            .collect();
+        assert_eq!(
+            found,
+            [
+                ("lib.proc_macro", "proc_macro", "proc-macro"),
+                ("lib.crate_type", "crate_type", "crate-type"),
+                (
+                    "dependencies.serde.default_features",
+                    "default_features",
+                    "default-features"
+                ),
+                ("dev_dependencies", "dev_dependencies", "dev-dependencies"),
+                (
+                    "target.\"cfg(unix)\".build_dependencies",
+                    "build_dependencies",
+                    "build-dependencies"
+                ),
+            ]
+        );
+
+        let both = "[dependencies]\nserde = { version = \"1\", default-features = false, default_features = true }\n";
+        let Err(Error::Parse { diagnostic }) = CargoToml::parse(both) else {
+            panic!("expected both spellings to be rejected");
+        };
+        assert_eq!(
+            diagnostic.message,
+            "`default_features` and `default-features` are both set"
+        );
+        assert_eq!(diagnostic.column, Some(52));
+
+        let both = "[dev-dependencies]\ninsta = \"1\"\n\n[dev_dependencies]\nserde = \"1\"\n";
+        assert!(CargoToml::parse(both).is_err());
+    }
+}
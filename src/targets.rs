@@ -0,0 +1,815 @@
+//! Build targets: the ones declared in the manifest merged with the ones Cargo
+//! discovers on disk.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+
+use camino::{Utf8Path, Utf8PathBuf};
+
+use crate::{
+    BenchTarget, BinTarget, CargoToml, Edition, EditionOrWorkspace, Error, ExampleTarget, Package,
+    Severity, Span, Spanned, TestTarget, ValidationIssue,
+};
+
+/// The kind of a build target.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum TargetKind {
+    /// The library, from `[lib]` or `src/lib.rs`.
+    Lib,
+    /// A binary, from `[[bin]]`, `src/main.rs` or `src/bin/`.
+    Bin,
+    /// An example, from `[[example]]` or `examples/`.
+    Example,
+    /// An integration test, from `[[test]]` or `tests/`.
+    Test,
+    /// A benchmark, from `[[bench]]` or `benches/`.
+    Bench,
+}
+
+impl TargetKind {
+    /// The manifest table the kind is declared in, like `bin` for `[[bin]]`.
+    pub fn table(self) -> &'static str {
+        match self {
+            TargetKind::Lib => "lib",
+            TargetKind::Bin => "bin",
+            TargetKind::Example => "example",
+            TargetKind::Test => "test",
+            TargetKind::Bench => "bench",
+        }
+    }
+}
+
+impl fmt::Display for TargetKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.table())
+    }
+}
+
+/// A build target with every default filled in.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Target {
+    /// What kind of target this is.
+    pub kind: TargetKind,
+    /// The crate name, with hyphens replaced by underscores for libraries.
+    pub name: String,
+    /// The root source file, joined to the package directory.
+    pub path: Utf8PathBuf,
+    /// The edition of the target, falling back to the package's.
+    ///
+    /// `None` when the package inherits its edition from the workspace; see
+    /// [`CargoToml::resolve`].
+    pub edition: Option<Edition>,
+    /// Features that must be enabled for the target to be built.
+    pub required_features: Vec<String>,
+    /// The crate types to generate, like `lib`, `proc-macro` or `bin`.
+    pub crate_types: Vec<String>,
+    /// Whether the target is declared in the manifest rather than discovered.
+    pub explicit: bool,
+    /// The span of the declaration's `name`, or failing that its `path`.
+    pub span: Option<Span>,
+}
+
+/// The result of [`CargoToml::targets`].
+#[derive(Debug, Clone, Default)]
+pub struct Targets {
+    /// Every target, in Cargo's order: the library, then binaries, examples,
+    /// tests and benchmarks, each with declared targets first.
+    pub targets: Vec<Target>,
+    /// Problems Cargo would reject or warn about, like two binaries with the
+    /// same name or a `[[test]]` whose file does not exist.
+    pub conflicts: Vec<ValidationIssue>,
+}
+
+impl Targets {
+    /// The targets of one kind.
+    pub fn of_kind(&self, kind: TargetKind) -> impl Iterator<Item = &Target> {
+        self.targets.iter().filter(move |t| t.kind == kind)
+    }
+
+    /// The library target, if there is one.
+    pub fn lib(&self) -> Option<&Target> {
+        self.of_kind(TargetKind::Lib).next()
+    }
+}
+
+/// The fields the `[[bin]]`, `[[example]]`, `[[test]]` and `[[bench]]` tables
+/// have in common.
+struct Declared<'a> {
+    name: Option<&'a Spanned<String>>,
+    path: Option<&'a Spanned<String>>,
+    edition: Option<Edition>,
+    required_features: Option<&'a Spanned<Vec<String>>>,
+    crate_type: Option<&'a Spanned<Vec<String>>>,
+}
+
+macro_rules! declared {
+    ($($ty:ty => $crate_type:ident),*) => {$(
+        impl<'a> From<&'a $ty> for Declared<'a> {
+            fn from(target: &'a $ty) -> Self {
+                Declared {
+                    name: target.name.as_ref(),
+                    path: target.path.as_ref(),
+                    edition: target.edition.as_ref().map(|e| e.value),
+                    required_features: target.required_features.as_ref(),
+                    crate_type: declared!(@crate_type target $crate_type),
+                }
+            }
+        }
+    )*};
+    (@crate_type $target:ident crate_type) => { $target.crate_type.as_ref() };
+    (@crate_type $target:ident none) => { None };
+}
+
+declared!(
+    BinTarget => none,
+    ExampleTarget => crate_type,
+    TestTarget => none,
+    BenchTarget => none
+);
+
+impl CargoToml {
+    /// The effective build targets of the package at `manifest_path`.
+    ///
+    /// Declared targets are completed with Cargo's defaults, and targets are
+    /// discovered in `src/lib.rs`, `src/main.rs`, `src/bin/`, `examples/`,
+    /// `tests/` and `benches/` unless the matching `auto*` key is false. A
+    /// discovered target is skipped when a declared one has the same name or
+    /// path. As in Cargo, packages on edition 2015 only discover targets of a
+    /// kind that has no declared ones.
+    ///
+    /// A manifest without `[package]` has no targets. Fails if a target
+    /// directory exists but cannot be read; entries with names that are not
+    /// UTF-8 are skipped with a warning.
+    pub fn targets(&self, manifest_path: impl AsRef<Utf8Path>) -> Result<Targets, Error> {
+        let Some(package) = &self.package else {
+            return Ok(Targets::default());
+        };
+        let root = manifest_path.as_ref().parent().unwrap_or(Utf8Path::new(""));
+        let mut discovery = Discovery {
+            root,
+            package_name: package.name.as_ref().map_or("", |n| &n.value),
+            edition: match &package.edition {
+                Some(EditionOrWorkspace::Edition(edition)) => Some(edition.value),
+                Some(EditionOrWorkspace::Workspace(_)) => None,
+                None => Some(Edition::E2015),
+            },
+            targets: Targets::default(),
+        };
+
+        discovery.lib(self, package)?;
+        let kinds: [(_, Vec<Declared>, _); 4] = [
+            (
+                TargetKind::Bin,
+                self.bin.iter().flatten().map(Declared::from).collect(),
+                &package.autobins,
+            ),
+            (
+                TargetKind::Example,
+                self.example.iter().flatten().map(Declared::from).collect(),
+                &package.autoexamples,
+            ),
+            (
+                TargetKind::Test,
+                self.test.iter().flatten().map(Declared::from).collect(),
+                &package.autotests,
+            ),
+            (
+                TargetKind::Bench,
+                self.bench.iter().flatten().map(Declared::from).collect(),
+                &package.autobenches,
+            ),
+        ];
+        for (kind, declared, auto) in kinds {
+            discovery.kind(kind, &declared, auto.as_ref().map(|a| a.value))?;
+        }
+        discovery.check();
+        Ok(discovery.targets)
+    }
+}
+
+struct Discovery<'a> {
+    root: &'a Utf8Path,
+    package_name: &'a str,
+    edition: Option<Edition>,
+    targets: Targets,
+}
+
+impl Discovery<'_> {
+    fn lib(&mut self, manifest: &CargoToml, package: &Package) -> Result<(), Error> {
+        let default = self.root.join("src/lib.rs");
+        let autolib = package.autolib.as_ref().is_none_or(|a| a.value);
+        let (path, span) = match &manifest.lib {
+            Some(lib) => {
+                let span = lib.name.as_ref().or(lib.path.as_ref()).and_then(|v| v.span);
+                match &lib.path {
+                    Some(path) => (self.root.join(&path.value), span),
+                    None if is_file(&default)? => (default, span),
+                    None => {
+                        self.conflict(
+                            Severity::Error,
+                            "lib",
+                            span,
+                            "`[lib]` has no `path` and `src/lib.rs` does not exist".to_owned(),
+                        );
+                        return Ok(());
+                    }
+                }
+            }
+            None if autolib && is_file(&default)? => (default, None),
+            None => return Ok(()),
+        };
+
+        let lib = manifest.lib.as_ref();
+        let name = match lib.and_then(|l| l.name.as_ref()) {
+            Some(name) => name.value.clone(),
+            None => self.package_name.replace('-', "_"),
+        };
+        let crate_types = if lib
+            .and_then(|l| l.proc_macro.as_ref())
+            .is_some_and(|p| p.value)
+        {
+            vec!["proc-macro".to_owned()]
+        } else {
+            lib.and_then(|l| l.crate_type.as_ref())
+                .map_or_else(|| vec!["lib".to_owned()], |c| c.value.clone())
+        };
+        self.targets.targets.push(Target {
+            kind: TargetKind::Lib,
+            name,
+            path,
+            edition: lib
+                .and_then(|l| l.edition.as_ref())
+                .map(|e| e.value)
+                .or(self.edition),
+            required_features: lib
+                .and_then(|l| l.required_features.as_ref())
+                .map_or_else(Vec::new, |f| f.value.clone()),
+            crate_types,
+            explicit: lib.is_some(),
+            span,
+        });
+        Ok(())
+    }
+
+    fn kind(
+        &mut self,
+        kind: TargetKind,
+        declared: &[Declared],
+        auto: Option<bool>,
+    ) -> Result<(), Error> {
+        let candidates = self.candidates(kind)?;
+        let table = kind.table();
+
+        let mut names = BTreeSet::new();
+        let mut paths = BTreeSet::new();
+        for target in declared {
+            let span = target.name.or(target.path).and_then(|v| v.span);
+            let Some(name) = target.name else {
+                self.conflict(
+                    Severity::Error,
+                    table,
+                    span,
+                    format!("a `[[{table}]]` target has no `name`"),
+                );
+                continue;
+            };
+            // Even if its source cannot be found, a declared target keeps
+            // discovery from adding another target of the same name.
+            names.insert(name.value.as_str());
+            let path = match target.path {
+                Some(path) => self.root.join(&path.value),
+                None => {
+                    let found: Vec<_> = candidates.get(&name.value).into_iter().flatten().collect();
+                    match found[..] {
+                        [path] => path.clone(),
+                        [] => {
+                            self.conflict(
+                                Severity::Error,
+                                table,
+                                span,
+                                format!(
+                                    "cannot find the source of {table} target `{}`; set `path`",
+                                    name.value
+                                ),
+                            );
+                            continue;
+                        }
+                        _ => {
+                            self.conflict(
+                                Severity::Error,
+                                table,
+                                span,
+                                format!(
+                                    "the source of {table} target `{}` is ambiguous between {}; set `path`",
+                                    name.value,
+                                    quoted(found.iter().map(|p| self.relative(p))),
+                                ),
+                            );
+                            continue;
+                        }
+                    }
+                }
+            };
+            paths.insert(path.clone());
+            self.targets.targets.push(Target {
+                kind,
+                name: name.value.clone(),
+                path,
+                edition: target.edition.or(self.edition),
+                required_features: target
+                    .required_features
+                    .map_or_else(Vec::new, |f| f.value.clone()),
+                crate_types: target
+                    .crate_type
+                    .map_or_else(|| vec!["bin".to_owned()], |c| c.value.clone()),
+                explicit: true,
+                span,
+            });
+        }
+
+        let remaining: Vec<_> = candidates
+            .iter()
+            .flat_map(|(name, paths)| paths.iter().map(move |path| (name, path)))
+            .filter(|(name, path)| !names.contains(name.as_str()) && !paths.contains(*path))
+            .collect();
+        let discover = match auto {
+            Some(auto) => auto,
+            None => declared.is_empty() || self.edition != Some(Edition::E2015),
+        };
+        if !discover {
+            if auto.is_none() && !remaining.is_empty() {
+                let key = format!("package.auto{}", auto_suffix(kind));
+                let message = format!(
+                    "{} not discovered because `[[{table}]]` targets are declared on edition 2015; set `{key} = true` to include them",
+                    quoted(remaining.iter().map(|(_, path)| self.relative(path))),
+                );
+                self.conflict(Severity::Warning, &key, None, message);
+            }
+            return Ok(());
+        }
+        for (name, path) in remaining {
+            self.targets.targets.push(Target {
+                kind,
+                name: name.clone(),
+                path: path.clone(),
+                edition: self.edition,
+                required_features: Vec::new(),
+                crate_types: vec!["bin".to_owned()],
+                explicit: false,
+                span: None,
+            });
+        }
+        Ok(())
+    }
+
+    /// The files Cargo would discover for `kind`, by target name.
+    ///
+    /// A name maps to several files when both `name.rs` and `name/main.rs`
+    /// exist. Entries whose names are not UTF-8 are skipped with a warning.
+    fn candidates(
+        &mut self,
+        kind: TargetKind,
+    ) -> Result<BTreeMap<String, Vec<Utf8PathBuf>>, Error> {
+        let mut candidates: BTreeMap<_, Vec<_>> = BTreeMap::new();
+        let dir = match kind {
+            TargetKind::Lib => return Ok(candidates),
+            TargetKind::Bin => {
+                let main = self.root.join("src/main.rs");
+                if is_file(&main)? {
+                    candidates
+                        .entry(self.package_name.to_owned())
+                        .or_default()
+                        .push(main);
+                }
+                "src/bin"
+            }
+            TargetKind::Example => "examples",
+            TargetKind::Test => "tests",
+            TargetKind::Bench => "benches",
+        };
+
+        let dir = self.root.join(dir);
+        let entries = match dir.read_dir() {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(candidates),
+            Err(source) => {
+                return Err(Error::Io {
+                    path: dir,
+                    source: source.into(),
+                });
+            }
+        };
+        let mut found = Vec::new();
+        for entry in entries {
+            let entry = entry.map_err(|source| Error::Io {
+                path: dir.clone(),
+                source: source.into(),
+            })?;
+            let path = match Utf8PathBuf::from_path_buf(entry.path()) {
+                Ok(path) => path,
+                Err(path) => {
+                    let path = path.strip_prefix(self.root).unwrap_or(&path).to_owned();
+                    self.conflict(
+                        Severity::Warning,
+                        kind.table(),
+                        None,
+                        format!("`{}` is skipped: its name is not UTF-8", path.display()),
+                    );
+                    continue;
+                }
+            };
+            if path.extension() == Some("rs") && is_file(&path)? {
+                found.push((path.file_stem().unwrap_or_default().to_owned(), path));
+            } else if is_file(&path.join("main.rs"))? {
+                let name = path.file_name().unwrap_or_default().to_owned();
+                found.push((name, path.join("main.rs")));
+            }
+        }
+        found.sort();
+        for (name, path) in found {
+            candidates.entry(name).or_default().push(path);
+        }
+        Ok(candidates)
+    }
+
+    /// Report the name and path clashes Cargo rejects or warns about.
+    fn check(&mut self) {
+        let mut issues = Vec::new();
+        let mut names = BTreeSet::new();
+        let mut paths: BTreeMap<_, Vec<_>> = BTreeMap::new();
+        for target in &self.targets.targets {
+            let table = target.kind.table();
+            if !names.insert((target.kind, &target.name)) {
+                issues.push((
+                    Severity::Error,
+                    table,
+                    target.span,
+                    format!("found duplicate {table} target name `{}`", target.name),
+                ));
+            }
+            paths.entry(&target.path).or_default().push(target);
+        }
+        for (path, targets) in paths.into_iter().filter(|(_, t)| t.len() > 1) {
+            let names = quoted(targets.iter().map(|t| format!("{} {}", t.kind, t.name)));
+            issues.push((
+                Severity::Warning,
+                targets[1].kind.table(),
+                targets[1].span,
+                format!(
+                    "`{}` is the source of several targets: {names}",
+                    self.relative(path)
+                ),
+            ));
+        }
+        if self.targets.targets.is_empty() && self.targets.conflicts.is_empty() {
+            issues.push((
+                Severity::Error,
+                "package",
+                None,
+                "the package has no targets".to_owned(),
+            ));
+        }
+        for (severity, key, span, message) in issues {
+            self.conflict(severity, key, span, message);
+        }
+    }
+
+    fn conflict(&mut self, severity: Severity, key: &str, span: Option<Span>, message: String) {
+        self.targets.conflicts.push(ValidationIssue {
+            severity,
+            key: key.to_owned(),
+            message,
+            span,
+        });
+    }
+
+    fn relative(&self, path: &Utf8Path) -> String {
+        path.strip_prefix(self.root).unwrap_or(path).to_string()
+    }
+}
+
+/// The suffix of the `auto*` key for `kind`, like `bins` in `autobins`.
+fn auto_suffix(kind: TargetKind) -> &'static str {
+    match kind {
+        TargetKind::Lib => "lib",
+        TargetKind::Bin => "bins",
+        TargetKind::Example => "examples",
+        TargetKind::Test => "tests",
+        TargetKind::Bench => "benches",
+    }
+}
+
+fn quoted(items: impl Iterator<Item = impl fmt::Display>) -> String {
+    items
+        .map(|i| format!("`{i}`"))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+fn is_file(path: &Utf8Path) -> Result<bool, Error> {
+    match path.metadata() {
+        Ok(metadata) => Ok(metadata.is_file()),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(false),
+        Err(source) => Err(Error::Io {
+            path: path.to_owned(),
+            source: source.into(),
+        }),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testdir::TestDir;
+
+    /// Each target's kind, name, path relative to `dir` and whether it is
+    /// declared.
+    fn summary<'a>(
+        targets: &'a Targets,
+        dir: &TestDir,
+    ) -> Vec<(TargetKind, &'a str, &'a str, bool)> {
+        targets
+            .targets
+            .iter()
+            .map(|t| {
+                (
+                    t.kind,
+                    t.name.as_str(),
+                    t.path.strip_prefix(dir.path()).unwrap().as_str(),
+                    t.explicit,
+                )
+            })
+            .collect()
+    }
+
+    fn messages(targets: &Targets) -> Vec<(Severity, &str)> {
+        targets
+            .conflicts
+            .iter()
+            .map(|c| (c.severity, c.message.as_str()))
+            .collect()
+    }
+
+    #[test]
+    fn discover_targets() {
+        let dir = TestDir::new("targets");
+        for file in [
+            "src/lib.rs",
+            "src/main.rs",
+            "src/bin/tool.rs",
+            "src/bin/server/main.rs",
+            "src/bin/twice.rs",
+            "src/bin/twice/main.rs",
+            "tests/smoke.rs",
+            "tests/custom.rs",
+            "examples/demo.rs",
+        ] {
+            dir.write(file, "");
+        }
+        let source = r#"
+[package]
+name = "my-app"
+edition = "2021"
+
+[lib]
+crate-type = ["rlib", "cdylib"]
+
+[[bin]]
+name = "tool"
+required-features = ["cli"]
+
+[[bin]]
+name = "twice"
+
+[[test]]
+name = "integration"
+path = "tests/custom.rs"
+
+[[test]]
+name = "missing"
+
+[[example]]
+name = "demo"
+edition = "2024"
+"#;
+        let manifest = CargoToml::parse(source).unwrap();
+        let targets = manifest.targets(dir.path().join("Cargo.toml")).unwrap();
+
+        assert_eq!(
+            summary(&targets, &dir),
+            [
+                (TargetKind::Lib, "my_app", "src/lib.rs", true),
+                (TargetKind::Bin, "tool", "src/bin/tool.rs", true),
+                (TargetKind::Bin, "my-app", "src/main.rs", false),
+                (TargetKind::Bin, "server", "src/bin/server/main.rs", false),
+                (TargetKind::Example, "demo", "examples/demo.rs", true),
+                (TargetKind::Test, "integration", "tests/custom.rs", true),
+                (TargetKind::Test, "smoke", "tests/smoke.rs", false),
+            ]
+        );
+        let lib = targets.lib().unwrap();
+        assert_eq!(lib.crate_types, ["rlib", "cdylib"]);
+        assert_eq!(lib.edition, Some(Edition::E2021));
+        assert_eq!(targets.targets[1].required_features, ["cli"]);
+        assert_eq!(targets.targets[4].edition, Some(Edition::E2024));
+
+        let conflicts: Vec<_> = targets
+            .conflicts
+            .iter()
+            .map(|c| {
+                let span = c.span.unwrap();
+                (
+                    c.severity,
+                    &source[span.offset as usize..][..span.len as usize],
+                )
+            })
+            .collect();
+        assert_eq!(
+            conflicts,
+            [
+                (Severity::Error, "\"twice\""),
+                (Severity::Error, "\"missing\"")
+            ]
+        );
+    }
+
+    #[test]
+    fn auto_discovery_disabled() {
+        let dir = TestDir::new("targets-auto");
+        for file in [
+            "src/main.rs",
+            "src/bin/tool.rs",
+            "examples/demo.rs",
+            "tests/smoke.rs",
+        ] {
+            dir.write(file, "");
+        }
+        let manifest = CargoToml::parse(
+            r#"
+[package]
+name = "app"
+edition = "2021"
+autobins = false
+autoexamples = false
+autotests = false
+
+[[bin]]
+name = "tool"
+"#,
+        )
+        .unwrap();
+        let targets = manifest.targets(dir.path().join("Cargo.toml")).unwrap();
+        assert_eq!(
+            summary(&targets, &dir),
+            [(TargetKind::Bin, "tool", "src/bin/tool.rs", true)]
+        );
+        assert_eq!(messages(&targets), []);
+
+        // Edition 2015 stops discovering a kind once one target is declared.
+        let manifest =
+            CargoToml::parse("[package]\nname = \"app\"\n\n[[bin]]\nname = \"tool\"\n").unwrap();
+        let targets = manifest.targets(dir.path().join("Cargo.toml")).unwrap();
+        assert_eq!(
+            summary(&targets, &dir),
+            [
+                (TargetKind::Bin, "tool", "src/bin/tool.rs", true),
+                (TargetKind::Example, "demo", "examples/demo.rs", false),
+                (TargetKind::Test, "smoke", "tests/smoke.rs", false),
+            ]
+        );
+        assert_eq!(
+            messages(&targets),
+            [(
+                Severity::Warning,
+                "`src/main.rs` not discovered because `[[bin]]` targets are declared on edition 2015; set `package.autobins = true` to include them"
+            )]
+        );
+    }
+
+    #[test]
+    fn declared_bins_override_discovered() {
+        let dir = TestDir::new("targets-declared");
+        for file in ["src/main.rs", "src/bin/tool.rs", "src/bin/other.rs"] {
+            dir.write(file, "");
+        }
+        let manifest = CargoToml::parse(
+            r#"
+[package]
+name = "app"
+edition = "2021"
+
+[[bin]]
+name = "tool"
+required-features = ["cli"]
+
+[[bin]]
+name = "renamed"
+path = "src/bin/other.rs"
+"#,
+        )
+        .unwrap();
+        let targets = manifest.targets(dir.path().join("Cargo.toml")).unwrap();
+        assert_eq!(
+            summary(&targets, &dir),
+            [
+                (TargetKind::Bin, "tool", "src/bin/tool.rs", true),
+                (TargetKind::Bin, "renamed", "src/bin/other.rs", true),
+                (TargetKind::Bin, "app", "src/main.rs", false),
+            ]
+        );
+        assert_eq!(targets.targets[0].required_features, ["cli"]);
+        assert_eq!(messages(&targets), []);
+    }
+
+    #[test]
+    fn bin_directories() {
+        let dir = TestDir::new("targets-bin-dir");
+        for file in [
+            "src/bin/server/main.rs",
+            "src/bin/server/util.rs",
+            "src/bin/empty/lib.rs",
+        ] {
+            dir.write(file, "");
+        }
+        let manifest = CargoToml::parse("[package]\nname = \"app\"\nedition = \"2021\"\n").unwrap();
+        let targets = manifest.targets(dir.path().join("Cargo.toml")).unwrap();
+        assert_eq!(
+            summary(&targets, &dir),
+            [(TargetKind::Bin, "server", "src/bin/server/main.rs", false)]
+        );
+    }
+
+    #[test]
+    fn name_collisions() {
+        let dir = TestDir::new("targets-collisions");
+        for file in [
+            "src/bin/twice.rs",
+            "src/bin/twice/main.rs",
+            "src/bin/shared.rs",
+        ] {
+            dir.write(file, "");
+        }
+        let manifest = CargoToml::parse(
+            r#"
+[package]
+name = "app"
+edition = "2021"
+
+[[bin]]
+name = "a"
+path = "src/bin/shared.rs"
+
+[[bin]]
+name = "b"
+path = "src/bin/shared.rs"
+
+[[example]]
+name = "dup"
+path = "src/bin/shared.rs"
+
+[[example]]
+name = "dup"
+path = "src/bin/shared.rs"
+"#,
+        )
+        .unwrap();
+        let targets = manifest.targets(dir.path().join("Cargo.toml")).unwrap();
+        assert_eq!(
+            messages(&targets),
+            [
+                (Severity::Error, "found duplicate bin target name `twice`"),
+                (Severity::Error, "found duplicate example target name `dup`"),
+                (
+                    Severity::Warning,
+                    "`src/bin/shared.rs` is the source of several targets: `bin a`, `bin b`, `example dup`, `example dup`"
+                ),
+            ]
+        );
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn non_utf8_entries() {
+        use std::os::unix::ffi::OsStrExt;
+
+        let dir = TestDir::new("targets-non-utf8");
+        dir.write("src/main.rs", "");
+        let name = std::ffi::OsStr::from_bytes(b"bad\xff.rs");
+        std::fs::create_dir_all(dir.path().join("src/bin")).unwrap();
+        std::fs::write(dir.path().join("src/bin").as_std_path().join(name), "").unwrap();
+
+        let manifest = CargoToml::parse("[package]\nname = \"app\"\nedition = \"2021\"\n").unwrap();
+        let targets = manifest.targets(dir.path().join("Cargo.toml")).unwrap();
+        assert_eq!(
+            summary(&targets, &dir),
+            [(TargetKind::Bin, "app", "src/main.rs", false)]
+        );
+        assert_eq!(
+            messages(&targets),
+            [(
+                Severity::Warning,
+                "`src/bin/bad\u{fffd}.rs` is skipped: its name is not UTF-8"
+            )]
+        );
+    }
+}
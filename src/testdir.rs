@@ -0,0 +1,40 @@
+//! Scratch directories for tests that read the filesystem.
+
+use camino::{Utf8Path, Utf8PathBuf};
+
+/// A directory under the system temp dir, removed when dropped, even if the
+/// test panics.
+pub(crate) struct TestDir {
+    path: Utf8PathBuf,
+}
+
+impl TestDir {
+    /// Create an empty directory. `name` must be unique among the tests, as
+    /// they run in parallel.
+    pub(crate) fn new(name: &str) -> Self {
+        let path =
+            std::env::temp_dir().join(format!("facet-cargo-toml-{name}-{}", std::process::id()));
+        let path = Utf8PathBuf::from_path_buf(path).unwrap();
+        let _ = std::fs::remove_dir_all(&path);
+        std::fs::create_dir_all(&path).unwrap();
+        TestDir { path }
+    }
+
+    pub(crate) fn path(&self) -> &Utf8Path {
+        &self.path
+    }
+
+    /// Write `contents` to `path`, relative to the directory, creating its
+    /// parents.
+    pub(crate) fn write(&self, path: &str, contents: &str) {
+        let path = self.path.join(path);
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(path, contents).unwrap();
+    }
+}
+
+impl Drop for TestDir {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.path);
+    }
+}
@@ -0,0 +1,379 @@
+//! Finding manifest keys the typed model does not know, like Cargo's
+//! "unused manifest key" warnings.
+
+use std::collections::HashSet;
+use std::fmt;
+use std::ops::Range;
+
+use facet::{Def, Facet, Shape, Type, UserType};
+
+use crate::layout::{Scan, parse_key, skip_string};
+use crate::serialize::toml_key;
+use crate::{CargoToml, Diagnostic, Error, Span};
+
+/// A key in a manifest that [`CargoToml`] has no field for.
+///
+/// facet-toml skips such keys, so a typo like `default-feature` or
+/// `[pakage]` silently has no effect.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UnusedKey {
+    /// Dotted path of the key, like `dependencies.serde.default-feature`.
+    pub key: String,
+    /// Where the key, or the header of the table it names, is written.
+    pub span: Option<Span>,
+    /// A known key with a similar name, like `default-features`.
+    pub suggestion: Option<String>,
+}
+
+impl fmt::Display for UnusedKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "unused manifest key: {}", self.key)?;
+        if let Some(suggestion) = &self.suggestion {
+            write!(f, " (did you mean `{suggestion}`?)")?;
+        }
+        Ok(())
+    }
+}
+
+impl CargoToml {
+    /// Parse a `Cargo.toml` from a string, also returning the keys that were
+    /// not recognised, in source order.
+    ///
+    /// A key is reported once, at its first occurrence; keys below an unknown
+    /// key or table are not reported separately.
+    pub fn parse_with_unused_keys(contents: &str) -> Result<(Self, Vec<UnusedKey>), Error> {
+        let manifest = Self::parse(contents)?;
+        Ok((manifest, unused_keys(contents)))
+    }
+
+    /// Parse a `Cargo.toml` from a string, failing on the first key that is
+    /// not recognised; see [`CargoToml::parse_with_unused_keys`].
+    pub fn parse_strict(contents: &str) -> Result<Self, Error> {
+        let (manifest, unused) = Self::parse_with_unused_keys(contents)?;
+        let Some(unused) = unused.into_iter().next() else {
+            return Ok(manifest);
+        };
+        let mut diagnostic = Diagnostic::new(
+            format!("unused manifest key `{}`", unused.key),
+            contents,
+            unused.span,
+        );
+        diagnostic.help = unused.suggestion.map(|s| format!("did you mean `{s}`?"));
+        Err(Error::Parse {
+            diagnostic: Box::new(diagnostic),
+        })
+    }
+}
+
+fn unused_keys(source: &str) -> Vec<UnusedKey> {
+    let mut seen = HashSet::new();
+    let mut unused = Vec::new();
+    for (path, range) in keys(source) {
+        let Err((depth, known)) = resolve(CargoToml::SHAPE, &path) else {
+            continue;
+        };
+        let key: Vec<_> = path[..=depth].iter().map(|s| toml_key(s)).collect();
+        let key = key.join(".");
+        if !seen.insert(key.clone()) {
+            continue;
+        }
+        unused.push(UnusedKey {
+            key,
+            span: Some(Span {
+                offset: range.start as u32,
+                len: range.len() as u32,
+            }),
+            suggestion: suggest(&path[depth], &known),
+        });
+    }
+    unused
+}
+
+/// Every key in `source` with the full path it sets, in source order: table
+/// headers, keys of entries and keys inside inline tables.
+pub(crate) fn keys(source: &str) -> Vec<(Vec<String>, Range<usize>)> {
+    let mut keys = Vec::new();
+    let scan = Scan::new(source);
+    for table in scan.tables.iter().skip(1) {
+        let header = &source[table.start..table.body];
+        let start = table.start + (header.len() - header.trim_start().len());
+        let end = table.start + header.rfind(']').map_or(header.trim_end().len(), |i| i + 1);
+        keys.push((table.path.clone(), start..end));
+    }
+    for entry in &scan.entries {
+        let mut path = scan.tables[entry.table].path.clone();
+        path.extend(entry.key.iter().cloned());
+        inline_keys(source, entry.value.start, &path, &mut keys);
+        keys.push((path, entry.key_span.clone()));
+    }
+    keys.sort_by_key(|(_, range)| range.start);
+    keys
+}
+
+/// Collect the keys of the inline tables in the value at `pos`, including
+/// tables inside arrays.
+fn inline_keys(
+    source: &str,
+    mut pos: usize,
+    path: &[String],
+    keys: &mut Vec<(Vec<String>, Range<usize>)>,
+) -> usize {
+    let bytes = source.as_bytes();
+    let (open, close) = match bytes.get(pos) {
+        Some(b'{') => (b'{', b'}'),
+        Some(b'[') => (b'[', b']'),
+        _ => return skip_value(bytes, pos),
+    };
+    pos += 1;
+    loop {
+        pos = skip_blank(bytes, pos);
+        match bytes.get(pos) {
+            None => return pos,
+            Some(&b) if b == close => return pos + 1,
+            Some(b',') => pos += 1,
+            Some(_) if open == b'[' => pos = inline_keys(source, pos, path, keys),
+            Some(_) => {
+                let (key, after_key) = parse_key(source, pos);
+                if bytes.get(after_key) != Some(&b'=') {
+                    return source.len();
+                }
+                let key_span = pos..pos + source[pos..after_key].trim_end().len();
+                let mut full = path.to_vec();
+                full.extend(key);
+                pos = skip_blank(bytes, after_key + 1);
+                pos = inline_keys(source, pos, &full, keys);
+                keys.push((full, key_span));
+            }
+        }
+    }
+}
+
+/// Skip a scalar value inside an inline table or array.
+fn skip_value(bytes: &[u8], mut pos: usize) -> usize {
+    while let Some(&b) = bytes.get(pos) {
+        match b {
+            b'"' | b'\'' => pos = skip_string(bytes, pos, b),
+            b',' | b'}' | b']' | b'\n' | b'#' => return pos,
+            _ => pos += 1,
+        }
+    }
+    pos
+}
+
+/// Skip whitespace, newlines and comments.
+fn skip_blank(bytes: &[u8], mut pos: usize) -> usize {
+    while let Some(&b) = bytes.get(pos) {
+        match b {
+            b' ' | b'\t' | b'\r' | b'\n' => pos += 1,
+            b'#' => {
+                while bytes.get(pos).is_some_and(|&b| b != b'\n') {
+                    pos += 1;
+                }
+            }
+            _ => break,
+        }
+    }
+    pos
+}
+
+/// Where a key path resolved to: the first segment written with a field's
+/// alias, if any, with the field's own name.
+pub(crate) type Aliased = Option<(usize, &'static str)>;
+
+/// Follow `path` through `shape`. On failure, returns the index of the first
+/// unknown segment and the keys that would have been accepted there.
+pub(crate) fn resolve(
+    mut shape: &'static Shape,
+    path: &[String],
+) -> Result<Aliased, (usize, Vec<&'static str>)> {
+    let mut aliased = None;
+    let mut depth = 0;
+    loop {
+        shape = unwrap(shape);
+        let Some(segment) = path.get(depth) else {
+            return Ok(aliased);
+        };
+        match (shape.def, shape.ty) {
+            (Def::DynamicValue(_), _) => return Ok(aliased),
+            (Def::Map(map), _) => shape = map.v,
+            (_, Type::User(UserType::Struct(st))) => {
+                let fields = st.fields.iter().filter(|f| !f.should_skip_deserializing());
+                let field = fields.clone().find(|f| {
+                    !f.is_flattened() && (key_name(f) == segment || f.alias == Some(segment))
+                });
+                match field {
+                    Some(field) => {
+                        if key_name(field) != segment && aliased.is_none() {
+                            aliased = Some((depth, key_name(field)));
+                        }
+                        shape = field.shape();
+                    }
+                    None => match fields.clone().find(|f| f.is_flattened()) {
+                        // A flattened field takes the key itself.
+                        Some(flattened) => {
+                            shape = flattened.shape();
+                            continue;
+                        }
+                        None => return Err((depth, fields.map(key_name).collect())),
+                    },
+                }
+            }
+            (_, Type::User(UserType::Enum(en))) => {
+                // An untagged enum accepts a key if any of its variants does.
+                let mut deepest: Option<(usize, Vec<&'static str>)> = None;
+                for variant in en.variants {
+                    let [field] = variant.data.fields else {
+                        continue;
+                    };
+                    match resolve(field.shape(), &path[depth..]) {
+                        Ok(inner) => {
+                            return Ok(aliased.or(inner.map(|(at, name)| (depth + at, name))));
+                        }
+                        Err((at, known)) => match &mut deepest {
+                            Some((deepest, all)) if *deepest == at => all.extend(known),
+                            Some((deepest, _)) if *deepest > at => {}
+                            _ => deepest = Some((at, known)),
+                        },
+                    }
+                }
+                let (at, known) = deepest.unwrap_or_default();
+                return Err((depth + at, known));
+            }
+            // Nothing below a scalar is known.
+            _ => return Err((depth, Vec::new())),
+        }
+        depth += 1;
+    }
+}
+
+/// Look through options, lists and `Spanned` to the shape holding the keys.
+fn unwrap(mut shape: &'static Shape) -> &'static Shape {
+    loop {
+        shape = match (shape.def, shape.ty) {
+            (Def::Option(option), _) => option.t,
+            (Def::List(list), _) => list.t,
+            (_, Type::User(UserType::Struct(st))) if shape.is_metadata_container() => {
+                match st.fields.iter().find(|f| f.metadata.is_none()) {
+                    Some(value) => value.shape(),
+                    None => return shape,
+                }
+            }
+            _ => return shape,
+        };
+    }
+}
+
+fn key_name(field: &facet::Field) -> &'static str {
+    field.rename.unwrap_or(field.name)
+}
+
+/// The known key closest to `key`, if any is close enough to be a typo.
+fn suggest(key: &str, known: &[&str]) -> Option<String> {
+    known
+        .iter()
+        .map(|candidate| (edit_distance(key, candidate), *candidate))
+        .filter(|(distance, candidate)| *distance <= 3.min(candidate.len() / 2).max(1))
+        .min_by_key(|(distance, _)| *distance)
+        .map(|(_, candidate)| candidate.to_owned())
+}
+
+/// The Levenshtein distance between `a` and `b`.
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut row: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut previous = row[0];
+        row[0] = i + 1;
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = previous + usize::from(ca != *cb);
+            previous = row[j + 1];
+            row[j + 1] = substitution.min(previous + 1).min(row[j] + 1);
+        }
+    }
+    row[b.len()]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn unused_keys() {
+        let source = r#"
+[package]
+name = "demo"
+version = "0.1.0"
+edtion = "2021"
+metadata.docs.rs = { all-features = true }
+
+[pakage]
+name = "typo"
+
+[dependencies]
+serde = { version = "1", default-feature = false }
+local = { path = "../local", verison = "0.1" }
+shared = { workspace = true, features = ["std"] }
+
+[dev-dependences]
+insta = "1"
+
+[target.'cfg(unix)'.dependencies]
+libc = { version = "0.2", optinal = true }
+
+[[bin]]
+name = "demo"
+pth = "src/main.rs"
+
+[badges]
+maintenance = { status = "actively-developed" }
+
+[lints.rust]
+unsafe_code = { level = "deny", priorty = 1 }
+"#;
+        let (_, unused) = CargoToml::parse_with_unused_keys(source).unwrap();
+        let found: Vec<_> = unused
+            .iter()
+            .map(|u| {
+                let span = u.span.unwrap();
+                let text = &source[span.offset as usize..][..span.len as usize];
+                (u.key.as_str(), text, u.suggestion.as_deref())
+            })
+            .collect();
+        assert_eq!(
+            found,
+            [
+                ("package.edtion", "edtion", Some("edition")),
+                ("pakage", "[pakage]", Some("package")),
+                (
+                    "dependencies.serde.default-feature",
+                    "default-feature",
+                    Some("default-features")
+                ),
+                ("dependencies.local.verison", "verison", Some("version")),
+                (
+                    "dev-dependences",
+                    "[dev-dependences]",
+                    Some("dev-dependencies")
+                ),
+                (
+                    "target.\"cfg(unix)\".dependencies.libc.optinal",
+                    "optinal",
+                    Some("optional")
+                ),
+                ("bin.pth", "pth", Some("path")),
+                (
+                    "lints.rust.unsafe_code.priorty",
+                    "priorty",
+                    Some("priority")
+                ),
+            ]
+        );
+
+        let Err(Error::Parse { diagnostic }) = CargoToml::parse_strict(source) else {
+            panic!("expected a strict parse error");
+        };
+        assert_eq!(diagnostic.line, Some(5));
+        assert_eq!(diagnostic.help.as_deref(), Some("did you mean `edition`?"));
+        assert!(CargoToml::parse_strict("[package]\nname = \"ok\"\n").is_ok());
+    }
+}
@@ -0,0 +1,943 @@
+//! Semantic validation of manifests, mirroring the checks Cargo performs
+//! after parsing.
+
+use std::collections::HashMap;
+use std::fmt;
+
+use crate::msrv::edition_mismatch;
+use crate::serialize::toml_key;
+use crate::{
+    ArtifactKind, BoolOrVecOrWorkspace, BuildOverride, CargoFeature, CargoToml, DebugLevel,
+    Dependency, DeprecatedSpelling, Edition, EditionOrWorkspace, Lints, OptLevel, Package,
+    PackageProfile, Profile, Span, Spanned, StringOrBoolOrWorkspace, StringOrWorkspace,
+    VecOrWorkspace, WorkspaceRef,
+};
+
+/// How serious a [`ValidationIssue`] is.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Severity {
+    /// Cargo accepts the manifest but warns about it.
+    Warning,
+    /// Cargo rejects the manifest.
+    Error,
+}
+
+/// A problem found by [`CargoToml::validate`].
+#[derive(Debug, Clone)]
+pub struct ValidationIssue {
+    /// Whether Cargo would reject the manifest or only warn.
+    pub severity: Severity,
+    /// Dotted path of the offending key, like `profile.release.opt-level`.
+    pub key: String,
+    /// What is wrong.
+    pub message: String,
+    /// The span of the offending value, when known.
+    pub span: Option<Span>,
+}
+
+impl fmt::Display for ValidationIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let severity = match self.severity {
+            Severity::Warning => "warning",
+            Severity::Error => "error",
+        };
+        write!(f, "{severity}: `{}`: {}", self.key, self.message)
+    }
+}
+
+/// Profiles Cargo defines; every other profile must say what it `inherits`.
+const BUILT_IN_PROFILES: [&str; 5] = ["dev", "release", "test", "bench", "doc"];
+
+impl CargoToml {
+    /// Check the manifest against the rules Cargo applies after parsing.
+    ///
+    /// Catches values the types accept but Cargo does not, like
+    /// `opt-level = 7`, `codegen-units = 0`, a dependency with both `git` and
+    /// `path`, or a feature enabling a dependency that does not exist. Issues
+    /// are ordered by position in the manifest's sections, with map keys
+    /// sorted.
+    ///
+    /// This does not touch the filesystem, so targets Cargo would discover on
+    /// disk are unknown: a `default-run` that names no declared `[[bin]]` is
+    /// only a warning unless `autobins = false`.
+    ///
+    /// Underscore spellings like `dev_dependencies` are not part of the
+    /// parsed manifest; check them with [`CargoToml::spelling_issues`].
+    pub fn validate(&self) -> Vec<ValidationIssue> {
+        let mut v = Validator {
+            path_bases: self
+                .cargo_features
+                .iter()
+                .flatten()
+                .any(|f| f.value == CargoFeature::PathBases),
+            ..Validator::default()
+        };
+
+        for feature in self.cargo_features.iter().flatten() {
+            if let CargoFeature::Unknown(name) = &feature.value {
+                v.warning(
+                    "cargo-features",
+                    feature.span,
+                    format!(
+                        "unknown cargo feature `{name}`; it may need a newer nightly Cargo \
+                         than this crate knows about"
+                    ),
+                );
+            } else if let Some(release) = feature.value.stabilized_in() {
+                v.warning(
+                    "cargo-features",
+                    feature.span,
+                    format!(
+                        "the cargo feature `{}` has been stabilized in the {release} release \
+                         and is no longer necessary to be listed in the manifest",
+                        feature.value.name()
+                    ),
+                );
+            }
+        }
+        if let Some(package) = &self.package {
+            v.package(self, package);
+        }
+        v.issues.extend(self.feature_graph().dangling());
+        for (table, dependencies) in [
+            ("dependencies", &self.dependencies),
+            ("dev-dependencies", &self.dev_dependencies),
+            ("build-dependencies", &self.build_dependencies),
+        ] {
+            v.dependencies(table, dependencies.as_ref(), false);
+        }
+        for (target, spec) in sorted(self.target.as_ref()) {
+            for (table, dependencies) in [
+                ("dependencies", &spec.dependencies),
+                ("dev-dependencies", &spec.dev_dependencies),
+                ("build-dependencies", &spec.build_dependencies),
+            ] {
+                let table = format!("target.{}.{table}", toml_key(target));
+                v.dependencies(&table, dependencies.as_ref(), false);
+            }
+        }
+        if let Some(lints) = &self.lints {
+            v.lints("lints", lints, true);
+        }
+        if let Some(workspace) = &self.workspace {
+            if let Some(rust_version) = workspace
+                .package
+                .as_ref()
+                .and_then(|p| p.rust_version.as_ref())
+            {
+                v.rust_version("workspace.package.rust-version", rust_version, None);
+            }
+            v.dependencies(
+                "workspace.dependencies",
+                workspace.dependencies.as_ref(),
+                true,
+            );
+            if let Some(lints) = &workspace.lints {
+                v.lints("workspace.lints", lints, false);
+            }
+        }
+        for (name, profile) in sorted(self.profile.as_ref()) {
+            v.profile(name, profile);
+        }
+        for (registry, dependencies) in sorted(self.patch.as_ref()) {
+            let table = format!("patch.{}", toml_key(registry));
+            v.dependencies(&table, Some(dependencies), false);
+        }
+        v.dependencies("replace", self.replace.as_ref(), false);
+
+        v.issues
+    }
+
+    /// Cargo's warnings for the underscore spellings found by
+    /// [`CargoToml::deprecated_spellings`]; they are errors as of the 2024
+    /// edition.
+    ///
+    /// `root` is the workspace root manifest, used to resolve
+    /// `edition.workspace = true`; pass `None` when this manifest is the root
+    /// or is not in a workspace.
+    pub fn spelling_issues(
+        &self,
+        spellings: &[DeprecatedSpelling],
+        root: Option<&CargoToml>,
+    ) -> Vec<ValidationIssue> {
+        let mut v = Validator::default();
+        let edition = match self.package.as_ref().and_then(|p| p.edition.as_ref()) {
+            Some(EditionOrWorkspace::Edition(edition)) => Some(edition.value),
+            Some(EditionOrWorkspace::Workspace(_)) => root
+                .unwrap_or(self)
+                .workspace
+                .as_ref()
+                .and_then(|w| w.package.as_ref())
+                .and_then(|p| p.edition.as_ref())
+                .map(|e| e.value),
+            None => None,
+        };
+        for spelling in spellings {
+            let (written, preferred) = (&spelling.written, &spelling.preferred);
+            if edition == Some(Edition::E2024) {
+                v.error(
+                    &spelling.key,
+                    spelling.span,
+                    format!("`{written}` is unsupported as of the 2024 edition; instead use `{preferred}`"),
+                );
+            } else {
+                v.warning(
+                    &spelling.key,
+                    spelling.span,
+                    format!(
+                        "`{written}` is deprecated in favor of `{preferred}` and will not work \
+                         in the 2024 edition"
+                    ),
+                );
+            }
+        }
+        v.issues
+    }
+}
+
+#[derive(Default)]
+struct Validator {
+    issues: Vec<ValidationIssue>,
+    /// Whether `cargo-features` enables `base` for path dependencies.
+    path_bases: bool,
+}
+
+impl Validator {
+    fn error(&mut self, key: impl Into<String>, span: Option<Span>, message: impl Into<String>) {
+        self.push(Severity::Error, key.into(), span, message.into());
+    }
+
+    fn warning(&mut self, key: impl Into<String>, span: Option<Span>, message: impl Into<String>) {
+        self.push(Severity::Warning, key.into(), span, message.into());
+    }
+
+    fn push(&mut self, severity: Severity, key: String, span: Option<Span>, message: String) {
+        self.issues.push(ValidationIssue {
+            severity,
+            key,
+            message,
+            span,
+        });
+    }
+
+    fn package(&mut self, manifest: &CargoToml, package: &Package) {
+        match &package.name {
+            None => self.error("package.name", None, "missing package name"),
+            Some(name) => {
+                if let Some(reason) = invalid_package_name(&name.value) {
+                    self.error("package.name", name.span, reason);
+                }
+            }
+        }
+        if let Some(StringOrWorkspace::String(version)) = &package.version
+            && let Err(e) = version.to_version()
+        {
+            self.error("package.version", version.span, e.to_string());
+        }
+        if let Some(StringOrWorkspace::String(rust_version)) = &package.rust_version {
+            let edition = match &package.edition {
+                Some(EditionOrWorkspace::Edition(edition)) => Some(edition.value),
+                _ => None,
+            };
+            self.rust_version("package.rust-version", rust_version, edition);
+        }
+
+        let refs = [
+            ("version", package.version.as_ref().and_then(string_ref)),
+            ("authors", package.authors.as_ref().and_then(vec_ref)),
+            ("edition", package.edition.as_ref().and_then(edition_ref)),
+            (
+                "rust-version",
+                package.rust_version.as_ref().and_then(string_ref),
+            ),
+            (
+                "description",
+                package.description.as_ref().and_then(string_ref),
+            ),
+            (
+                "documentation",
+                package.documentation.as_ref().and_then(string_ref),
+            ),
+            ("readme", package.readme.as_ref().and_then(readme_ref)),
+            ("homepage", package.homepage.as_ref().and_then(string_ref)),
+            (
+                "repository",
+                package.repository.as_ref().and_then(string_ref),
+            ),
+            ("license", package.license.as_ref().and_then(string_ref)),
+            (
+                "license-file",
+                package.license_file.as_ref().and_then(string_ref),
+            ),
+            ("keywords", package.keywords.as_ref().and_then(vec_ref)),
+            ("categories", package.categories.as_ref().and_then(vec_ref)),
+            ("exclude", package.exclude.as_ref().and_then(vec_ref)),
+            ("include", package.include.as_ref().and_then(vec_ref)),
+            ("publish", package.publish.as_ref().and_then(publish_ref)),
+        ];
+        for (key, workspace) in refs {
+            if let Some(workspace) = workspace {
+                self.workspace_ref(&format!("package.{key}"), workspace);
+            }
+        }
+
+        if let Some(default_run) = &package.default_run {
+            // Without `autobins`, `src/main.rs` is not a target either.
+            let autobins = package.autobins.as_ref().is_none_or(|a| a.value);
+            let declared = manifest
+                .bin
+                .iter()
+                .flatten()
+                .filter_map(|bin| bin.name.as_ref())
+                .chain(package.name.as_ref().filter(|_| autobins))
+                .any(|name| name.value == default_run.value);
+            if !declared {
+                let message = format!("`{}` is not the name of a binary target", default_run.value);
+                if !autobins {
+                    self.error("package.default-run", default_run.span, message);
+                } else {
+                    self.warning(
+                        "package.default-run",
+                        default_run.span,
+                        format!("{message} declared in the manifest"),
+                    );
+                }
+            }
+        }
+    }
+
+    fn rust_version(
+        &mut self,
+        key: &str,
+        rust_version: &Spanned<String>,
+        edition: Option<Edition>,
+    ) {
+        match rust_version.to_rust_version() {
+            Err(e) => self.error(key, rust_version.span, e.to_string()),
+            Ok(version) => {
+                if let Some(edition) = edition
+                    && version < edition.first_rust_version()
+                {
+                    self.error(key, rust_version.span, edition_mismatch(version, edition));
+                }
+            }
+        }
+    }
+
+    fn workspace_ref(&mut self, key: &str, workspace: &WorkspaceRef) {
+        if !workspace.workspace.value {
+            self.error(key, workspace.workspace.span, "`workspace` cannot be false");
+        }
+    }
+
+    fn dependencies(
+        &mut self,
+        table: &str,
+        dependencies: Option<&HashMap<String, Dependency>>,
+        in_workspace: bool,
+    ) {
+        for (name, dependency) in sorted(dependencies) {
+            let key = format!("{table}.{}", toml_key(name));
+            self.dependency(&key, name, dependency, in_workspace);
+        }
+    }
+
+    fn dependency(&mut self, key: &str, name: &str, dependency: &Dependency, in_workspace: bool) {
+        let detail = match dependency {
+            Dependency::Version(version) => {
+                self.version_req(key, version);
+                return;
+            }
+            Dependency::Workspace(inherited) => {
+                if in_workspace {
+                    self.error(
+                        key,
+                        inherited.workspace.span,
+                        "workspace dependencies cannot themselves be inherited",
+                    );
+                } else if !inherited.workspace.value {
+                    self.error(key, inherited.workspace.span, "`workspace` cannot be false");
+                }
+                return;
+            }
+            Dependency::Detailed(detail) => detail,
+        };
+
+        if let Some(version) = &detail.version {
+            self.version_req(&format!("{key}.version"), version);
+        }
+        if let (Some(_), Some(path)) = (&detail.git, &detail.path) {
+            self.error(
+                format!("{key}.path"),
+                path.span,
+                format!("dependency `{name}` specifies both `git` and `path`; only one is allowed"),
+            );
+        }
+        if let (Some(_), Some(index)) = (&detail.registry, &detail.registry_index) {
+            self.error(
+                format!("{key}.registry-index"),
+                index.span,
+                format!(
+                    "dependency `{name}` specifies both `registry` and `registry-index`; only one is allowed"
+                ),
+            );
+        }
+
+        let references = [
+            ("branch", &detail.branch),
+            ("tag", &detail.tag),
+            ("rev", &detail.rev),
+        ];
+        let mut given = references
+            .iter()
+            .filter_map(|(field, value)| Some((*field, value.as_ref()?)));
+        if detail.git.is_none() {
+            for (field, value) in given {
+                self.error(
+                    format!("{key}.{field}"),
+                    value.span,
+                    format!("`{field}` is ignored for dependency `{name}` without `git`"),
+                );
+            }
+        } else if let (Some(_), Some((field, value))) = (given.next(), given.next()) {
+            self.error(
+                format!("{key}.{field}"),
+                value.span,
+                format!("dependency `{name}` specifies more than one of `branch`, `tag` and `rev`"),
+            );
+        }
+
+        for value in detail.artifact.iter().flat_map(|a| a.values()) {
+            if let Err(e) = ArtifactKind::parse(&value.value) {
+                self.error(format!("{key}.artifact"), value.span, e.to_string());
+            }
+        }
+        if detail.artifact.is_none() {
+            for (field, value) in [
+                ("lib", detail.lib.as_ref().map(|v| v.span)),
+                ("target", detail.target.as_ref().map(|v| v.span)),
+            ] {
+                if let Some(span) = value {
+                    self.error(
+                        format!("{key}.{field}"),
+                        span,
+                        format!(
+                            "`{field}` is only allowed with `artifact` for dependency `{name}`"
+                        ),
+                    );
+                }
+            }
+        }
+        if let Some(base) = &detail.base {
+            if detail.path.is_none() {
+                self.error(
+                    format!("{key}.base"),
+                    base.span,
+                    format!("`base` is only allowed with `path` for dependency `{name}`"),
+                );
+            } else if !self.path_bases {
+                self.error(
+                    format!("{key}.base"),
+                    base.span,
+                    "`base` requires `cargo-features = [\"path-bases\"]`",
+                );
+            }
+        }
+
+        if detail.version.is_none() && detail.path.is_none() && detail.git.is_none() {
+            self.warning(
+                key,
+                None,
+                format!(
+                    "dependency `{name}` is specified without a version, path, Git repository or workspace dependency"
+                ),
+            );
+        }
+    }
+
+    fn version_req(&mut self, key: &str, version: &Spanned<String>) {
+        if let Err(e) = version.to_version_req() {
+            self.error(key, version.span, e.to_string());
+        }
+    }
+
+    fn lints(&mut self, key: &str, lints: &Lints, in_package: bool) {
+        let Some(workspace) = &lints.workspace else {
+            return;
+        };
+        if !in_package {
+            self.error(
+                format!("{key}.workspace"),
+                workspace.span,
+                "`workspace` is not a lint tool",
+            );
+        } else if !workspace.value {
+            self.error(
+                format!("{key}.workspace"),
+                workspace.span,
+                "`workspace` cannot be false",
+            );
+        } else if lints.rust.is_some() || lints.clippy.is_some() || lints.rustdoc.is_some() {
+            self.error(
+                format!("{key}.workspace"),
+                workspace.span,
+                "cannot override `workspace.lints` in `lints`; remove the overrides or set them all in this manifest",
+            );
+        }
+    }
+
+    fn profile(&mut self, name: &str, profile: &Profile) {
+        let key = format!("profile.{}", toml_key(name));
+        let built_in = BUILT_IN_PROFILES.contains(&name);
+        match &profile.inherits {
+            Some(inherits) if matches!(name, "dev" | "release") => self.error(
+                format!("{key}.inherits"),
+                inherits.span,
+                format!("`inherits` must not be specified in root profile `{name}`"),
+            ),
+            None if !built_in => self.error(
+                &key,
+                None,
+                format!("profile `{name}` is missing an `inherits` directive"),
+            ),
+            _ => {}
+        }
+
+        self.opt_level(&key, profile.opt_level.as_ref());
+        self.debug(&key, profile.debug.as_ref());
+        self.codegen_units(&key, profile.codegen_units.as_ref());
+        if let Some(split) = &profile.split_debuginfo
+            && !matches!(split.value.as_str(), "off" | "packed" | "unpacked")
+        {
+            self.error(
+                format!("{key}.split-debuginfo"),
+                split.span,
+                format!(
+                    "invalid value `{}`: expected `off`, `packed` or `unpacked`",
+                    split.value
+                ),
+            );
+        }
+
+        for (package, overrides) in sorted(profile.package.as_ref()) {
+            self.package_profile(&format!("{key}.package.{}", toml_key(package)), overrides);
+        }
+        if let Some(build) = &profile.build_override {
+            self.build_override(&format!("{key}.build-override"), build);
+        }
+    }
+
+    fn package_profile(&mut self, key: &str, profile: &PackageProfile) {
+        self.opt_level(key, profile.opt_level.as_ref());
+        self.debug(key, profile.debug.as_ref());
+        self.codegen_units(key, profile.codegen_units.as_ref());
+    }
+
+    fn build_override(&mut self, key: &str, profile: &BuildOverride) {
+        self.opt_level(key, profile.opt_level.as_ref());
+        self.debug(key, profile.debug.as_ref());
+        self.codegen_units(key, profile.codegen_units.as_ref());
+    }
+
+    fn opt_level(&mut self, profile: &str, opt_level: Option<&OptLevel>) {
+        if let Some(OptLevel::Number(n)) = opt_level
+            && n.value > 3
+        {
+            self.error(
+                format!("{profile}.opt-level"),
+                n.span,
+                format!(
+                    "invalid value {}: expected `0`, `1`, `2`, `3`, `\"s\"` or `\"z\"`",
+                    n.value
+                ),
+            );
+        }
+    }
+
+    fn debug(&mut self, profile: &str, debug: Option<&DebugLevel>) {
+        if let Some(DebugLevel::Number(n)) = debug
+            && n.value > 2
+        {
+            self.error(
+                format!("{profile}.debug"),
+                n.span,
+                format!(
+                    "invalid value {}: expected a boolean, `0`, `1`, `2`, `\"none\"`, `\"line-directives-only\"`, `\"line-tables-only\"`, `\"limited\"` or `\"full\"`",
+                    n.value
+                ),
+            );
+        }
+    }
+
+    fn codegen_units(&mut self, profile: &str, codegen_units: Option<&Spanned<u32>>) {
+        if let Some(units) = codegen_units
+            && units.value == 0
+        {
+            self.error(
+                format!("{profile}.codegen-units"),
+                units.span,
+                "`codegen-units` must be greater than 0",
+            );
+        }
+    }
+}
+
+/// Cargo's rules for package names, or `None` if `name` is valid.
+fn invalid_package_name(name: &str) -> Option<String> {
+    let mut chars = name.chars();
+    match chars.next() {
+        None => Some("package name cannot be empty".to_owned()),
+        Some(c) if c.is_ascii_digit() => Some(format!(
+            "invalid package name `{name}`: the name cannot start with a digit"
+        )),
+        Some(c) if !c.is_alphabetic() && c != '_' => Some(format!(
+            "invalid package name `{name}`: the first character must be a letter or `_`"
+        )),
+        Some(_) => chars
+            .find(|c| !c.is_alphanumeric() && *c != '-' && *c != '_')
+            .map(|c| {
+                format!(
+                    "invalid character `{c}` in package name `{name}`: only letters, numbers, `-` and `_` are allowed"
+                )
+            }),
+    }
+}
+
+fn string_ref(value: &StringOrWorkspace) -> Option<&WorkspaceRef> {
+    match value {
+        StringOrWorkspace::Workspace(r) => Some(r),
+        StringOrWorkspace::String(_) => None,
+    }
+}
+
+fn vec_ref(value: &VecOrWorkspace) -> Option<&WorkspaceRef> {
+    match value {
+        VecOrWorkspace::Workspace(r) => Some(r),
+        VecOrWorkspace::Values(_) => None,
+    }
+}
+
+fn edition_ref(value: &EditionOrWorkspace) -> Option<&WorkspaceRef> {
+    match value {
+        EditionOrWorkspace::Workspace(r) => Some(r),
+        EditionOrWorkspace::Edition(_) => None,
+    }
+}
+
+fn readme_ref(value: &StringOrBoolOrWorkspace) -> Option<&WorkspaceRef> {
+    match value {
+        StringOrBoolOrWorkspace::Workspace(r) => Some(r),
+        _ => None,
+    }
+}
+
+fn publish_ref(value: &BoolOrVecOrWorkspace) -> Option<&WorkspaceRef> {
+    match value {
+        BoolOrVecOrWorkspace::Workspace(r) => Some(r),
+        _ => None,
+    }
+}
+
+/// The entries of an optional map, sorted by key.
+fn sorted<V>(map: Option<&HashMap<String, V>>) -> Vec<(&String, &V)> {
+    let mut entries: Vec<_> = map.into_iter().flatten().collect();
+    entries.sort_by_key(|(k, _)| *k);
+    entries
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Validate `source` and compare each issue's severity, key and the text
+    /// its span covers.
+    #[track_caller]
+    fn check(source: &str, expected: &[(Severity, &str, Option<&str>)]) {
+        let manifest = CargoToml::parse(source).unwrap();
+        let issues = manifest.validate();
+        let found: Vec<_> = issues
+            .iter()
+            .map(|i| {
+                let span = i
+                    .span
+                    .map(|s| &source[s.offset as usize..][..s.len as usize]);
+                (i.severity, i.key.as_str(), span)
+            })
+            .collect();
+        assert_eq!(found, expected);
+    }
+
+    #[test]
+    fn cargo_features() {
+        check(
+            "cargo-features = [\"trim-paths\", \"edition2024\", \"edition2077\"]\n",
+            &[
+                (Severity::Warning, "cargo-features", Some("\"edition2024\"")),
+                (Severity::Warning, "cargo-features", Some("\"edition2077\"")),
+            ],
+        );
+
+        let manifest = CargoToml::parse("cargo-features = [\"edition2077\"]\n").unwrap();
+        let features: Vec<_> = manifest.unstable_features().map(|f| &f.value).collect();
+        assert_eq!(features, [&CargoFeature::Unknown("edition2077".to_owned())]);
+        assert!(manifest.requires_nightly());
+    }
+
+    #[test]
+    fn deprecated_spellings() {
+        let source = r#"
+[dependencies]
+stray = { version = "1", default_features = false }
+"#;
+        let root = CargoToml::parse("[workspace]\npackage.edition = \"2024\"\n").unwrap();
+        let spelling_issues = |source: &str| {
+            let manifest = CargoToml::parse(source).unwrap();
+            let spellings = CargoToml::deprecated_spellings(source).unwrap();
+            let issues = manifest.spelling_issues(&spellings, Some(&root));
+            issues
+                .iter()
+                .map(|i| {
+                    let span = i.span.unwrap();
+                    let text = &source[span.offset as usize..][..span.len as usize];
+                    (i.severity, i.key.clone(), text.to_owned())
+                })
+                .collect::<Vec<_>>()
+        };
+        let expected = |severity| {
+            vec![(
+                severity,
+                "dependencies.stray.default_features".to_owned(),
+                "default_features".to_owned(),
+            )]
+        };
+        check(source, &[]);
+        assert_eq!(spelling_issues(source), expected(Severity::Warning));
+        let edition = format!("[package]\nname = \"demo\"\nedition = \"2024\"\n{source}");
+        assert_eq!(spelling_issues(&edition), expected(Severity::Error));
+        let inherited = format!("[package]\nname = \"demo\"\nedition.workspace = true\n{source}");
+        assert_eq!(spelling_issues(&inherited), expected(Severity::Error));
+    }
+
+    #[test]
+    fn package_fields() {
+        check(
+            r#"
+[package]
+name = "demo"
+version = "0.1.0"
+edition = "2021"
+rust-version = "1.50"
+description = { workspace = false }
+"#,
+            &[
+                (Severity::Error, "package.rust-version", Some("\"1.50\"")),
+                (Severity::Error, "package.description", Some("false")),
+            ],
+        );
+        check(
+            "[package]\nversion = \"1\"\n",
+            &[
+                (Severity::Error, "package.name", None),
+                (Severity::Error, "package.version", Some("\"1\"")),
+            ],
+        );
+    }
+
+    #[test]
+    fn default_run() {
+        let source = "[package]\nname = \"demo\"\ndefault-run = \"missing\"\n";
+        check(
+            source,
+            &[(
+                Severity::Warning,
+                "package.default-run",
+                Some("\"missing\""),
+            )],
+        );
+        check(
+            &format!("{source}autobins = false\n"),
+            &[(Severity::Error, "package.default-run", Some("\"missing\""))],
+        );
+        check("[package]\nname = \"demo\"\ndefault-run = \"demo\"\n", &[]);
+        check(
+            "[package]\nname = \"demo\"\ndefault-run = \"demo\"\nautobins = false\n",
+            &[(Severity::Error, "package.default-run", Some("\"demo\""))],
+        );
+    }
+
+    #[test]
+    fn dependency_sources() {
+        check(
+            r#"
+[dependencies]
+both = { git = "https://example.com/both", path = "../both" }
+registries = { version = "1", registry = "a", registry-index = "https://example.com/index" }
+nothing = { package = "something" }
+"#,
+            &[
+                (
+                    Severity::Error,
+                    "dependencies.both.path",
+                    Some("\"../both\""),
+                ),
+                (Severity::Warning, "dependencies.nothing", None),
+                (
+                    Severity::Error,
+                    "dependencies.registries.registry-index",
+                    Some("\"https://example.com/index\""),
+                ),
+            ],
+        );
+    }
+
+    #[test]
+    fn git_references() {
+        check(
+            r#"
+[dependencies]
+both = { git = "https://example.com/both", branch = "main", tag = "v1" }
+pinned = { git = "https://example.com/pinned", rev = "abc123" }
+stray = { version = "1", rev = "abc123" }
+"#,
+            &[
+                (Severity::Error, "dependencies.both.tag", Some("\"v1\"")),
+                (
+                    Severity::Error,
+                    "dependencies.stray.rev",
+                    Some("\"abc123\""),
+                ),
+            ],
+        );
+    }
+
+    #[test]
+    fn artifact_dependencies() {
+        check(
+            r#"
+[dependencies]
+shared = { version = "1", artifact = "so", target = "x86_64-unknown-linux-gnu" }
+plain = { version = "1", lib = true }
+bin = { version = "1", artifact = "bin", lib = true }
+"#,
+            &[
+                (Severity::Error, "dependencies.plain.lib", Some("true")),
+                (
+                    Severity::Error,
+                    "dependencies.shared.artifact",
+                    Some("\"so\""),
+                ),
+            ],
+        );
+    }
+
+    #[test]
+    fn path_bases() {
+        let source = r#"
+[dependencies]
+shared = { path = "../shared", base = "libs" }
+versioned = { version = "1", base = "libs" }
+"#;
+        check(
+            source,
+            &[
+                (
+                    Severity::Error,
+                    "dependencies.shared.base",
+                    Some("\"libs\""),
+                ),
+                (
+                    Severity::Error,
+                    "dependencies.versioned.base",
+                    Some("\"libs\""),
+                ),
+            ],
+        );
+        check(
+            &format!("cargo-features = [\"path-bases\"]\n{source}"),
+            &[(
+                Severity::Error,
+                "dependencies.versioned.base",
+                Some("\"libs\""),
+            )],
+        );
+    }
+
+    #[test]
+    fn workspace_inheritance() {
+        check(
+            r#"
+[dependencies]
+serde = { workspace = false }
+
+[workspace.dependencies]
+log = { workspace = true }
+"#,
+            &[
+                (Severity::Error, "dependencies.serde", Some("false")),
+                (Severity::Error, "workspace.dependencies.log", Some("true")),
+            ],
+        );
+    }
+
+    #[test]
+    fn lints() {
+        check(
+            r#"
+[lints]
+workspace = true
+
+[lints.rust]
+unsafe_code = "forbid"
+
+[workspace.lints]
+workspace = true
+"#,
+            &[
+                (Severity::Error, "lints.workspace", Some("true")),
+                (Severity::Error, "workspace.lints.workspace", Some("true")),
+            ],
+        );
+    }
+
+    #[test]
+    fn profiles() {
+        check(
+            r#"
+[profile.release]
+opt-level = 7
+debug = 5
+codegen-units = 0
+split-debuginfo = "split"
+
+[profile.release.package.foo]
+opt-level = 4
+
+[profile.dev]
+inherits = "release"
+
+[profile.custom]
+opt-level = "s"
+
+[profile.fast]
+inherits = "release"
+"#,
+            &[
+                (Severity::Error, "profile.custom", None),
+                (Severity::Error, "profile.dev.inherits", Some("\"release\"")),
+                (Severity::Error, "profile.release.opt-level", Some("7")),
+                (Severity::Error, "profile.release.debug", Some("5")),
+                (Severity::Error, "profile.release.codegen-units", Some("0")),
+                (
+                    Severity::Error,
+                    "profile.release.split-debuginfo",
+                    Some("\"split\""),
+                ),
+                (
+                    Severity::Error,
+                    "profile.release.package.foo.opt-level",
+                    Some("4"),
+                ),
+            ],
+        );
+    }
+}
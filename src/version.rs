@@ -0,0 +1,181 @@
+//! Semver versions and Cargo version requirements.
+//!
+//! Manifest and lockfile fields keep the version strings exactly as written,
+//! as `Spanned<String>` or `String`, rather than as [`Version`] and
+//! [`VersionReq`]. This is deliberate: `semver` normalizes what it parses
+//! (`^1.2` and `1.2` print the same), so typed fields could not be written
+//! back unchanged, and a bad version would fail the whole manifest instead
+//! of being reported at its span. The methods here parse them on demand with
+//! Cargo's rules.
+
+use semver::{Version, VersionReq};
+
+use crate::{
+    Dependency, DependencyDetail, Error, LockPackage, Package, Spanned, StringOrWorkspace,
+    WorkspacePackage,
+};
+
+impl Spanned<String> {
+    /// Parse this value as a semver version, like `1.2.3` or `0.1.0-alpha.1`.
+    pub fn to_version(&self) -> Result<Version, Error> {
+        Version::parse(&self.value).map_err(|e| Error::InvalidVersion {
+            value: self.value.clone(),
+            reason: e.to_string(),
+            span: self.span,
+        })
+    }
+
+    /// Parse this value as a Cargo version requirement.
+    ///
+    /// A bare version is a caret requirement (`1.2` means `^1.2`); `=`, `~`,
+    /// `>`, `>=`, `<`, `<=`, wildcards (`1.*`) and comma-separated ranges
+    /// (`>=1.2, <1.5`) are also accepted.
+    pub fn to_version_req(&self) -> Result<VersionReq, Error> {
+        VersionReq::parse(&self.value).map_err(|e| Error::InvalidVersionReq {
+            value: self.value.clone(),
+            reason: e.to_string(),
+            span: self.span,
+        })
+    }
+}
+
+impl Package {
+    /// The package version as a semver version.
+    ///
+    /// Returns `None` when the version is unset or inherited from the
+    /// workspace; use [`crate::CargoToml::resolve`] to resolve inherited keys.
+    pub fn semver_version(&self) -> Result<Option<Version>, Error> {
+        match &self.version {
+            Some(StringOrWorkspace::String(version)) => version.to_version().map(Some),
+            _ => Ok(None),
+        }
+    }
+}
+
+impl WorkspacePackage {
+    /// The `[workspace.package]` version as a semver version.
+    pub fn semver_version(&self) -> Result<Option<Version>, Error> {
+        self.version.as_ref().map(Spanned::to_version).transpose()
+    }
+}
+
+impl Dependency {
+    /// The version requirement of this dependency.
+    ///
+    /// Returns `None` for dependencies without a `version`, such as plain path
+    /// or git dependencies, and for dependencies inherited from the workspace.
+    pub fn version_req(&self) -> Result<Option<VersionReq>, Error> {
+        match self {
+            Dependency::Version(version) => version.to_version_req().map(Some),
+            Dependency::Detailed(detail) => detail.version_req(),
+            Dependency::Workspace(_) => Ok(None),
+        }
+    }
+
+    /// Whether the locked `package` satisfies this dependency's version
+    /// requirement.
+    ///
+    /// A dependency without a requirement matches any version. Only the
+    /// version is checked, not the package name or source. Pre-releases only
+    /// match requirements that mention a pre-release of the same version, as
+    /// in Cargo.
+    pub fn matches(&self, package: &LockPackage) -> Result<bool, Error> {
+        match self.version_req()? {
+            Some(req) => Ok(req.matches(&package.semver_version()?)),
+            None => Ok(true),
+        }
+    }
+}
+
+impl DependencyDetail {
+    /// The version requirement of this dependency, if it has one.
+    pub fn version_req(&self) -> Result<Option<VersionReq>, Error> {
+        self.version
+            .as_ref()
+            .map(Spanned::to_version_req)
+            .transpose()
+    }
+}
+
+impl LockPackage {
+    /// The locked version as a semver version.
+    pub fn semver_version(&self) -> Result<Version, Error> {
+        Version::parse(&self.version).map_err(|e| Error::InvalidVersion {
+            value: self.version.clone(),
+            reason: e.to_string(),
+            span: None,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{CargoLock, CargoToml};
+
+    #[test]
+    fn version_requirements() {
+        let manifest = CargoToml::parse(
+            r#"
+[package]
+name = "demo"
+version = "0.3.1"
+
+[dependencies]
+caret = "1.2"
+exact = { version = "=1.2.3" }
+tilde = "~1.2"
+wildcard = "1.*"
+range = ">=1.2, <1.5"
+path-only = { path = "../path-only" }
+"#,
+        )
+        .unwrap();
+        let lock = CargoLock::parse(
+            r#"
+version = 4
+
+[[package]]
+name = "dep"
+version = "1.4.0"
+"#,
+        )
+        .unwrap();
+
+        let package = manifest.package.as_ref().unwrap();
+        assert_eq!(
+            package.semver_version().unwrap().unwrap().to_string(),
+            "0.3.1"
+        );
+
+        let dependencies = manifest.dependencies.as_ref().unwrap();
+        let locked = &lock.packages[0];
+        let matches = |name: &str| dependencies[name].matches(locked).unwrap();
+        assert!(matches("caret"));
+        assert!(!matches("exact"));
+        assert!(!matches("tilde"));
+        assert!(matches("wildcard"));
+        assert!(matches("range"));
+        assert!(matches("path-only"));
+    }
+
+    #[test]
+    fn invalid_version_requirement() {
+        let source = r#"
+[dependencies]
+serde = "1.0.0.0"
+"#;
+        let manifest = CargoToml::parse(source).unwrap();
+        let err = manifest.dependencies.unwrap()["serde"]
+            .version_req()
+            .unwrap_err();
+        let crate::Error::InvalidVersionReq { value, span, .. } = &err else {
+            panic!("unexpected error: {err}");
+        };
+        assert_eq!(value, "1.0.0.0");
+        let span = span.unwrap();
+        assert_eq!(
+            &source[span.offset as usize..][..span.len as usize],
+            "\"1.0.0.0\""
+        );
+    }
+}
@@ -0,0 +1,530 @@
+//! Workspace discovery and member enumeration.
+
+use std::collections::{BTreeMap, HashMap};
+
+use camino::{Utf8Component, Utf8Path, Utf8PathBuf};
+
+use crate::lockcheck::dependency_span;
+use crate::{
+    CargoToml, Dependency, DependencyDetail, Error, Severity, StringOrWorkspace, ValidationIssue,
+};
+
+/// A Cargo workspace loaded from disk: the root manifest and every member.
+///
+/// A package that is not part of any workspace is loaded as a workspace of one,
+/// like Cargo does.
+#[derive(Debug, Clone)]
+pub struct CargoWorkspace {
+    /// Path to the root `Cargo.toml`.
+    pub root_manifest: Utf8PathBuf,
+    /// The parsed root manifest.
+    pub root: CargoToml,
+    /// Every member, keyed by the path to its `Cargo.toml`.
+    ///
+    /// Includes the root package when the root manifest has a `[package]`.
+    pub members: BTreeMap<Utf8PathBuf, CargoToml>,
+}
+
+impl CargoWorkspace {
+    /// Discover and load the workspace containing `path`.
+    ///
+    /// `path` may be a `Cargo.toml`, any other file or any directory; the
+    /// nearest manifest at or above it is used as the starting point. From
+    /// there, `package.workspace` is honoured, otherwise parent directories are
+    /// searched for a manifest with a `[workspace]` table that does not exclude
+    /// the package.
+    pub fn discover(path: impl AsRef<Utf8Path>) -> Result<Self, Error> {
+        let start = find_manifest(&absolute(path.as_ref())?)?;
+        let manifest = CargoToml::from_path(&start)?;
+        let root_manifest = find_root(&start, &manifest)?;
+        if root_manifest == start {
+            return Self::load(root_manifest, manifest);
+        }
+
+        let workspace = Self::from_root(&root_manifest)?;
+        if !workspace.members.contains_key(&start) {
+            return Err(Error::NotAWorkspaceMember {
+                member: start,
+                root: root_manifest,
+            });
+        }
+        Ok(workspace)
+    }
+
+    /// Load the workspace whose root manifest is at `root_manifest`.
+    pub fn from_root(root_manifest: impl AsRef<Utf8Path>) -> Result<Self, Error> {
+        let root_manifest = absolute(root_manifest.as_ref())?;
+        let root = CargoToml::from_path(&root_manifest)?;
+        Self::load(root_manifest, root)
+    }
+
+    fn load(root_manifest: Utf8PathBuf, root: CargoToml) -> Result<Self, Error> {
+        let root_dir = parent_dir(&root_manifest);
+        let mut pending = Vec::new();
+
+        if root.package.is_some() {
+            pending.push(root_manifest.clone());
+        }
+        if let Some(workspace) = &root.workspace {
+            for dir in expand_paths(&root_dir, workspace.members.as_ref().map(|m| &m.value))? {
+                if !is_excluded(&root, &root_dir, &dir) {
+                    pending.push(dir.join("Cargo.toml"));
+                }
+            }
+        }
+
+        let mut members = BTreeMap::new();
+        while let Some(manifest_path) = pending.pop() {
+            if members.contains_key(&manifest_path) {
+                continue;
+            }
+            let manifest = if manifest_path == root_manifest {
+                root.clone()
+            } else {
+                CargoToml::from_path(&manifest_path)?
+            };
+
+            // Path dependencies inside the workspace directory are implicit members.
+            let member_dir = parent_dir(&manifest_path);
+            for dir in path_dependencies(&manifest, &member_dir, &root_dir, &root) {
+                if dir.starts_with(&root_dir) && !is_excluded(&root, &root_dir, &dir) {
+                    let path = dir.join("Cargo.toml");
+                    if path.is_file() {
+                        pending.push(path);
+                    }
+                }
+            }
+            members.insert(manifest_path, manifest);
+        }
+
+        Ok(CargoWorkspace {
+            root_manifest,
+            root,
+            members,
+        })
+    }
+
+    /// Path to the workspace root directory.
+    pub fn root_dir(&self) -> &Utf8Path {
+        self.root_manifest.parent().unwrap_or(&self.root_manifest)
+    }
+
+    /// The members Cargo operates on when no package is selected.
+    ///
+    /// This is `workspace.default-members` if set, otherwise the root package
+    /// for a non-virtual workspace, otherwise every member.
+    ///
+    /// Fails if a directory matched by a `default-members` glob cannot be
+    /// read.
+    pub fn default_members(
+        &self,
+    ) -> Result<impl Iterator<Item = (&Utf8PathBuf, &CargoToml)>, Error> {
+        let root_dir = self.root_dir();
+        let defaults = self
+            .root
+            .workspace
+            .as_ref()
+            .and_then(|w| w.default_members.as_ref())
+            .map(|d| expand_paths(root_dir, Some(&d.value)))
+            .transpose()?;
+        let root_only = defaults.is_none() && self.root.package.is_some();
+
+        Ok(self.members.iter().filter(move |(path, _)| {
+            if let Some(defaults) = &defaults {
+                defaults.iter().any(|d| parent_dir(path) == *d)
+            } else if root_only {
+                **path == self.root_manifest
+            } else {
+                true
+            }
+        }))
+    }
+
+    /// Warnings for `[patch]`, `[replace]` and `[profile]` tables in members
+    /// other than the root, which Cargo ignores: they only take effect in the
+    /// workspace root manifest.
+    pub fn ignored_member_settings(&self) -> Vec<(&Utf8PathBuf, ValidationIssue)> {
+        let mut issues = Vec::new();
+        for (path, manifest) in &self.members {
+            if *path == self.root_manifest {
+                continue;
+            }
+            let first_span = |tables: Vec<&HashMap<String, Dependency>>| {
+                let mut dependencies: Vec<_> = tables.into_iter().flatten().collect();
+                dependencies.sort_by_key(|(name, _)| *name);
+                dependencies.first().and_then(|(_, d)| dependency_span(d))
+            };
+            let ignored = [
+                (
+                    "patch",
+                    manifest
+                        .patch
+                        .as_ref()
+                        .map(|p| first_span(p.values().collect())),
+                ),
+                (
+                    "replace",
+                    manifest.replace.as_ref().map(|r| first_span(vec![r])),
+                ),
+                ("profile", manifest.profile.as_ref().map(|_| None)),
+            ];
+            for (table, span) in ignored {
+                let Some(span) = span else {
+                    continue;
+                };
+                let issue = ValidationIssue {
+                    severity: Severity::Warning,
+                    key: table.to_owned(),
+                    message: format!(
+                        "`[{table}]` is ignored in a workspace member; \
+                         specify it in the workspace root manifest"
+                    ),
+                    span,
+                };
+                issues.push((path, issue));
+            }
+        }
+        issues
+    }
+}
+
+/// Walk up from `path` to the nearest `Cargo.toml`; any other file is
+/// searched from its directory.
+fn find_manifest(path: &Utf8Path) -> Result<Utf8PathBuf, Error> {
+    if path.is_file() && path.file_name() == Some("Cargo.toml") {
+        return Ok(path.to_owned());
+    }
+    let dir = if path.is_file() {
+        parent_dir(path)
+    } else {
+        path.to_owned()
+    };
+    dir.ancestors()
+        .map(|dir| dir.join("Cargo.toml"))
+        .find(|candidate| candidate.is_file())
+        .ok_or_else(|| Error::ManifestNotFound {
+            path: path.to_owned(),
+        })
+}
+
+/// Find the root manifest of the workspace `manifest` (at `path`) belongs to.
+fn find_root(path: &Utf8Path, manifest: &CargoToml) -> Result<Utf8PathBuf, Error> {
+    if manifest.workspace.is_some() {
+        return Ok(path.to_owned());
+    }
+
+    let dir = parent_dir(path);
+    if let Some(StringOrWorkspace::String(root)) =
+        manifest.package.as_ref().and_then(|p| p.workspace.as_ref())
+    {
+        let candidate = normalize(&dir.join(&root.value).join("Cargo.toml"));
+        if CargoToml::from_path(&candidate)?.workspace.is_none() {
+            return Err(Error::NotAWorkspaceRoot {
+                path: candidate,
+                member: path.to_owned(),
+            });
+        }
+        return Ok(candidate);
+    }
+
+    for ancestor in dir.ancestors().skip(1) {
+        let candidate = ancestor.join("Cargo.toml");
+        if !candidate.is_file() {
+            continue;
+        }
+        let root = CargoToml::from_path(&candidate)?;
+        if root.workspace.is_some() && !is_excluded(&root, ancestor, &dir) {
+            return Ok(candidate);
+        }
+    }
+
+    Ok(path.to_owned())
+}
+
+/// Whether `dir` falls under one of the root's `workspace.exclude` paths.
+fn is_excluded(root: &CargoToml, root_dir: &Utf8Path, dir: &Utf8Path) -> bool {
+    root.workspace
+        .as_ref()
+        .and_then(|w| w.exclude.as_ref())
+        .is_some_and(|exclude| {
+            exclude
+                .value
+                .iter()
+                .any(|e| dir.starts_with(normalize(&root_dir.join(e))))
+        })
+}
+
+/// Directories of every path dependency declared by `manifest`.
+fn path_dependencies(
+    manifest: &CargoToml,
+    member_dir: &Utf8Path,
+    root_dir: &Utf8Path,
+    root: &CargoToml,
+) -> Vec<Utf8PathBuf> {
+    let inherited = root
+        .workspace
+        .as_ref()
+        .and_then(|w| w.dependencies.as_ref());
+    let tables = [
+        &manifest.dependencies,
+        &manifest.dev_dependencies,
+        &manifest.build_dependencies,
+    ]
+    .into_iter()
+    .chain(manifest.target.iter().flat_map(|t| {
+        t.values()
+            .flat_map(|s| [&s.dependencies, &s.dev_dependencies, &s.build_dependencies])
+    }));
+
+    // Paths are relative to their `base`, if any. Only the built-in
+    // `workspace` base is known without reading `.cargo/config.toml`.
+    let path = |detail: &DependencyDetail, dir: &Utf8Path| {
+        let dir = match detail.base.as_ref().map(|b| b.value.as_str()) {
+            None => dir,
+            Some("workspace") => root_dir,
+            Some(_) => return None,
+        };
+        detail.path.as_ref().map(|p| dir.join(&p.value))
+    };
+    let mut dirs = Vec::new();
+    for (name, dep) in tables.flatten().flatten() {
+        let path = match dep {
+            Dependency::Detailed(detail) => path(detail, member_dir),
+            Dependency::Workspace(_) => match inherited.and_then(|deps| deps.get(name)) {
+                Some(Dependency::Detailed(detail)) => path(detail, root_dir),
+                _ => None,
+            },
+            Dependency::Version(_) => None,
+        };
+        dirs.extend(path.map(|p| normalize(&p)));
+    }
+    dirs
+}
+
+/// Expand `workspace.members`-style patterns relative to `root_dir`.
+///
+/// Literal entries are returned as-is; entries containing `*` or `?` only yield
+/// directories that contain a `Cargo.toml`.
+fn expand_paths(
+    root_dir: &Utf8Path,
+    patterns: Option<&Vec<String>>,
+) -> Result<Vec<Utf8PathBuf>, Error> {
+    let mut dirs = Vec::new();
+    for pattern in patterns.into_iter().flatten() {
+        if !is_glob(pattern) {
+            dirs.push(normalize(&root_dir.join(pattern)));
+            continue;
+        }
+
+        let mut candidates = vec![root_dir.to_owned()];
+        for component in Utf8Path::new(pattern).components() {
+            let component = component.as_str();
+            if !is_glob(component) {
+                candidates = candidates.into_iter().map(|c| c.join(component)).collect();
+                continue;
+            }
+            let mut next = Vec::new();
+            for dir in candidates.iter().filter(|c| c.is_dir()) {
+                let entries = dir.read_dir_utf8().map_err(|source| Error::Io {
+                    path: dir.clone(),
+                    source: crate::IoError::from(source),
+                })?;
+                for entry in entries.flatten() {
+                    if glob_match(component, entry.file_name()) && entry.path().is_dir() {
+                        next.push(entry.path().to_owned());
+                    }
+                }
+            }
+            candidates = next;
+        }
+
+        candidates.sort();
+        dirs.extend(
+            candidates
+                .into_iter()
+                .filter(|c| c.join("Cargo.toml").is_file())
+                .map(|c| normalize(&c)),
+        );
+    }
+    Ok(dirs)
+}
+
+fn is_glob(pattern: &str) -> bool {
+    pattern.contains(['*', '?'])
+}
+
+/// Match a single path component against a pattern with `*` and `?` wildcards.
+fn glob_match(pattern: &str, name: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let name: Vec<char> = name.chars().collect();
+    let (mut p, mut n) = (0, 0);
+    let mut backtrack = None;
+
+    while n < name.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                backtrack = Some((p, n));
+                p += 1;
+            }
+            Some(&c) if c == '?' || c == name[n] => {
+                p += 1;
+                n += 1;
+            }
+            _ => match backtrack {
+                Some((bp, bn)) => {
+                    p = bp + 1;
+                    n = bn + 1;
+                    backtrack = Some((bp, bn + 1));
+                }
+                None => return false,
+            },
+        }
+    }
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+fn parent_dir(manifest_path: &Utf8Path) -> Utf8PathBuf {
+    manifest_path.parent().unwrap_or(manifest_path).to_owned()
+}
+
+fn absolute(path: &Utf8Path) -> Result<Utf8PathBuf, Error> {
+    let io_error = |source| Error::Io {
+        path: path.to_owned(),
+        source: crate::IoError::from(source),
+    };
+    let absolute = std::path::absolute(path).map_err(io_error)?;
+    let absolute = Utf8PathBuf::from_path_buf(absolute).map_err(|_| {
+        io_error(std::io::Error::new(
+            std::io::ErrorKind::InvalidData,
+            "path is not valid UTF-8",
+        ))
+    })?;
+    Ok(normalize(&absolute))
+}
+
+/// Lexically resolve `.` and `..` components, keeping the leading `..` of a
+/// relative path.
+pub(crate) fn normalize(path: &Utf8Path) -> Utf8PathBuf {
+    let mut normalized = Utf8PathBuf::new();
+    for component in path.components() {
+        match component {
+            Utf8Component::CurDir => {}
+            Utf8Component::ParentDir => match normalized.components().next_back() {
+                Some(Utf8Component::Normal(_)) => {
+                    normalized.pop();
+                }
+                None | Some(Utf8Component::ParentDir) => normalized.push(".."),
+                Some(_) => {}
+            },
+            other => normalized.push(other),
+        }
+    }
+    normalized
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testdir::TestDir;
+
+    #[test]
+    fn glob_components() {
+        assert!(glob_match("*", "foo"));
+        assert!(glob_match("facet-*", "facet-toml"));
+        assert!(!glob_match("facet-*", "serde"));
+        assert!(glob_match("a?c", "abc"));
+        assert!(glob_match("*-e2e", "lexi-e2e"));
+        assert!(!glob_match("*-e2e", "lexi-e2e-old"));
+    }
+
+    #[test]
+    fn discover_members() {
+        let dir = TestDir::new("ws");
+        dir.write(
+            "Cargo.toml",
+            r#"
+[workspace]
+members = ["crates/*"]
+exclude = ["crates/skipped"]
+default-members = ["crates/a"]
+"#,
+        );
+        dir.write(
+            "crates/a/Cargo.toml",
+            "[package]\nname = \"a\"\n[dependencies]\nb = { path = \"../../extra/b\" }\n",
+        );
+        dir.write("crates/a/src/lib.rs", "");
+        dir.write(
+            "crates/skipped/Cargo.toml",
+            "[package]\nname = \"skipped\"\n",
+        );
+        dir.write("crates/not-a-crate/README.md", "");
+        dir.write(
+            "extra/b/Cargo.toml",
+            "[package]\nname = \"b\"\n[patch.crates-io]\nlog = { path = \"../log\" }\n[profile.release]\nlto = true\n",
+        );
+
+        let workspace = CargoWorkspace::discover(dir.path().join("crates/a/src")).unwrap();
+        let from_file = CargoWorkspace::discover(dir.path().join("crates/a/src/lib.rs")).unwrap();
+        assert_eq!(from_file.root_manifest, workspace.root_manifest);
+        let members: Vec<_> = workspace
+            .members
+            .keys()
+            .map(|p| p.strip_prefix(dir.path()).unwrap().as_str())
+            .collect();
+        assert_eq!(members, ["crates/a/Cargo.toml", "extra/b/Cargo.toml"]);
+
+        let defaults: Vec<_> = workspace
+            .default_members()
+            .unwrap()
+            .map(|(p, _)| p)
+            .collect();
+        assert_eq!(defaults, [&dir.path().join("crates/a/Cargo.toml")]);
+
+        let ignored: Vec<_> = workspace
+            .ignored_member_settings()
+            .into_iter()
+            .map(|(path, issue)| (path.strip_prefix(dir.path()).unwrap().as_str(), issue.key))
+            .collect();
+        assert_eq!(
+            ignored,
+            [
+                ("extra/b/Cargo.toml", "patch".to_owned()),
+                ("extra/b/Cargo.toml", "profile".to_owned())
+            ]
+        );
+    }
+
+    #[test]
+    fn package_workspace_key() {
+        let dir = TestDir::new("ws-key");
+        dir.write(
+            "outer/Cargo.toml",
+            "[workspace]\nmembers = [\"../member\"]\n",
+        );
+        dir.write(
+            "member/Cargo.toml",
+            "[package]\nname = \"member\"\nworkspace = \"../outer\"\n",
+        );
+        dir.write(
+            "stray/Cargo.toml",
+            "[package]\nname = \"stray\"\nworkspace = \"../member\"\n",
+        );
+
+        let workspace = CargoWorkspace::discover(dir.path().join("member")).unwrap();
+        assert_eq!(workspace.root_manifest, dir.path().join("outer/Cargo.toml"));
+        assert!(
+            workspace
+                .members
+                .contains_key(&dir.path().join("member/Cargo.toml"))
+        );
+
+        match CargoWorkspace::discover(dir.path().join("stray")) {
+            Err(Error::NotAWorkspaceRoot { path, member }) => {
+                assert_eq!(path, dir.path().join("member/Cargo.toml"));
+                assert_eq!(member, dir.path().join("stray/Cargo.toml"));
+            }
+            other => panic!("expected NotAWorkspaceRoot, got {other:?}"),
+        }
+    }
+}
@@ -12,6 +12,22 @@ fn parse_lockfile(path: &Path) -> datatest_stable::Result<()> {
         }
     };
 
+    for package in &lockfile.packages {
+        if let Err(e) = lockfile.dependencies_of(package) {
+            panic!("{} {}: {e}", package.name, package.version);
+        }
+        if let Err(e) = package.semver_version() {
+            panic!("{}: {e}", package.name);
+        }
+        if let Err(e) = package.dependency_source() {
+            panic!("{}: {e}", package.name);
+        }
+    }
+    match lockfile.diff(&lockfile) {
+        Ok(diff) => assert!(diff.is_empty(), "{diff}"),
+        Err(e) => panic!("diff failed: {e}"),
+    }
+
     println!(
         "  version: {}, {} packages",
         lockfile.version,
@@ -1,6 +1,6 @@
 //! Data-driven tests: parse every Cargo.toml from ~/bearcove/
 
-use facet_cargo_toml::CargoToml;
+use facet_cargo_toml::{CargoToml, ProfileUnit, Severity};
 use std::path::Path;
 
 fn parse_manifest(path: &Path) -> datatest_stable::Result<()> {
@@ -20,6 +20,38 @@ fn parse_manifest(path: &Path) -> datatest_stable::Result<()> {
         println!("  workspace manifest");
     }
 
+    for declared in manifest.all_dependencies() {
+        if let Err(e) = declared.dependency.version_req() {
+            panic!("dependency `{}`: {e}", declared.name);
+        }
+    }
+
+    if let Err(e) = manifest.feature_graph().activate(&[], true) {
+        panic!("default features: {e}");
+    }
+
+    let defined = manifest.profile.iter().flat_map(|profiles| profiles.keys());
+    for profile in ["dev", "release", "test", "bench"]
+        .into_iter()
+        .chain(defined.map(String::as_str))
+    {
+        if let Err(e) = manifest.effective_profile(profile, ProfileUnit::default()) {
+            panic!("{e}");
+        }
+    }
+
+    for issue in manifest.validate() {
+        assert_ne!(issue.severity, Severity::Error, "{issue}");
+    }
+
+    let diff = manifest.diff(&manifest);
+    assert!(diff.is_empty(), "{diff}");
+
+    let contents = std::fs::read_to_string(path)?;
+    if let Err(e) = CargoToml::parse_strict(&contents) {
+        panic!("{e}");
+    }
+
     Ok(())
 }
 
@@ -0,0 +1,16 @@
+//! Golden tests: every Cargo.lock fixture serializes back to identical bytes.
+
+use facet_cargo_toml::CargoLock;
+use std::path::Path;
+
+fn roundtrip_lockfile(path: &Path) -> datatest_stable::Result<()> {
+    let contents = std::fs::read_to_string(path)?;
+    let lockfile = CargoLock::parse(&contents)?;
+    assert_eq!(lockfile.to_toml_string(), contents);
+
+    Ok(())
+}
+
+datatest_stable::harness! {
+    { test = roundtrip_lockfile, root = "tests/fixtures-lockfile", pattern = r"^.*Cargo\.lock$" },
+}
@@ -0,0 +1,40 @@
+//! Round-trip tests: every Cargo.toml fixture serializes to TOML that parses
+//! back to the same manifest.
+
+use facet_cargo_toml::CargoToml;
+use std::path::Path;
+
+fn roundtrip_manifest(path: &Path) -> datatest_stable::Result<()> {
+    let contents = std::fs::read_to_string(path)?;
+    let mut original = CargoToml::parse(&contents)?;
+    let serialized = original.to_toml_string();
+    let mut reparsed = match CargoToml::parse(&serialized) {
+        Ok(manifest) => manifest,
+        Err(e) => panic!("serialized manifest does not parse: {e}\n{serialized}"),
+    };
+
+    // facet-value cannot serialize the flattened badge attributes, so compare
+    // them on their own.
+    let badges = |manifest: &mut CargoToml| {
+        let mut badges: Vec<_> = manifest
+            .badges
+            .take()
+            .into_iter()
+            .flatten()
+            .map(|(name, badge)| (name, badge.attributes))
+            .collect();
+        badges.sort_by(|a, b| a.0.cmp(&b.0));
+        badges
+    };
+    assert_eq!(badges(&mut reparsed), badges(&mut original));
+
+    let original = facet_value::to_value(&original)?;
+    let reparsed = facet_value::to_value(&reparsed)?;
+    assert_eq!(reparsed, original, "\n{serialized}");
+
+    Ok(())
+}
+
+datatest_stable::harness! {
+    { test = roundtrip_manifest, root = "tests/fixtures", pattern = r"^.*\.toml$" },
+}